@@ -0,0 +1,186 @@
+use crate::{parse_message, FractalMessage, MidiMessage};
+
+const SYSEX_START: u8 = 0xF0;
+const SYSEX_END: u8 = 0xF7;
+
+/// Large enough for a grid layout or preset name response, small enough that
+/// a lost `F7` doesn't grow the buffer without bound.
+pub const DEFAULT_MAX_SYSEX_LENGTH: usize = 4096;
+
+fn is_realtime(byte: u8) -> bool {
+    byte >= 0xF8
+}
+
+fn data_length(status: u8) -> usize {
+    match status {
+        0x80..=0xBF | 0xE0..=0xEF => 2,
+        0xC0..=0xDF => 1,
+        0xF1 | 0xF3 => 1,
+        0xF2 => 2,
+        _ => 0,
+    }
+}
+
+/// Reassembles complete MIDI messages from arbitrarily chunked input, as
+/// delivered by most MIDI input callbacks.
+///
+/// Real-time bytes (`F8`-`FF`) are emitted immediately as single byte
+/// messages, even when they arrive in the middle of a SysEx frame. Channel
+/// voice messages are emitted with their status byte restored when the sender
+/// uses running status. A SysEx frame that is interrupted by a new `F0` or
+/// status byte, or that grows past the maximum length, is discarded.
+#[derive(Debug)]
+pub struct SysexAssembler {
+    buffer: Vec<u8>,
+    in_sysex: bool,
+    overflowed: bool,
+    running_status: Option<u8>,
+    max_length: usize,
+    dropped_frames: usize,
+}
+
+impl Default for SysexAssembler {
+    fn default() -> Self {
+        Self::with_max_length(DEFAULT_MAX_SYSEX_LENGTH)
+    }
+}
+
+impl SysexAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_length(max_length: usize) -> Self {
+        SysexAssembler {
+            buffer: vec![],
+            in_sysex: false,
+            overflowed: false,
+            running_status: None,
+            max_length,
+            dropped_frames: 0,
+        }
+    }
+
+    /// Number of SysEx frames discarded because they were interrupted or too long.
+    pub fn dropped_frames(&self) -> usize {
+        self.dropped_frames
+    }
+
+    /// Feeds a chunk of input and returns every message completed by it.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<MidiMessage> {
+        let mut messages = vec![];
+        for &byte in bytes {
+            if let Some(msg) = self.push_byte(byte) {
+                messages.push(msg);
+            }
+        }
+        messages
+    }
+
+    /// Like `push`, but only returns the SysEx frames, decoded with `parse_message`.
+    pub fn push_fractal(&mut self, bytes: &[u8]) -> Vec<FractalMessage> {
+        self.push(bytes)
+            .into_iter()
+            .filter(|msg| msg.first() == Some(&SYSEX_START))
+            .map(parse_message)
+            .collect()
+    }
+
+    fn push_byte(&mut self, byte: u8) -> Option<MidiMessage> {
+        if is_realtime(byte) {
+            return Some(vec![byte]);
+        }
+        if self.in_sysex {
+            return self.push_sysex_byte(byte);
+        }
+        match byte {
+            SYSEX_START => {
+                self.running_status = None;
+                self.in_sysex = true;
+                self.overflowed = false;
+                self.buffer = vec![byte];
+                None
+            }
+            // A stray end of exclusive, eg. the tail of a dropped frame.
+            SYSEX_END => None,
+            0x80..=0xEF => {
+                self.running_status = Some(byte);
+                self.buffer = vec![byte];
+                self.complete_voice_message()
+            }
+            0xF1..=0xF6 => {
+                self.running_status = None;
+                self.buffer = vec![byte];
+                self.complete_voice_message()
+            }
+            _ => match self.running_status {
+                Some(status) => {
+                    if self.buffer.is_empty() {
+                        self.buffer.push(status);
+                    }
+                    self.buffer.push(byte);
+                    self.complete_voice_message()
+                }
+                // System common messages take their data bytes but leave
+                // no running status behind.
+                None if matches!(self.buffer.first(), Some(0xF1..=0xF6)) => {
+                    self.buffer.push(byte);
+                    self.complete_voice_message()
+                }
+                None => {
+                    // Data byte without a status to attach it to.
+                    self.buffer.clear();
+                    None
+                }
+            },
+        }
+    }
+
+    fn complete_voice_message(&mut self) -> Option<MidiMessage> {
+        let status = *self.buffer.first()?;
+        if self.buffer.len() > data_length(status) {
+            Some(std::mem::take(&mut self.buffer))
+        } else {
+            None
+        }
+    }
+
+    fn push_sysex_byte(&mut self, byte: u8) -> Option<MidiMessage> {
+        match byte {
+            SYSEX_END => {
+                self.in_sysex = false;
+                if self.overflowed {
+                    self.buffer.clear();
+                    return None;
+                }
+                self.buffer.push(byte);
+                Some(std::mem::take(&mut self.buffer))
+            }
+            0x80..=0xF6 => {
+                // The previous frame never finished; start over with this byte.
+                self.drop_frame();
+                self.in_sysex = false;
+                self.push_byte(byte)
+            }
+            _ => {
+                if self.overflowed {
+                    return None;
+                }
+                if self.buffer.len() + 1 >= self.max_length {
+                    self.drop_frame();
+                    self.overflowed = true;
+                    return None;
+                }
+                self.buffer.push(byte);
+                None
+            }
+        }
+    }
+
+    fn drop_frame(&mut self) {
+        if !self.overflowed {
+            self.dropped_frames += 1;
+        }
+        self.buffer.clear();
+    }
+}
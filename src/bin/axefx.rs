@@ -0,0 +1,629 @@
+//! A command line front end to the message builders, for shell scripts and
+//! quick checks.
+//!
+//! Without `--device` the message for the command is written out as hex or
+//! as a raw `.syx` file; with it the message is sent to a raw MIDI device
+//! file, eg. `/dev/snd/midiC1D0`, and the reply is printed.
+//!
+//! `axefx decode` goes the other way, taking messages apart with `describe`.
+
+use axe_fx_midi::tempo::{MAX_TEMPO, MIN_TEMPO};
+use axe_fx_midi::*;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+const USAGE: &str = "\
+usage: axefx --model MODEL [--emit hex|syx] [--out FILE] [--device PATH] [--timeout MS] COMMAND
+       axefx decode [FILE...]
+
+commands:
+    preset get                 current preset number
+    preset set N               select preset N
+    preset name [N]            name of the current preset, or of preset N
+    preset rename [N] NAME     rename the current preset, or preset N (N is
+                               required offline on the III, FM3 and FM9)
+    scene get                  current scene (1-8)
+    scene set N                select scene N (1-8)
+    scene name [N]             name of the current scene, or of scene N
+    tempo get                  current tempo
+    tempo set BPM              set the tempo (24-250)
+    tuner on|off               show or hide the tuner
+    status                     bypass state and channel of every block
+    grid                       the current preset's block grid
+    store N                    store the current preset in slot N
+
+decode reads .syx files, Standard MIDI Files or hex text, from stdin when no FILE is given, and
+explains every message byte by byte.
+
+MODEL is one of II, III, FM3 or FM9. Without --device the message is written
+to stdout, or to FILE with --out: as hex by default, as raw bytes with
+--emit syx.";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Emit {
+    Hex,
+    Syx,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Options {
+    model: FractalModel,
+    emit: Emit,
+    out: Option<PathBuf>,
+    device: Option<PathBuf>,
+    timeout: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Command {
+    PresetGet,
+    PresetSet(u32),
+    PresetName(Option<u32>),
+    PresetRename(Option<u32>, String),
+    SceneGet,
+    /// Scenes are numbered 1–8 as on the front panel.
+    SceneSet(u8),
+    SceneName(Option<u8>),
+    TempoGet,
+    TempoSet(u32),
+    Tuner(TunerStatus),
+    Status,
+    Grid,
+    Store(u32),
+}
+
+fn parse_model(name: &str) -> Option<FractalModel> {
+    guess_model(name).or_else(|| match name.to_ascii_uppercase().as_str() {
+        "II" | "AXE-FX II" | "AXEFX2" => Some(FractalModel::II),
+        "III" | "AXE-FX III" | "AXEFX3" => Some(FractalModel::III),
+        "FM3" => Some(FractalModel::FM3),
+        "FM9" => Some(FractalModel::FM9),
+        _ => None,
+    })
+}
+
+fn number<T: std::str::FromStr>(arg: &str, what: &str) -> Result<T, String> {
+    arg.parse()
+        .map_err(|_| format!("invalid {}: {}", what, arg))
+}
+
+fn scene(arg: &str) -> Result<u8, String> {
+    match number(arg, "scene")? {
+        n @ 1..=8 => Ok(n),
+        _ => Err(format!("scene must be 1-8: {}", arg)),
+    }
+}
+
+fn tempo(arg: &str) -> Result<u32, String> {
+    match number(arg, "tempo")? {
+        bpm if (MIN_TEMPO..=MAX_TEMPO).contains(&(bpm as f64)) => Ok(bpm),
+        _ => Err(format!(
+            "tempo must be {}-{}: {}",
+            MIN_TEMPO, MAX_TEMPO, arg
+        )),
+    }
+}
+
+fn preset_name(arg: &str) -> Result<String, String> {
+    if arg.chars().count() > 32 {
+        return Err(format!("preset names are at most 32 characters: {}", arg));
+    }
+    Ok(arg.to_string())
+}
+
+fn parse_command(args: &[&str]) -> Result<Command, String> {
+    Ok(match args {
+        ["preset", "get"] => Command::PresetGet,
+        ["preset", "set", n] => Command::PresetSet(number(n, "preset")?),
+        ["preset", "name"] => Command::PresetName(None),
+        ["preset", "name", n] => Command::PresetName(Some(number(n, "preset")?)),
+        ["preset", "rename", name] => Command::PresetRename(None, preset_name(name)?),
+        ["preset", "rename", n, name] => {
+            Command::PresetRename(Some(number(n, "preset")?), preset_name(name)?)
+        }
+        ["scene", "get"] => Command::SceneGet,
+        ["scene", "set", n] => Command::SceneSet(scene(n)?),
+        ["scene", "name"] => Command::SceneName(None),
+        ["scene", "name", n] => Command::SceneName(Some(scene(n)?)),
+        ["tempo", "get"] => Command::TempoGet,
+        ["tempo", "set", bpm] => Command::TempoSet(tempo(bpm)?),
+        ["tuner", "on"] => Command::Tuner(TunerStatus::On),
+        ["tuner", "off"] => Command::Tuner(TunerStatus::Off),
+        ["status"] => Command::Status,
+        ["grid"] => Command::Grid,
+        ["store", n] => Command::Store(number(n, "preset")?),
+        [] => return Err(String::from("missing command")),
+        _ => return Err(format!("unknown command: {}", args.join(" "))),
+    })
+}
+
+fn parse_args(args: &[String]) -> Result<(Options, Command), String> {
+    let mut model = None;
+    let mut emit = Emit::Hex;
+    let mut out = None;
+    let mut device = None;
+    let mut timeout = DEFAULT_TIMEOUT;
+    let mut rest = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .map(String::as_str)
+                .ok_or(format!("{} needs a value", arg))
+        };
+        match arg.as_str() {
+            "--model" => {
+                let name = value()?;
+                model = Some(parse_model(name).ok_or(format!("unknown model: {}", name))?);
+            }
+            "--emit" => {
+                emit = match value()? {
+                    "hex" => Emit::Hex,
+                    "syx" => Emit::Syx,
+                    other => return Err(format!("--emit must be hex or syx: {}", other)),
+                }
+            }
+            "--out" => out = Some(PathBuf::from(value()?)),
+            "--device" => device = Some(PathBuf::from(value()?)),
+            "--timeout" => timeout = Duration::from_millis(number(value()?, "timeout")?),
+            _ => rest.push(arg.as_str()),
+        }
+    }
+    let command = parse_command(&rest)?;
+    let options = Options {
+        model: model.ok_or("--model is required")?,
+        emit,
+        out,
+        device,
+        timeout,
+    };
+    Ok((options, command))
+}
+
+/// The message that carries out `command`.
+fn message(model: FractalModel, command: &Command) -> Result<MidiMessage, String> {
+    let iii = model.uses_iii_protocol();
+    let iii_only = |msg| {
+        if iii {
+            Ok(msg)
+        } else {
+            Err(format!("not supported by the {:?}", model))
+        }
+    };
+    Ok(match command {
+        Command::PresetGet if iii => get_current_preset_name(model),
+        Command::PresetGet => get_preset_number(model),
+        Command::PresetSet(n) => set_preset_number(model, *n),
+        Command::PresetName(None) => get_current_preset_name(model),
+        // On the II this selects the preset and then asks for its name.
+        Command::PresetName(Some(n)) => get_preset_name(model, *n).concat(),
+        // The III has no "current preset" rename, and offline there is no
+        // device to ask which preset is loaded.
+        Command::PresetRename(None, _) if iii => {
+            return Err(format!(
+                "the {:?} needs the preset number: preset rename N NAME",
+                model
+            ))
+        }
+        Command::PresetRename(None, name) => set_current_preset_name(model, name),
+        Command::PresetRename(Some(n), name) => iii_only(set_preset_name(model, *n, name))?,
+        // An out of range scene selects nothing, and the reply names the
+        // current one.
+        Command::SceneGet => set_scene_number(model, 0x7F),
+        Command::SceneSet(n) => set_scene_number(model, n - 1),
+        Command::SceneName(None) => iii_only(get_current_scene_name(model))?,
+        Command::SceneName(Some(n)) => iii_only(get_scene_name(model, n - 1))?,
+        Command::TempoGet => iii_only(get_tempo(model))?,
+        Command::TempoSet(bpm) => set_tempo(model, *bpm),
+        Command::Tuner(status) => toggle_tuner_sysex(model, *status),
+        Command::Status => iii_only(status_dump(model))?,
+        Command::Grid => get_grid_layout_and_routing(model),
+        Command::Store(n) => store_in_preset(model, *n),
+    })
+}
+
+fn hex(msg: &[u8]) -> String {
+    let bytes: Vec<String> = msg.iter().map(|b| format!("{:02X}", b)).collect();
+    bytes.join(" ") + "\n"
+}
+
+fn emit(options: &Options, msg: &[u8]) -> io::Result<()> {
+    let bytes = match options.emit {
+        Emit::Hex => hex(msg).into_bytes(),
+        Emit::Syx => msg.to_vec(),
+    };
+    match &options.out {
+        Some(path) => File::create(path)?.write_all(&bytes),
+        None => io::stdout().write_all(&bytes),
+    }
+}
+
+/// The device's scene number as shown on the front panel. The III counts
+/// from 0, the II's reply is already counted from 1.
+fn display_scene(model: FractalModel, scene: u8) -> u8 {
+    if model.uses_iii_protocol() {
+        scene + 1
+    } else {
+        scene
+    }
+}
+
+fn acknowledged(function_id: u8) -> impl FnMut(&FractalMessage) -> Option<()> {
+    move |message| match message {
+        FractalMessage::MultipurposeResponse {
+            function_id: id,
+            response_code: 0,
+        } if *id == function_id => Some(()),
+        _ => None,
+    }
+}
+
+/// Carries out `command` over `session` and returns what to print.
+fn run<T: MidiTransport>(
+    session: &mut Session<T>,
+    command: &Command,
+) -> Result<String, SessionError> {
+    let model = session.model();
+    let iii = model.uses_iii_protocol();
+    if let (Command::PresetRename(n, name), true) = (command, iii) {
+        // The III has no "current preset" rename, so look the number up.
+        let n = match n {
+            Some(n) => *n,
+            None => session.current_preset_number()?,
+        };
+        session.request(&set_preset_name(model, n, name), acknowledged(0x01))?;
+        return Ok(String::new());
+    }
+    let msg = message(model, command).map_err(|_| SessionError::Unsupported)?;
+    Ok(match command {
+        Command::PresetGet => session.current_preset_number()?.to_string(),
+        Command::PresetSet(_) => session.request(&msg, |message| match message {
+            FractalMessage::PresetName(n, name) => Some(format!("{} {}", n, name)),
+            FractalMessage::CurrentPresetNumber(n) => Some(n.to_string()),
+            _ => None,
+        })?,
+        Command::PresetName(None) => session.current_preset_name()?,
+        Command::PresetName(Some(n)) => session.preset_name(*n)?,
+        Command::PresetRename(..) => {
+            session.request(&msg, acknowledged(0x09))?;
+            String::new()
+        }
+        Command::SceneGet | Command::SceneSet(_) => {
+            let scene = session.request(&msg, |message| match message {
+                FractalMessage::CurrentSceneNumber(n) => Some(*n),
+                _ => None,
+            })?;
+            display_scene(model, scene).to_string()
+        }
+        Command::SceneName(None) => session.current_scene_name()?,
+        Command::SceneName(Some(n)) => session.scene_name(n - 1)?,
+        Command::TempoGet => session.tempo()?.to_string(),
+        Command::TempoSet(_) if iii => session
+            .request(&msg, |message| match message {
+                FractalMessage::CurrentTempo(tempo) => Some(*tempo),
+                _ => None,
+            })?
+            .to_string(),
+        Command::TempoSet(_) => {
+            session.request(&msg, acknowledged(0x14))?;
+            String::new()
+        }
+        Command::Tuner(_) => {
+            let status = session.request(&msg, |message| match message {
+                FractalMessage::TunerStatus(status) => Some(*status),
+                _ => None,
+            })?;
+            format!("{:?}", status).to_lowercase()
+        }
+        Command::Status => {
+            let lines: Vec<String> = session
+                .status()?
+                .iter()
+                .map(|status| {
+                    format!(
+                        "{:?} {:?}{}",
+                        status.effect,
+                        status.channel,
+                        if status.bypassed { " bypassed" } else { "" }
+                    )
+                })
+                .collect();
+            lines.join("\n")
+        }
+        Command::Grid => {
+            let text = session.grid()?.routing().to_text();
+            text.trim_end().to_string()
+        }
+        Command::Store(_) => {
+            session.request(&msg, acknowledged(if iii { 0x01 } else { 0x1D }))?;
+            String::new()
+        }
+    })
+}
+
+/// Raw SysEx when it contains an `F0`, hex text otherwise.
+fn read_messages(contents: &[u8]) -> Result<Vec<u8>, String> {
+    if contents.contains(&0xF0) {
+        return Ok(contents.to_vec());
+    }
+    let text = std::str::from_utf8(contents).map_err(|_| "neither SysEx nor hex text")?;
+    parse_hex(text).map_err(|err| err.to_string())
+}
+
+fn decode(files: &[String]) -> Result<String, String> {
+    let inputs = if files.is_empty() {
+        let mut contents = vec![];
+        io::stdin()
+            .read_to_end(&mut contents)
+            .map_err(|err| err.to_string())?;
+        vec![contents]
+    } else {
+        files
+            .iter()
+            .map(|file| std::fs::read(file).map_err(|err| format!("{}: {}", file, err)))
+            .collect::<Result<_, _>>()?
+    };
+    let mut output = String::new();
+    for contents in inputs {
+        if contents.starts_with(b"MThd") {
+            // A Standard MIDI File: each message with its time and track.
+            for captured in capture::read_smf(&contents).map_err(|err| err.to_string())? {
+                let time = captured.time.unwrap_or_default().as_secs_f64();
+                output.push_str(&format!("{:.3}s track {}: ", time, captured.track));
+                for description in describe(&captured.bytes) {
+                    output.push_str(&description.to_string());
+                }
+            }
+            continue;
+        }
+        for description in describe(&read_messages(&contents)?) {
+            output.push_str(&description.to_string());
+        }
+    }
+    Ok(output)
+}
+
+/// A raw MIDI device file, as ALSA exposes under `/dev/snd`. A thread reads
+/// it so that `receive` can give up after a timeout.
+struct RawMidi {
+    device: File,
+    incoming: Receiver<Vec<u8>>,
+}
+
+impl RawMidi {
+    fn open(path: &PathBuf) -> io::Result<Self> {
+        let device = OpenOptions::new().read(true).write(true).open(path)?;
+        let mut reader = device.try_clone()?;
+        let (sender, incoming) = mpsc::channel();
+        thread::spawn(move || {
+            let mut buffer = [0; 1024];
+            while let Ok(n @ 1..=1024) = reader.read(&mut buffer) {
+                if sender.send(buffer[..n].to_vec()).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(RawMidi { device, incoming })
+    }
+}
+
+impl MidiTransport for RawMidi {
+    fn send(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.device.write_all(bytes)?;
+        self.device.flush()
+    }
+
+    fn receive(&mut self, timeout: Duration) -> io::Result<Vec<u8>> {
+        match self.incoming.recv_timeout(timeout) {
+            Ok(bytes) => Ok(bytes),
+            Err(RecvTimeoutError::Timeout) => Ok(vec![]),
+            Err(RecvTimeoutError::Disconnected) => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "MIDI device closed",
+            )),
+        }
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+    if args.first().map(String::as_str) == Some("decode") {
+        match decode(&args[1..]) {
+            Ok(output) => print!("{}", output),
+            Err(err) => {
+                eprintln!("axefx: {}", err);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+    let (options, command) = parse_args(&args).unwrap_or_else(|err| {
+        eprintln!("axefx: {}\n\n{}", err, USAGE);
+        process::exit(2);
+    });
+    let result = match &options.device {
+        None => message(options.model, &command)
+            .and_then(|msg| emit(&options, &msg).map_err(|err| err.to_string())),
+        Some(path) => RawMidi::open(path)
+            .map_err(|err| format!("{}: {}", path.display(), err))
+            .and_then(|device| {
+                let mut session = Session::new(device, options.model).with_timeout(options.timeout);
+                run(&mut session, &command).map_err(|err| err.to_string())
+            })
+            .map(|output| {
+                if !output.is_empty() {
+                    println!("{}", output);
+                }
+            }),
+    };
+    if let Err(err) = result {
+        eprintln!("axefx: {}", err);
+        process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axe_fx_midi::simulator::Simulator;
+    use pretty_assertions::assert_eq;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    fn simulated(model: FractalModel) -> Session<Simulator> {
+        Session::new(Simulator::new(model), model)
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let (options, command) = parse_args(&args("--model III --emit syx scene set 3")).unwrap();
+        assert_eq!(
+            Options {
+                model: FractalModel::III,
+                emit: Emit::Syx,
+                out: None,
+                device: None,
+                timeout: DEFAULT_TIMEOUT,
+            },
+            options
+        );
+        assert_eq!(Command::SceneSet(3), command);
+
+        let (options, command) =
+            parse_args(&args("preset rename 12 Clean --model FM3 --out x.syx")).unwrap();
+        assert_eq!(FractalModel::FM3, options.model);
+        assert_eq!(Some(PathBuf::from("x.syx")), options.out);
+        assert_eq!(
+            Command::PresetRename(Some(12), String::from("Clean")),
+            command
+        );
+
+        assert_eq!(
+            Err(String::from("--model is required")),
+            parse_args(&args("status"))
+        );
+        assert_eq!(
+            Err(String::from("scene must be 1-8: 9")),
+            parse_args(&args("--model III scene set 9"))
+        );
+        assert_eq!(
+            Err(String::from("tempo must be 24-250: 251")),
+            parse_args(&args("--model III tempo set 251"))
+        );
+        assert_eq!(
+            Err(String::from("tempo must be 24-250: 0")),
+            parse_args(&args("--model II tempo set 0"))
+        );
+        assert_eq!(
+            Err(String::from("unknown command: tempo tap")),
+            parse_args(&args("--model III tempo tap"))
+        );
+        assert_eq!(
+            Err(String::from("--emit must be hex or syx: bin")),
+            parse_args(&args("--model III --emit bin grid"))
+        );
+    }
+
+    #[test]
+    fn test_message() {
+        let iii = FractalModel::III;
+        assert_eq!(
+            Ok(set_scene_number(iii, 2)),
+            message(iii, &Command::SceneSet(3))
+        );
+        assert_eq!(
+            Ok(set_tempo(iii, 120)),
+            message(iii, &Command::TempoSet(120))
+        );
+        assert_eq!(
+            Ok(toggle_tuner_sysex(iii, TunerStatus::On)),
+            message(iii, &Command::Tuner(TunerStatus::On))
+        );
+        assert_eq!(
+            Ok(store_in_preset(iii, 7)),
+            message(iii, &Command::Store(7))
+        );
+        assert_eq!(
+            Ok(get_grid_layout_and_routing(iii)),
+            message(iii, &Command::Grid)
+        );
+        assert_eq!(
+            Ok(get_preset_number(FractalModel::II)),
+            message(FractalModel::II, &Command::PresetGet)
+        );
+        assert_eq!(
+            Err(String::from("not supported by the II")),
+            message(FractalModel::II, &Command::TempoGet)
+        );
+        assert_eq!(
+            Err(String::from(
+                "the III needs the preset number: preset rename N NAME"
+            )),
+            message(iii, &Command::PresetRename(None, String::from("Clean")))
+        );
+        assert_eq!(
+            Ok(set_current_preset_name(FractalModel::II, "Clean")),
+            message(
+                FractalModel::II,
+                &Command::PresetRename(None, String::from("Clean"))
+            )
+        );
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(
+            "F0 00 01 74 10 14 46 00 47 F7\n",
+            hex(&set_tempo(FractalModel::III, 70))
+        );
+    }
+
+    #[test]
+    fn test_read_messages() {
+        let msg = set_tempo(FractalModel::III, 70);
+        assert_eq!(Ok(msg.clone()), read_messages(&msg));
+        assert_eq!(Ok(msg.clone()), read_messages(hex(&msg).as_bytes()));
+        assert!(read_messages(b"F0 0").is_err());
+    }
+
+    #[test]
+    fn test_run_against_simulator() {
+        let mut session = simulated(FractalModel::III);
+        assert_eq!("4", run(&mut session, &Command::SceneSet(4)).unwrap());
+        assert_eq!("4", run(&mut session, &Command::SceneGet).unwrap());
+        assert_eq!("96", run(&mut session, &Command::TempoSet(96)).unwrap());
+        assert_eq!("96", run(&mut session, &Command::TempoGet).unwrap());
+        assert_eq!(
+            "on",
+            run(&mut session, &Command::Tuner(TunerStatus::On)).unwrap()
+        );
+        assert_eq!("", run(&mut session, &Command::Store(5)).unwrap());
+        assert_eq!(
+            "",
+            run(
+                &mut session,
+                &Command::PresetRename(None, String::from("Clean"))
+            )
+            .unwrap()
+        );
+
+        let mut session = simulated(FractalModel::II);
+        assert_eq!("2", run(&mut session, &Command::SceneSet(2)).unwrap());
+        assert_eq!("2", run(&mut session, &Command::SceneGet).unwrap());
+        assert!(matches!(
+            run(&mut session, &Command::Status),
+            Err(SessionError::Unsupported)
+        ));
+    }
+}
@@ -0,0 +1,106 @@
+//! Reading user cabinet IR `.syx` files.
+//!
+//! An IR is a header message (function `0x7A`) carrying the target user cab
+//! slot and the IR name, a run of data messages (`0x7B`) and a footer
+//! (`0x7C`). The slot is sent most significant byte first like a preset
+//! number; the name is 32 ASCII characters starting at the fifth payload
+//! byte, padded with spaces or NULs.
+//!
+//! This layout has not been checked against a cab file published by
+//! Fractal; the tests build their IRs from it, so they can't catch a mistake
+//! in it. For that reason an IR is only ever uploaded exactly as read, to the
+//! slot its own header names: nothing rewrites the slot.
+
+use crate::parse::ParseError;
+use crate::preset_dump::SequenceAssembler;
+use crate::preset_file::{parse_frame, split_messages, PresetFileError};
+use crate::{FractalModel, MidiMessage, SysexFrame};
+
+pub const CAB_IR_HEADER: u8 = 0x7A;
+pub const CAB_IR_DATA: u8 = 0x7B;
+pub const CAB_IR_FOOTER: u8 = 0x7C;
+
+const NAME_OFFSET: usize = 4;
+const NAME_LENGTH: usize = 32;
+
+/// A user cab IR read from a `.syx` file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CabFile {
+    pub model: FractalModel,
+    pub slot: u32,
+    pub name: String,
+    pub header: SysexFrame,
+    pub data: Vec<SysexFrame>,
+    pub footer: SysexFrame,
+}
+
+impl CabFile {
+    /// Parses an IR file, checking every message's checksum. Errors carry
+    /// the index and byte offset of the offending message.
+    pub fn parse(bytes: &[u8]) -> Result<Self, PresetFileError> {
+        let mut assembler = SequenceAssembler::new(CAB_IR_HEADER, CAB_IR_DATA, CAB_IR_FOOTER);
+        for (index, (offset, msg)) in split_messages(bytes)?.into_iter().enumerate() {
+            let frame = parse_frame(index, offset, msg)?;
+            if frame.function_id == CAB_IR_HEADER && frame.payload.len() < NAME_OFFSET {
+                return Err(PresetFileError::InvalidMessage {
+                    index,
+                    offset,
+                    error: ParseError::PayloadTooShort {
+                        function_id: CAB_IR_HEADER,
+                        expected: NAME_OFFSET,
+                        actual: frame.payload.len(),
+                    },
+                });
+            }
+            if let Some(sequence) = assembler.push(frame)? {
+                return Ok(CabFile::from_frames(
+                    sequence.header,
+                    sequence.data,
+                    sequence.footer,
+                ));
+            }
+        }
+        Err(PresetFileError::Incomplete)
+    }
+
+    fn from_frames(header: SysexFrame, data: Vec<SysexFrame>, footer: SysexFrame) -> Self {
+        CabFile {
+            model: header.model,
+            slot: decode_slot(&header.payload),
+            name: decode_name(&header.payload),
+            header,
+            data,
+            footer,
+        }
+    }
+
+    /// The messages of the IR, in order.
+    pub fn messages(&self) -> Vec<MidiMessage> {
+        std::iter::once(&self.header)
+            .chain(self.data.iter())
+            .chain(std::iter::once(&self.footer))
+            .map(SysexFrame::to_message)
+            .collect()
+    }
+
+    /// The file contents: every message of the IR back to back.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.messages().concat()
+    }
+}
+
+fn decode_slot(payload: &[u8]) -> u32 {
+    u32::from(payload[0]) << 7 | u32::from(payload[1])
+}
+
+fn decode_name(payload: &[u8]) -> String {
+    payload
+        .iter()
+        .skip(NAME_OFFSET)
+        .take(NAME_LENGTH)
+        .take_while(|c| **c > 0)
+        .map(|c| *c as char)
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
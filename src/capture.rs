@@ -0,0 +1,379 @@
+//! Pulling Fractal SysEx out of captures, to see what a controller actually
+//! sent.
+//!
+//! Standard MIDI Files are read in full: every track of a format 0 or 1 file
+//! is walked, SysEx split across `F0` and `F7` continuation events is joined
+//! back up, and event times are converted to wall-clock time through the
+//! file's tempo map. Raw byte logs carry no timing, so their messages come
+//! back in order without times.
+
+use crate::{parse_message, FractalMessage, MidiMessage, SysexAssembler};
+use std::fmt;
+use std::time::Duration;
+
+/// Microseconds per quarter note until the first tempo event.
+const DEFAULT_TEMPO: u64 = 500_000;
+
+const META_EVENT: u8 = 0xFF;
+const META_TEMPO: u8 = 0x51;
+const META_END_OF_TRACK: u8 = 0x2F;
+const SYSEX_START: u8 = 0xF0;
+const SYSEX_END: u8 = 0xF7;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SmfError {
+    /// The file doesn't start with an `MThd` chunk.
+    NotSmf,
+    /// Format 2 files hold independent sequences with no shared timeline.
+    UnsupportedFormat(u16),
+    /// The file ends inside the chunk or event starting at `offset`.
+    Truncated { offset: usize },
+    /// A byte at `offset` that can't start an event: a data byte with no
+    /// running status to apply it to, or a status that has no place in a
+    /// file.
+    InvalidEvent { offset: usize, byte: u8 },
+}
+
+impl fmt::Display for SmfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SmfError::NotSmf => write!(f, "not a Standard MIDI File"),
+            SmfError::UnsupportedFormat(format) => {
+                write!(f, "format {} files are not supported", format)
+            }
+            SmfError::Truncated { offset } => {
+                write!(f, "file ends inside the chunk or event at byte {}", offset)
+            }
+            SmfError::InvalidEvent { offset, byte } => {
+                write!(
+                    f,
+                    "byte 0x{:02X} at {} does not start an event",
+                    byte, offset
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SmfError {}
+
+/// A SysEx message found in a capture.
+#[derive(Debug, PartialEq)]
+pub struct CapturedMessage {
+    /// Time since the start of the file, or `None` for raw logs.
+    pub time: Option<Duration>,
+    /// The track the message was found in; always 0 for raw logs.
+    pub track: usize,
+    pub bytes: MidiMessage,
+    pub message: FractalMessage,
+}
+
+impl CapturedMessage {
+    fn new(time: Option<Duration>, track: usize, bytes: MidiMessage) -> Self {
+        CapturedMessage {
+            time,
+            track,
+            message: parse_message(bytes.clone()),
+            bytes,
+        }
+    }
+}
+
+/// Reads a capture of either kind, telling them apart by the `MThd` header.
+pub fn read_capture(bytes: &[u8]) -> Result<Vec<CapturedMessage>, SmfError> {
+    if bytes.starts_with(b"MThd") {
+        read_smf(bytes)
+    } else {
+        Ok(read_raw_log(bytes))
+    }
+}
+
+/// Every complete SysEx message in a raw byte log, in order. Anything else
+/// in the log, including interrupted SysEx, is skipped.
+pub fn read_raw_log(bytes: &[u8]) -> Vec<CapturedMessage> {
+    SysexAssembler::with_max_length(usize::MAX)
+        .push(bytes)
+        .into_iter()
+        .filter(|msg| msg.first() == Some(&SYSEX_START))
+        .map(|msg| CapturedMessage::new(None, 0, msg))
+        .collect()
+}
+
+/// How the file counts time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Division {
+    TicksPerQuarter(u64),
+    /// Ticks per second as a fraction, since 29.97 fps isn't whole.
+    Timecode {
+        numerator: u64,
+        denominator: u64,
+    },
+}
+
+impl Division {
+    fn from_bytes(a: u8, b: u8) -> Self {
+        if a & 0x80 == 0 {
+            // A zero division is malformed; one tick per quarter at least
+            // keeps the times finite.
+            return Division::TicksPerQuarter(u64::from(u16::from_be_bytes([a, b])).max(1));
+        }
+        let ticks_per_frame = u64::from(b).max(1);
+        match -i64::from(a as i8) {
+            // Drop-frame timecode.
+            29 => Division::Timecode {
+                numerator: 30_000 * ticks_per_frame,
+                denominator: 1001,
+            },
+            fps => Division::Timecode {
+                numerator: fps.max(1) as u64 * ticks_per_frame,
+                denominator: 1,
+            },
+        }
+    }
+}
+
+/// Tempo changes in tick order, as `(tick, microseconds per quarter note)`.
+struct TempoMap {
+    division: Division,
+    changes: Vec<(u64, u64)>,
+}
+
+impl TempoMap {
+    fn nanos(ticks: u64, tempo: u64, ticks_per_quarter: u64) -> u128 {
+        u128::from(ticks) * u128::from(tempo) * 1000 / u128::from(ticks_per_quarter)
+    }
+
+    fn time(&self, tick: u64) -> Duration {
+        let nanos = match self.division {
+            Division::Timecode {
+                numerator,
+                denominator,
+            } => u128::from(tick) * 1_000_000_000 * u128::from(denominator) / u128::from(numerator),
+            Division::TicksPerQuarter(ticks_per_quarter) => {
+                let mut nanos = 0;
+                let mut from = 0;
+                let mut tempo = DEFAULT_TEMPO;
+                for &(at, next) in self.changes.iter().take_while(|(at, _)| *at < tick) {
+                    nanos += Self::nanos(at - from, tempo, ticks_per_quarter);
+                    from = at;
+                    tempo = next;
+                }
+                nanos + Self::nanos(tick - from, tempo, ticks_per_quarter)
+            }
+        };
+        Duration::from_nanos(nanos as u64)
+    }
+}
+
+/// What one track contributes: SysEx messages by tick, and tempo changes.
+#[derive(Default)]
+struct Track {
+    sysex: Vec<(u64, MidiMessage)>,
+    tempos: Vec<(u64, u64)>,
+}
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn truncated(&self) -> SmfError {
+        SmfError::Truncated {
+            offset: self.position,
+        }
+    }
+
+    fn byte(&mut self) -> Result<u8, SmfError> {
+        let byte = *self
+            .bytes
+            .get(self.position)
+            .ok_or_else(|| self.truncated())?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8], SmfError> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + length)
+            .ok_or_else(|| self.truncated())?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    /// A variable-length quantity: seven bits per byte, most significant
+    /// first, with the high bit set on all but the last byte.
+    fn variable(&mut self) -> Result<u64, SmfError> {
+        let mut value = 0;
+        for _ in 0..4 {
+            let byte = self.byte()?;
+            value = value << 7 | u64::from(byte & 0x7F);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(SmfError::InvalidEvent {
+            offset: self.position - 1,
+            byte: self.bytes[self.position - 1],
+        })
+    }
+
+    fn u16(&mut self) -> Result<u16, SmfError> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> Result<u32, SmfError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+}
+
+fn channel_data_length(status: u8) -> usize {
+    match status {
+        0xC0..=0xDF => 1,
+        _ => 2,
+    }
+}
+
+/// Walks the events of one `MTrk` chunk starting at `start`, `length` bytes
+/// long.
+fn read_track(bytes: &[u8], start: usize, length: usize) -> Result<Track, SmfError> {
+    let mut cursor = Cursor {
+        bytes: &bytes[..start + length],
+        position: start,
+    };
+    let mut track = Track::default();
+    let mut tick = 0;
+    let mut running_status = None;
+    // A SysEx message still waiting for its `F7`, with the tick it began at.
+    let mut pending: Option<(u64, MidiMessage)> = None;
+    while cursor.position < start + length {
+        tick += cursor.variable()?;
+        let offset = cursor.position;
+        let first = cursor.byte()?;
+        let status = match (first, running_status) {
+            (0x80..=0xFF, _) => first,
+            (_, Some(status)) => {
+                cursor.position -= 1;
+                status
+            }
+            (byte, None) => return Err(SmfError::InvalidEvent { offset, byte }),
+        };
+        match status {
+            0x80..=0xEF => {
+                cursor.take(channel_data_length(status))?;
+                running_status = Some(status);
+            }
+            META_EVENT => {
+                running_status = None;
+                let kind = cursor.byte()?;
+                let length = cursor.variable()? as usize;
+                let data = cursor.take(length)?;
+                match kind {
+                    META_TEMPO if length == 3 => track.tempos.push((
+                        tick,
+                        u64::from(data[0]) << 16 | u64::from(data[1]) << 8 | u64::from(data[2]),
+                    )),
+                    META_END_OF_TRACK => break,
+                    _ => {}
+                }
+            }
+            SYSEX_START | SYSEX_END => {
+                running_status = None;
+                let length = cursor.variable()? as usize;
+                let data = cursor.take(length)?;
+                let message = match (status, pending.take()) {
+                    // A new message cuts off one still waiting for its end,
+                    // which is kept as it is.
+                    (SYSEX_START, unfinished) => {
+                        track.sysex.extend(unfinished);
+                        Some((tick, [&[SYSEX_START][..], data].concat()))
+                    }
+                    // Realtime bytes can't be part of a SysEx message, so
+                    // a realtime escape in the middle of one is dropped.
+                    (_, Some((began, mut message))) => {
+                        message.extend(data.iter().filter(|byte| **byte < 0xF8));
+                        Some((began, message))
+                    }
+                    // An escape holding a whole message, as some writers
+                    // store SysEx.
+                    (_, None) if data.first() == Some(&SYSEX_START) => Some((tick, data.to_vec())),
+                    // Any other escape carries realtime or other raw bytes.
+                    (_, None) => None,
+                };
+                match message {
+                    Some((began, message)) if message.last() == Some(&SYSEX_END) => {
+                        track.sysex.push((began, message))
+                    }
+                    unfinished => pending = unfinished,
+                }
+            }
+            byte => return Err(SmfError::InvalidEvent { offset, byte }),
+        }
+    }
+    track.sysex.extend(pending);
+    Ok(track)
+}
+
+/// Every SysEx message in a Standard MIDI File, decoded with
+/// `parse_message` and ordered by time.
+///
+/// A message split across several events is timed by its first. Tempo
+/// events in any track apply to all of them, as format 1 expects of the
+/// first track. Messages that never see their `F7` are still returned, and
+/// decode as `FractalMessage::Unknown`.
+pub fn read_smf(bytes: &[u8]) -> Result<Vec<CapturedMessage>, SmfError> {
+    let mut cursor = Cursor { bytes, position: 0 };
+    if !bytes.starts_with(b"MThd") {
+        return Err(SmfError::NotSmf);
+    }
+    cursor.position = 4;
+    let header_length = cursor.u32()? as usize;
+    let header_start = cursor.position;
+    let format = cursor.u16()?;
+    if format > 1 {
+        return Err(SmfError::UnsupportedFormat(format));
+    }
+    cursor.u16()?;
+    let division = cursor.take(2)?;
+    let division = Division::from_bytes(division[0], division[1]);
+    cursor.position = header_start;
+    cursor.take(header_length)?;
+
+    let mut tracks = vec![];
+    while cursor.position < bytes.len() {
+        let chunk_start = cursor.position;
+        let kind = cursor.take(4)?;
+        let length = cursor.u32()? as usize;
+        let start = cursor.position;
+        cursor.take(length).map_err(|_| SmfError::Truncated {
+            offset: chunk_start,
+        })?;
+        // Chunks of other types are allowed and meant to be skipped.
+        if kind == b"MTrk" {
+            tracks.push(read_track(bytes, start, length)?);
+        }
+    }
+
+    let mut changes: Vec<(u64, u64)> = tracks
+        .iter()
+        .flat_map(|track| track.tempos.iter().copied())
+        .collect();
+    changes.sort_by_key(|(tick, _)| *tick);
+    let tempo_map = TempoMap { division, changes };
+    let mut messages: Vec<(u64, CapturedMessage)> = tracks
+        .into_iter()
+        .enumerate()
+        .flat_map(|(index, track)| {
+            let tempo_map = &tempo_map;
+            track.sysex.into_iter().map(move |(tick, bytes)| {
+                let time = Some(tempo_map.time(tick));
+                (tick, CapturedMessage::new(time, index, bytes))
+            })
+        })
+        .collect();
+    messages.sort_by_key(|(tick, message)| (*tick, message.track));
+    Ok(messages.into_iter().map(|(_, message)| message).collect())
+}
@@ -0,0 +1,833 @@
+//! Taking SysEx messages apart byte by byte, for debugging.
+//!
+//! `describe` splits a byte stream into messages and explains each one: the
+//! model, the function, every payload field with what it decodes to, and the
+//! checksum. Bytes that no field accounts for are kept as unexplained fields
+//! rather than skipped, so nothing in a capture goes unnoticed.
+
+use crate::frame::{carries_checksum, SYSEX_HEADER};
+use crate::parameters::parameter_for;
+use crate::parse::{
+    decode_effect_id, decode_parameter_value, decode_preset_blocks_flags, decode_preset_name,
+    decode_preset_number, Channel, XYState,
+};
+use crate::preset_dump::{decode_dump_target, PresetTarget};
+use crate::registry::EffectRegistry;
+use crate::{checksum, decode_preset_name_iii, FractalModel, Grid, TunerConfig, TunerReading};
+use num_traits::FromPrimitive;
+use std::fmt;
+
+/// Header, model and function ID.
+const PAYLOAD_OFFSET: usize = 6;
+
+/// Where the display label starts in a block parameter response.
+const PARAMETER_LABEL_OFFSET: usize = 12;
+
+/// Byte runs longer than this are shortened when displayed.
+const SHOWN_BYTES: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    /// Sent to the device.
+    Request,
+    /// Sent by the device.
+    Response,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ChecksumStatus {
+    Ok,
+    Mismatch {
+        expected: u8,
+        actual: u8,
+    },
+    /// The function carries a checksum, but the message ended before it.
+    Missing,
+    /// The function is sent without a checksum.
+    NotSent,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    /// Position of the first byte in the message.
+    pub offset: usize,
+    pub bytes: Vec<u8>,
+    pub name: &'static str,
+    /// What the bytes decode to, or `None` for bytes nothing explains.
+    pub meaning: Option<String>,
+}
+
+/// One message taken apart.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Description {
+    pub message: Vec<u8>,
+    pub model: Option<FractalModel>,
+    pub function_id: Option<u8>,
+    pub function: &'static str,
+    /// `None` when requests and responses share the layout, eg. a scene
+    /// number that was either selected or reported.
+    pub direction: Option<Direction>,
+    pub fields: Vec<Field>,
+    pub checksum: ChecksumStatus,
+    /// Anything malformed: a missing terminator, a short payload and so on.
+    pub problems: Vec<String>,
+}
+
+impl Description {
+    pub fn unexplained(&self) -> impl Iterator<Item = &Field> {
+        self.fields.iter().filter(|field| field.meaning.is_none())
+    }
+
+    /// Whether every byte is explained, the checksum holds and nothing is
+    /// malformed.
+    pub fn is_clean(&self) -> bool {
+        self.problems.is_empty()
+            && self.unexplained().next().is_none()
+            && matches!(self.checksum, ChecksumStatus::Ok | ChecksumStatus::NotSent)
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    let shown: Vec<String> = bytes
+        .iter()
+        .take(SHOWN_BYTES)
+        .map(|b| format!("{:02X}", b))
+        .collect();
+    if bytes.len() > SHOWN_BYTES {
+        format!("{} … ({} bytes)", shown.join(" "), bytes.len())
+    } else {
+        shown.join(" ")
+    }
+}
+
+impl fmt::Display for Description {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.model, self.function_id) {
+            (Some(model), Some(function_id)) => {
+                write!(f, "{:?} 0x{:02X} {}", model, function_id, self.function)?
+            }
+            _ => write!(f, "{}", self.function)?,
+        }
+        match self.direction {
+            Some(Direction::Request) => writeln!(f, " (request)")?,
+            Some(Direction::Response) => writeln!(f, " (response)")?,
+            None => writeln!(f)?,
+        }
+        for field in &self.fields {
+            write!(f, "  {:>3}  {:<24} ", field.offset, hex(&field.bytes))?;
+            match &field.meaning {
+                Some(meaning) => writeln!(f, "{}: {}", field.name, meaning)?,
+                None => writeln!(f, "?? {}", field.name)?,
+            }
+        }
+        for problem in &self.problems {
+            writeln!(f, "  !! {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+/// Hex text that isn't a byte.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HexError {
+    /// 1-based.
+    pub line: usize,
+    pub token: String,
+}
+
+impl fmt::Display for HexError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {:?} is not a hex byte", self.line, self.token)
+    }
+}
+
+impl std::error::Error for HexError {}
+
+/// Reads bytes written as hex: plain (`F0 00 01 74`), with `0x` prefixes and
+/// commas, run together (`F0000174`), or pasted from `hexdump -C` or `xxd`
+/// with the offset and text columns still attached. `//` and `#` comment
+/// markers at the start of a line are ignored.
+pub fn parse_hex(text: &str) -> Result<Vec<u8>, HexError> {
+    let mut bytes = vec![];
+    for (number, line) in text.lines().enumerate() {
+        let mut line = line.trim_start();
+        for marker in &["//", "#"] {
+            line = line.trim_start_matches(marker).trim_start();
+        }
+        // Both dump formats start with an offset: xxd ends it with a colon
+        // and separates its text column by two spaces, hexdump -C puts its
+        // text column between bars.
+        let mut skip_offset = false;
+        if let Some(bar) = line.find('|') {
+            line = &line[..bar];
+            skip_offset = true;
+        } else if let Some(colon) = line.find(':') {
+            line = line[colon + 1..].trim_start();
+            if let Some(end) = line.find("  ") {
+                line = &line[..end];
+            }
+        }
+        let tokens = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|token| !token.is_empty())
+            .skip(skip_offset as usize);
+        for token in tokens {
+            let digits = token
+                .trim_start_matches("0x")
+                .trim_start_matches("0X")
+                .trim_end_matches('h');
+            let invalid = || HexError {
+                line: number + 1,
+                token: token.to_string(),
+            };
+            if digits.is_empty() || digits.len() % 2 != 0 {
+                return Err(invalid());
+            }
+            for pair in digits.as_bytes().chunks(2) {
+                let pair = std::str::from_utf8(pair).map_err(|_| invalid())?;
+                bytes.push(u8::from_str_radix(pair, 16).map_err(|_| invalid())?);
+            }
+        }
+    }
+    Ok(bytes)
+}
+
+/// Splits `bytes` into messages and describes each. A message runs from
+/// `F0` to the next `F7`, or is cut off by the next `F0`; bytes between
+/// messages get a description of their own.
+pub fn describe(bytes: &[u8]) -> Vec<Description> {
+    let mut descriptions = vec![];
+    let mut start = 0;
+    while start < bytes.len() {
+        let end = if bytes[start] == 0xF0 {
+            match bytes[start + 1..]
+                .iter()
+                .position(|b| *b == 0xF7 || *b == 0xF0)
+            {
+                Some(i) if bytes[start + 1 + i] == 0xF7 => start + i + 2,
+                Some(i) => start + i + 1,
+                None => bytes.len(),
+            }
+        } else {
+            bytes[start..]
+                .iter()
+                .position(|b| *b == 0xF0)
+                .map_or(bytes.len(), |i| start + i)
+        };
+        let chunk = &bytes[start..end];
+        descriptions.push(if chunk[0] == 0xF0 {
+            describe_message(chunk)
+        } else {
+            stray(chunk, "bytes outside a SysEx message")
+        });
+        start = end;
+    }
+    descriptions
+}
+
+fn stray(bytes: &[u8], function: &'static str) -> Description {
+    Description {
+        message: bytes.to_vec(),
+        model: None,
+        function_id: None,
+        function,
+        direction: None,
+        fields: vec![Field {
+            offset: 0,
+            bytes: bytes.to_vec(),
+            name: "unexplained",
+            meaning: None,
+        }],
+        checksum: ChecksumStatus::NotSent,
+        problems: vec![],
+    }
+}
+
+/// The name of a function, whichever way it is sent.
+pub fn function_name(model: FractalModel, function_id: u8) -> &'static str {
+    let iii = model.uses_iii_protocol();
+    match function_id {
+        0x01 if iii => "block parameter / editor command",
+        0x01 => "block parameter",
+        0x02 => "set block parameter",
+        0x03 => "request preset dump",
+        0x08 => "firmware version",
+        0x09 if !iii => "rename current preset",
+        0x0A if iii => "block bypass",
+        0x0B if iii => "block channel",
+        0x0C if iii => "scene number",
+        0x0D if iii => "preset name",
+        0x0D => "tuner info",
+        0x0E if iii => "scene name",
+        0x0E => "preset blocks flags",
+        0x0F if iii => "looper",
+        0x0F => "preset name",
+        0x10 => "tempo beat",
+        0x11 => "tuner",
+        0x13 if iii => "status dump",
+        0x14 if iii => "tempo",
+        0x14 => "preset number",
+        0x17 => "MIDI channel",
+        0x1D if !iii => "store preset",
+        0x20 => "block grid",
+        0x21 => "front panel change",
+        0x29 if !iii => "scene number",
+        0x3C => "set preset number",
+        0x42 => "disconnect from controller",
+        0x64 => "multipurpose response",
+        0x77 => "preset dump header",
+        0x78 => "preset dump data",
+        0x79 => "preset dump footer",
+        0x7A => "cab IR header",
+        0x7B => "cab IR data",
+        0x7C => "cab IR footer",
+        _ => "unknown function",
+    }
+}
+
+/// Walks a payload field by field. Whatever is left at the end is
+/// unexplained.
+struct Reader<'a> {
+    payload: &'a [u8],
+    position: usize,
+    fields: Vec<Field>,
+    problems: Vec<String>,
+}
+
+impl<'a> Reader<'a> {
+    fn remaining(&self) -> &'a [u8] {
+        &self.payload[self.position..]
+    }
+
+    fn is_done(&self) -> bool {
+        self.position == self.payload.len()
+    }
+
+    /// Reads `length` bytes as `name`, or notes that the payload ends first.
+    fn field<F>(&mut self, name: &'static str, length: usize, meaning: F) -> bool
+    where
+        F: FnOnce(&[u8]) -> String,
+    {
+        if self.remaining().len() < length {
+            self.problems
+                .push(format!("payload ends before the {}", name));
+            return false;
+        }
+        let bytes = &self.payload[self.position..self.position + length];
+        self.fields.push(Field {
+            offset: PAYLOAD_OFFSET + self.position,
+            bytes: bytes.to_vec(),
+            name,
+            meaning: Some(meaning(bytes)),
+        });
+        self.position += length;
+        true
+    }
+
+    /// Reads whatever is left as `name`, if anything is.
+    fn rest<F>(&mut self, name: &'static str, meaning: F)
+    where
+        F: FnOnce(&[u8]) -> String,
+    {
+        if !self.is_done() {
+            self.field(name, self.remaining().len(), meaning);
+        }
+    }
+
+    fn unexplained(&mut self, length: usize) {
+        let length = length.min(self.remaining().len());
+        self.fields.push(Field {
+            offset: PAYLOAD_OFFSET + self.position,
+            bytes: self.remaining()[..length].to_vec(),
+            name: "unexplained",
+            meaning: None,
+        });
+        self.position += length;
+    }
+
+    /// Reads bytes that should all be zero.
+    fn padding(&mut self, length: usize) -> bool {
+        let zero = self.remaining().iter().take(length).all(|b| *b == 0);
+        if !zero {
+            self.problems.push(format!(
+                "padding at offset {} is not zero",
+                PAYLOAD_OFFSET + self.position
+            ));
+        }
+        self.field("padding", length, |_| String::from("unused"))
+    }
+
+    fn finish(mut self) -> (Vec<Field>, Vec<String>) {
+        if !self.is_done() {
+            self.unexplained(self.remaining().len());
+        }
+        (self.fields, self.problems)
+    }
+}
+
+fn effect(registry: &EffectRegistry, bytes: &[u8]) -> String {
+    let id = decode_effect_id(&bytes[0], &bytes[1]);
+    format!("{:?} (ID {})", registry.effect(id), id)
+}
+
+/// A preset number sent most significant byte first, as on the II.
+fn preset_msb_first(bytes: &[u8]) -> String {
+    format!("preset {}", decode_preset_number(bytes[0], bytes[1]))
+}
+
+/// The target of a preset dump request or header.
+fn dump_target(model: FractalModel, bytes: &[u8]) -> String {
+    match decode_dump_target(model, bytes) {
+        Some(PresetTarget::Preset(n)) => format!("preset {}", n),
+        _ => String::from("edit buffer"),
+    }
+}
+
+/// A preset number sent least significant byte first, as on the III;
+/// `7F 7F` means the current preset.
+fn preset_lsb_first(bytes: &[u8]) -> String {
+    match decode_effect_id(&bytes[0], &bytes[1]) {
+        0x3FFF => String::from("current preset"),
+        n => format!("preset {}", n),
+    }
+}
+
+fn name(bytes: &[u8]) -> String {
+    format!("{:?}", decode_preset_name(bytes))
+}
+
+fn scene(bytes: &[u8]) -> String {
+    match bytes[0] {
+        0x7F => String::from("current scene"),
+        n => format!("scene {}", u16::from(n) + 1),
+    }
+}
+
+fn flags(names: &[&str], byte: u8) -> String {
+    let set: Vec<&str> = names
+        .iter()
+        .enumerate()
+        .filter(|(bit, _)| byte & 1 << bit != 0)
+        .map(|(_, name)| *name)
+        .collect();
+    if set.is_empty() {
+        String::from("none")
+    } else {
+        set.join(", ")
+    }
+}
+
+const LOOPER_FLAGS: [&str; 6] = ["record", "play", "overdub", "once", "reverse", "half speed"];
+const LOOPER_COMMANDS: [&str; 6] = ["record", "play", "undo", "once", "reverse", "half speed"];
+
+fn rows(connections: u8) -> String {
+    let rows: Vec<String> = (0..7)
+        .filter(|row| connections & 1 << row != 0)
+        .map(|row| (row + 1).to_string())
+        .collect();
+    if rows.is_empty() {
+        String::from("no input")
+    } else {
+        format!("input from row {}", rows.join(", "))
+    }
+}
+
+fn grid_cell(registry: &EffectRegistry, bytes: &[u8]) -> String {
+    match decode_effect_id(&bytes[0], &bytes[1]) {
+        0 => String::from("empty"),
+        _ => format!("{}, {}", effect(registry, bytes), rows(bytes[2])),
+    }
+}
+
+/// The III's editor commands share 0x01 with block parameters, but start
+/// with a command byte and five zeros.
+fn is_editor_command(payload: &[u8]) -> bool {
+    [0x26, 0x28].contains(&payload[0]) && payload[1..6].iter().all(|b| *b == 0)
+}
+
+/// Reads the fields of one payload, returning the direction it was sent in.
+fn read_payload(model: FractalModel, function_id: u8, reader: &mut Reader) -> Option<Direction> {
+    use Direction::*;
+    let iii = model.uses_iii_protocol();
+    let registry = EffectRegistry::for_model(model);
+    let payload = reader.payload;
+    let length = payload.len();
+    match function_id {
+        0x01 if iii && length >= 8 && is_editor_command(payload) => {
+            let command = payload[0];
+            reader.field("editor command", 1, |_| {
+                String::from(match command {
+                    0x26 => "store preset",
+                    _ => "rename preset",
+                })
+            });
+            reader.padding(5);
+            reader.field("preset", 2, preset_lsb_first);
+            if command == 0x26 {
+                reader.padding(7);
+            } else {
+                reader.padding(5);
+                reader.field("name length", 1, |b| format!("{} characters", b[0]));
+                reader.padding(1);
+                reader.rest("name", |b| format!("{:?}", decode_preset_name_iii(b)));
+            }
+            Some(Request)
+        }
+        0x01 if length == 2 => {
+            reader.field("effect", 2, |b| effect(&registry, b));
+            Some(Request)
+        }
+        0x01 | 0x02 => {
+            let effect_id = payload.get(..2).map(|b| decode_effect_id(&b[0], &b[1]));
+            reader.field("effect", 2, |b| effect(&registry, b));
+            reader.field("parameter", 2, |b| {
+                let id = decode_effect_id(&b[0], &b[1]);
+                let effect = registry.effect(effect_id.unwrap_or(0));
+                format!("{:?} (ID {})", parameter_for(effect, id), id)
+            });
+            reader.field("value", 3, |b| {
+                decode_parameter_value(b[0], b[1], b[2]).to_string()
+            });
+            if function_id == 0x02 && length == 8 {
+                reader.field("action", 1, |b| {
+                    String::from(if b[0] == 1 { "set" } else { "query" })
+                });
+                Some(Request)
+            } else if function_id == 0x02 {
+                reader.unexplained(PARAMETER_LABEL_OFFSET - 7);
+                reader.rest("label", |b| {
+                    let label: String = b
+                        .iter()
+                        .take_while(|b| **b > 0)
+                        .map(|b| *b as char)
+                        .collect();
+                    format!("{:?}", label)
+                });
+                Some(Response)
+            } else {
+                Some(Response)
+            }
+        }
+        0x03 => {
+            reader.field("preset", 2, |b| dump_target(model, b));
+            Some(Request)
+        }
+        0x08 if length == 0 => Some(Request),
+        0x08 => {
+            reader.field("version", 2, |b| format!("{}.{:02}", b[0], b[1]));
+            reader.rest("reserved", |_| String::from("unused"));
+            Some(Response)
+        }
+        0x09 if !iii => {
+            reader.rest("name", name);
+            Some(Request)
+        }
+        0x0A | 0x0B if iii => {
+            reader.field("effect", 2, |b| effect(&registry, b));
+            let query = payload.get(2) == Some(&0x7F);
+            reader.field(
+                if function_id == 0x0A {
+                    "bypass"
+                } else {
+                    "channel"
+                },
+                1,
+                |b| match (function_id, b[0]) {
+                    (_, 0x7F) => String::from("query"),
+                    (0x0A, 0) => String::from("engaged"),
+                    (0x0A, _) => String::from("bypassed"),
+                    (_, n) => match Channel::from_u8(n) {
+                        Some(channel) => format!("{:?}", channel),
+                        None => format!("invalid channel {}", n),
+                    },
+                },
+            );
+            if query {
+                Some(Request)
+            } else {
+                None
+            }
+        }
+        0x0C if iii => {
+            reader.field("scene", 1, scene);
+            match payload.first() {
+                Some(n) if *n >= 8 => Some(Request),
+                _ => None,
+            }
+        }
+        0x0D if iii && length == 2 => {
+            reader.field("preset", 2, preset_lsb_first);
+            Some(Request)
+        }
+        0x0D if iii => {
+            reader.field("preset", 2, preset_lsb_first);
+            reader.rest("name", name);
+            Some(Response)
+        }
+        0x0D => {
+            if length >= 3 {
+                let reading =
+                    TunerReading::new(payload[0], payload[1], payload[2], &TunerConfig::default());
+                reader.field("note", 1, |_| String::from(reading.note_name));
+                reader.field("string", 1, |b| format!("string {}", b[0]));
+                reader.field("deviation", 1, |_| format!("{:+.1} cents", reading.cents));
+            } else {
+                reader
+                    .problems
+                    .push(String::from("payload ends before the tuner reading"));
+            }
+            Some(Response)
+        }
+        0x0E if iii && length == 1 => {
+            reader.field("scene", 1, scene);
+            Some(Request)
+        }
+        0x0E if iii => {
+            reader.field("scene", 1, scene);
+            reader.rest("name", name);
+            Some(Response)
+        }
+        0x0E if length == 0 => Some(Request),
+        0x0E => {
+            while reader.remaining().len() >= 5 {
+                reader.field("block", 5, |b| {
+                    let flags = &decode_preset_blocks_flags(&registry, b)[0];
+                    format!(
+                        "{:?} (ID {}), {}, {}, CC {}",
+                        flags.effect,
+                        flags.effect_id,
+                        if flags.is_bypassed {
+                            "bypassed"
+                        } else {
+                            "engaged"
+                        },
+                        match flags.xy_state {
+                            XYState::X => "X",
+                            XYState::Y => "Y",
+                        },
+                        flags.cc
+                    )
+                });
+            }
+            Some(Response)
+        }
+        0x0F if iii => {
+            let byte = payload.first().copied();
+            reader.field("looper", 1, |b| match b[0] {
+                0x7F => String::from("query"),
+                n if n < 6 => format!(
+                    "command {} or state {}",
+                    LOOPER_COMMANDS[n as usize],
+                    flags(&LOOPER_FLAGS, n)
+                ),
+                n => format!("state {}", flags(&LOOPER_FLAGS, n)),
+            });
+            match byte {
+                Some(0x7F) => Some(Request),
+                Some(n) if n >= 6 => Some(Response),
+                _ => None,
+            }
+        }
+        0x0F if length == 0 => Some(Request),
+        0x0F => {
+            reader.rest("name", name);
+            Some(Response)
+        }
+        0x10 | 0x21 => Some(Response),
+        0x11 => {
+            reader.field("tuner", 1, |b| {
+                String::from(if b[0] == 0 { "off" } else { "on" })
+            });
+            None
+        }
+        0x13 if iii && length == 0 => Some(Request),
+        0x13 if iii => {
+            while reader.remaining().len() >= 3 {
+                reader.field("block", 3, |b| {
+                    let channel = b[2] >> 1 & 0b111;
+                    format!(
+                        "{}, {}, channel {}, {} channels",
+                        effect(&registry, b),
+                        if b[2] & 1 != 0 { "bypassed" } else { "engaged" },
+                        Channel::from_u8(channel)
+                            .map_or_else(|| channel.to_string(), |c| format!("{:?}", c)),
+                        b[2] >> 4 & 0b111
+                    )
+                });
+            }
+            Some(Response)
+        }
+        0x14 if iii => {
+            let query = payload == [0x7F, 0x7F];
+            reader.field("tempo", 2, |b| {
+                if query {
+                    String::from("query")
+                } else {
+                    format!("{} BPM", decode_effect_id(&b[0], &b[1]))
+                }
+            });
+            if query {
+                Some(Request)
+            } else {
+                None
+            }
+        }
+        0x14 if length == 0 => Some(Request),
+        0x14 => {
+            reader.field("preset", 2, preset_msb_first);
+            Some(Response)
+        }
+        0x17 if length == 0 => Some(Request),
+        0x17 => {
+            reader.field("channel", 1, |b| format!("channel {}", u16::from(b[0]) + 1));
+            reader.rest("reserved", |_| String::from("unused"));
+            Some(Response)
+        }
+        0x1D if !iii => {
+            reader.field("preset", 2, preset_msb_first);
+            Some(Request)
+        }
+        0x20 if length == 0 => Some(Request),
+        0x20 => {
+            let (rows, columns) = Grid::dimensions(model);
+            for column in 0..columns {
+                for row in 0..rows {
+                    reader.field("cell", 4, |b| {
+                        format!(
+                            "row {}, column {}: {}",
+                            row + 1,
+                            column + 1,
+                            grid_cell(&registry, b)
+                        )
+                    });
+                }
+            }
+            Some(Response)
+        }
+        0x29 if !iii => {
+            reader.field("scene", 1, scene);
+            None
+        }
+        0x3C => {
+            reader.field("preset", 2, preset_msb_first);
+            Some(Request)
+        }
+        0x42 => Some(Request),
+        0x64 => {
+            reader.field("function", 1, |b| {
+                format!("0x{:02X} {}", b[0], function_name(model, b[0]))
+            });
+            reader.field("result", 1, |b| match b[0] {
+                0 => String::from("ok"),
+                n => format!("error {}", n),
+            });
+            Some(Response)
+        }
+        0x77 => {
+            reader.field("preset", 2, |b| dump_target(model, b));
+            reader.rest("header data", |b| format!("{} bytes", b.len()));
+            None
+        }
+        0x7A => {
+            reader.field("slot", 2, |b| match b {
+                [0x7F, 0x00] | [0x7F, 0x7F] => String::from("edit buffer"),
+                _ => format!("{}", decode_preset_number(b[0], b[1])),
+            });
+            reader.padding(2);
+            reader.rest("name", name);
+            None
+        }
+        0x78 | 0x79 | 0x7B | 0x7C => {
+            reader.rest("data", |b| format!("{} bytes", b.len()));
+            None
+        }
+        _ => None,
+    }
+}
+
+fn describe_message(msg: &[u8]) -> Description {
+    if msg.len() < PAYLOAD_OFFSET || msg[..SYSEX_HEADER.len()] != SYSEX_HEADER {
+        return stray(msg, "not a Fractal SysEx message");
+    }
+    let mut problems = vec![];
+    let end = if msg.last() == Some(&0xF7) {
+        msg.len() - 1
+    } else {
+        problems.push(String::from("message is not terminated by 0xF7"));
+        msg.len()
+    };
+    for (index, byte) in msg.iter().enumerate().take(end).skip(1) {
+        if byte & 0x80 != 0 {
+            problems.push(format!(
+                "byte 0x{:02X} at offset {} is not a 7-bit data byte",
+                byte, index
+            ));
+        }
+    }
+    let model = match FractalModel::from_code(&msg[4]) {
+        Some(model) => model,
+        None => {
+            let mut description = stray(&msg[4..end], "unknown model");
+            description.message = msg.to_vec();
+            description.fields[0].offset = 4;
+            description.problems = problems;
+            return description;
+        }
+    };
+    let function_id = msg[5];
+    let rest = &msg[PAYLOAD_OFFSET..end];
+    let expected = rest
+        .split_last()
+        .map(|(_, payload)| checksum(&msg[..PAYLOAD_OFFSET + payload.len()]));
+    let (payload, checksum_status) = match rest.split_last() {
+        Some((actual, payload)) if Some(*actual) == expected => (payload, ChecksumStatus::Ok),
+        _ if !carries_checksum(model, function_id) => (rest, ChecksumStatus::NotSent),
+        Some((actual, payload)) => (
+            payload,
+            ChecksumStatus::Mismatch {
+                expected: expected.unwrap_or(0),
+                actual: *actual,
+            },
+        ),
+        None => (rest, ChecksumStatus::Missing),
+    };
+    if checksum_status == ChecksumStatus::Missing {
+        problems.push(String::from("message ends before the checksum"));
+    }
+    let mut reader = Reader {
+        payload,
+        position: 0,
+        fields: vec![],
+        problems: vec![],
+    };
+    let direction = read_payload(model, function_id, &mut reader);
+    let (mut fields, more_problems) = reader.finish();
+    problems.extend(more_problems);
+    // Requests always end in a checksum, even for functions whose responses
+    // don't.
+    let direction = match (direction, checksum_status) {
+        (None, ChecksumStatus::NotSent) => Some(Direction::Response),
+        _ => direction,
+    };
+    if let ChecksumStatus::Ok | ChecksumStatus::Mismatch { .. } = checksum_status {
+        fields.push(Field {
+            offset: PAYLOAD_OFFSET + payload.len(),
+            bytes: vec![msg[PAYLOAD_OFFSET + payload.len()]],
+            name: "checksum",
+            meaning: Some(match checksum_status {
+                ChecksumStatus::Ok => String::from("ok"),
+                _ => format!("should be 0x{:02X}", expected.unwrap_or(0)),
+            }),
+        });
+    }
+    Description {
+        message: msg.to_vec(),
+        model: Some(model),
+        function_id: Some(function_id),
+        function: function_name(model, function_id),
+        direction,
+        fields,
+        checksum: checksum_status,
+        problems,
+    }
+}
@@ -0,0 +1,167 @@
+//! Checking firmware update `.syx` files without a device attached.
+//!
+//! Fractal hasn't published the layout of an update's messages, so `inspect`
+//! only checks what holds for any Fractal SysEx file: that every message is
+//! framed and checksummed, and that they all target the same model. It
+//! can't tell which firmware version a file holds, or whether its blocks are
+//! all there and in order.
+//!
+//! Unlike `PresetFile::parse`, `inspect` doesn't stop at the first problem:
+//! it walks the whole file and lists everything it finds.
+
+use crate::parse::ParseError;
+use crate::preset_file::{parse_frame, PresetFileError};
+use crate::FractalModel;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FirmwareProblem {
+    /// The message starting at `offset` is malformed or fails its checksum.
+    /// `offset` points at the offending byte where there is one.
+    InvalidMessage {
+        index: usize,
+        offset: usize,
+        error: ParseError,
+    },
+    /// The message targets a different model than the first one.
+    ModelMismatch {
+        index: usize,
+        offset: usize,
+        model: FractalModel,
+    },
+    /// `length` bytes starting at `offset` belong to no message.
+    StrayBytes { offset: usize, length: usize },
+}
+
+impl fmt::Display for FirmwareProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FirmwareProblem::InvalidMessage {
+                index,
+                offset,
+                error,
+            } => write!(f, "message {} (byte {}): {}", index, offset, error),
+            FirmwareProblem::ModelMismatch {
+                index,
+                offset,
+                model,
+            } => write!(
+                f,
+                "message {} (byte {}): model {:?} does not match the first message",
+                index, offset, model
+            ),
+            FirmwareProblem::StrayBytes { offset, length } => {
+                write!(f, "byte {}: {} bytes outside any message", offset, length)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FirmwareProblem {}
+
+/// What `inspect` found in a firmware file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FirmwareReport {
+    /// Every SysEx message in the file, including broken ones.
+    pub message_count: usize,
+    pub total_size: usize,
+    /// The model of the first readable message.
+    pub model: Option<FractalModel>,
+    pub problems: Vec<FirmwareProblem>,
+}
+
+impl FirmwareReport {
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Splits a file into messages like `preset_file::split_messages`, but
+/// carries on past stray bytes, reporting them by offset instead. They
+/// aren't messages, so they take no message index.
+fn walk_messages<'a>(
+    bytes: &'a [u8],
+    problems: &mut Vec<FirmwareProblem>,
+) -> Vec<(usize, &'a [u8])> {
+    let mut messages = vec![];
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let index = messages.len();
+        if bytes[offset] != 0xF0 {
+            let length = bytes[offset..]
+                .iter()
+                .position(|byte| *byte == 0xF0)
+                .unwrap_or(bytes.len() - offset);
+            problems.push(FirmwareProblem::StrayBytes { offset, length });
+            offset += length;
+            continue;
+        }
+        // A message cut short is followed directly by the next one's F0.
+        let end = bytes[offset + 1..]
+            .iter()
+            .position(|byte| *byte == 0xF7 || *byte == 0xF0)
+            .map(|len| offset + 1 + len);
+        match end {
+            Some(end) if bytes[end] == 0xF7 => {
+                messages.push((offset, &bytes[offset..=end]));
+                offset = end + 1;
+            }
+            _ => {
+                let end = end.unwrap_or(bytes.len());
+                problems.push(FirmwareProblem::InvalidMessage {
+                    index,
+                    offset,
+                    error: ParseError::Truncated,
+                });
+                messages.push((offset, &bytes[offset..end]));
+                offset = end;
+            }
+        }
+    }
+    messages
+}
+
+/// Walks a firmware update file, checking the framing and checksum of every
+/// message and that they all target one model.
+pub fn inspect(bytes: &[u8]) -> FirmwareReport {
+    let mut problems = vec![];
+    let messages = walk_messages(bytes, &mut problems);
+    let message_count = messages.len();
+    let mut model = None;
+    for (index, (offset, msg)) in messages.into_iter().enumerate() {
+        if msg.last() != Some(&0xF7) {
+            continue;
+        }
+        let frame = match parse_frame(index, offset, msg) {
+            Ok(frame) => frame,
+            Err(PresetFileError::InvalidMessage {
+                index,
+                offset,
+                error,
+            }) => {
+                problems.push(FirmwareProblem::InvalidMessage {
+                    index,
+                    offset,
+                    error,
+                });
+                continue;
+            }
+            Err(_) => continue,
+        };
+        match model {
+            None => model = Some(frame.model),
+            Some(model) if model != frame.model => problems.push(FirmwareProblem::ModelMismatch {
+                index,
+                offset,
+                model: frame.model,
+            }),
+            Some(_) => {}
+        }
+    }
+    FirmwareReport {
+        message_count,
+        total_size: bytes.len(),
+        model,
+        problems,
+    }
+}
@@ -0,0 +1,142 @@
+use crate::parse::ParseError;
+use crate::{checksum, model_code, FractalModel, MidiMessage};
+
+pub const SYSEX_HEADER: [u8; 4] = [0xF0, 0x00, 0x01, 0x74];
+const SYSEX_END: u8 = 0xF7;
+
+/// Some realtime notifications are sent by the device without a trailing
+/// checksum byte, so everything after the function ID is payload.
+pub(crate) fn carries_checksum(model: FractalModel, function_id: u8) -> bool {
+    match (model, function_id) {
+        (_, 0x08) | (_, 0x10) | (_, 0x17) | (_, 0x21) => false,
+        _ if model.uses_iii_protocol() => true,
+        (_, 0x0D) | (_, 0x0E) => false,
+        _ => true,
+    }
+}
+
+/// A Fractal SysEx message split into its parts.
+///
+/// `parse` validates the manufacturer header (`F0 00 01 74`) and the
+/// terminating `F7`, so only the fields that vary between messages are kept.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SysexFrame {
+    pub model: FractalModel,
+    pub function_id: u8,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_hex"))]
+    pub payload: Vec<u8>,
+    /// The checksum byte as received, or `None` for functions that are sent
+    /// without one.
+    pub checksum: Option<u8>,
+}
+
+impl SysexFrame {
+    /// Builds an outgoing frame with a freshly computed checksum.
+    pub fn new(model: FractalModel, function_id: u8, payload: Vec<u8>) -> Self {
+        let mut frame = SysexFrame {
+            model,
+            function_id,
+            payload,
+            checksum: None,
+        };
+        if carries_checksum(model, function_id) {
+            frame.checksum = Some(frame.expected_checksum());
+        }
+        frame
+    }
+
+    /// Splits a message received from the device.
+    pub fn parse(msg: &[u8]) -> Result<Self, ParseError> {
+        Self::parse_frame(msg, false)
+    }
+
+    /// Splits a message sent to the device. Requests built by this crate always
+    /// end in a checksum, even for functions whose responses don't.
+    pub fn parse_request(msg: &[u8]) -> Result<Self, ParseError> {
+        Self::parse_frame(msg, true)
+    }
+
+    fn parse_frame(msg: &[u8], is_request: bool) -> Result<Self, ParseError> {
+        if msg.len() < SYSEX_HEADER.len() + 2 || msg[..SYSEX_HEADER.len()] != SYSEX_HEADER {
+            return Err(ParseError::MissingHeader);
+        }
+        if msg.last() != Some(&SYSEX_END) {
+            return Err(ParseError::Truncated);
+        }
+        let end = msg.len() - 1;
+        if let Some(index) = msg[4..end].iter().position(|byte| byte & 0x80 != 0) {
+            return Err(ParseError::InvalidDataByte {
+                index: index + 4,
+                byte: msg[index + 4],
+            });
+        }
+        let model_code = msg[4];
+        let model =
+            FractalModel::from_code(&model_code).ok_or(ParseError::UnknownModel(model_code))?;
+        let function_id = msg[5];
+        let rest = &msg[6..end];
+        let (payload, checksum) = match rest.split_last() {
+            Some((checksum, payload)) if is_request || carries_checksum(model, function_id) => {
+                (payload, Some(*checksum))
+            }
+            _ => (rest, None),
+        };
+        Ok(SysexFrame {
+            model,
+            function_id,
+            payload: payload.to_vec(),
+            checksum,
+        })
+    }
+
+    /// The checksum the device would compute over this frame's header, model,
+    /// function ID and payload.
+    pub fn expected_checksum(&self) -> u8 {
+        checksum(&self.body())
+    }
+
+    /// Checks the received checksum against the frame contents. Responses for
+    /// functions that are sent without a checksum always verify.
+    pub fn verify(&self) -> Result<(), ParseError> {
+        if self.checksum.is_none() && !carries_checksum(self.model, self.function_id) {
+            return Ok(());
+        }
+        let expected = self.expected_checksum();
+        match self.checksum {
+            Some(actual) if actual == expected => Ok(()),
+            Some(actual) => Err(ParseError::ChecksumMismatch { expected, actual }),
+            None => Err(ParseError::MissingChecksum),
+        }
+    }
+
+    pub fn to_message(&self) -> MidiMessage {
+        [
+            self.body(),
+            self.checksum.into_iter().collect(),
+            vec![SYSEX_END],
+        ]
+        .concat()
+    }
+
+    fn body(&self) -> MidiMessage {
+        [
+            SYSEX_HEADER.to_vec(),
+            vec![model_code(self.model), self.function_id],
+            self.payload.clone(),
+        ]
+        .concat()
+    }
+}
+
+/// Parses `msg` and checks its checksum without decoding the payload.
+pub fn verify_checksum(msg: &[u8]) -> Result<(), ParseError> {
+    SysexFrame::parse(msg)?.verify()
+}
+
+/// Verifies `msg` and returns it with the checksum byte removed.
+pub fn strip_checksum(msg: &[u8]) -> Result<MidiMessage, ParseError> {
+    let frame = SysexFrame::parse(msg)?;
+    frame.verify()?;
+    Ok([frame.body(), vec![SYSEX_END]].concat())
+}
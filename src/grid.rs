@@ -0,0 +1,384 @@
+//! The block grid of a preset: which block sits in which cell, and how the
+//! cells are wired together.
+//!
+//! On the wire the grid is sent column by column, four bytes per cell: the
+//! effect ID (least significant byte first), a byte of connection flags and
+//! a fourth byte whose meaning isn't known. Bit `n` of the flags is set when
+//! the cell takes its input from row `n` of the previous column. The fourth
+//! byte is kept as read, so a grid encodes back to the bytes it came from;
+//! edits leave it alone.
+//!
+//! Grids are read from the device with `get_grid_layout_and_routing`. Edits
+//! only change the `Grid` itself: no message that writes a cell has been
+//! captured or documented yet, so sending edits to the device is left for a
+//! follow-up once one has.
+
+use crate::parse::{decode_effect_id, BlockGridBlock, Effect, ParseError};
+use crate::registry::EffectRegistry;
+use crate::{encode_effect_id, FractalModel};
+use std::fmt;
+
+const GRID_CELL_SIZE: usize = 4;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GridError {
+    /// The cell lies outside the model's grid.
+    OutOfBounds { row: usize, column: usize },
+    /// The cell already holds a block.
+    Occupied { row: usize, column: usize },
+    /// The cell holds no block.
+    Empty { row: usize, column: usize },
+    /// The effect already sits elsewhere on the grid. Only shunts may be
+    /// placed more than once.
+    AlreadyPlaced {
+        effect: Effect,
+        row: usize,
+        column: usize,
+    },
+    /// The model has no ID for the effect.
+    UnknownEffect(Effect),
+    /// Connections only run from one column to the next.
+    NotAdjacent,
+}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GridError::OutOfBounds { row, column } => {
+                write!(f, "cell ({}, {}) is outside the grid", row, column)
+            }
+            GridError::Occupied { row, column } => {
+                write!(f, "cell ({}, {}) already holds a block", row, column)
+            }
+            GridError::Empty { row, column } => write!(f, "cell ({}, {}) is empty", row, column),
+            GridError::AlreadyPlaced {
+                effect,
+                row,
+                column,
+            } => write!(f, "{:?} already sits at ({}, {})", effect, row, column),
+            GridError::UnknownEffect(effect) => write!(f, "{:?} has no ID on this model", effect),
+            GridError::NotAdjacent => write!(f, "cells are not in neighbouring columns"),
+        }
+    }
+}
+
+impl std::error::Error for GridError {}
+
+/// A preset's block grid, sized for its model.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid {
+    model: FractalModel,
+    rows: usize,
+    columns: usize,
+    /// Column by column, as on the wire.
+    cells: Vec<BlockGridBlock>,
+    /// The fourth byte of each cell, in the same order.
+    unknown: Vec<u8>,
+}
+
+impl Grid {
+    /// `(rows, columns)` of the grid on `model`.
+    pub fn dimensions(model: FractalModel) -> (usize, usize) {
+        match model {
+            FractalModel::III | FractalModel::FM9 => (6, 14),
+            _ => (4, 12),
+        }
+    }
+
+    /// An empty grid.
+    pub fn new(model: FractalModel) -> Self {
+        let (rows, columns) = Grid::dimensions(model);
+        Grid {
+            model,
+            rows,
+            columns,
+            cells: vec![BlockGridBlock::Empty; rows * columns],
+            unknown: vec![0; rows * columns],
+        }
+    }
+
+    pub(crate) fn decode(
+        registry: &EffectRegistry,
+        function_id: u8,
+        payload: &[u8],
+    ) -> Result<Self, ParseError> {
+        let mut grid = Grid::new(registry.model());
+        let expected = grid.cells.len() * GRID_CELL_SIZE;
+        if payload.len() < expected {
+            return Err(ParseError::PayloadTooShort {
+                function_id,
+                expected,
+                actual: payload.len(),
+            });
+        }
+        for (i, bytes) in payload
+            .chunks_exact(GRID_CELL_SIZE)
+            .enumerate()
+            .take(grid.cells.len())
+        {
+            grid.cells[i] = decode_cell(registry, bytes);
+            grid.unknown[i] = bytes[3];
+        }
+        Ok(grid)
+    }
+
+    /// The grid in its wire format, as sent in response to
+    /// `get_grid_layout_and_routing`.
+    pub fn encode(&self) -> Vec<u8> {
+        self.cells
+            .iter()
+            .zip(&self.unknown)
+            .flat_map(|(cell, unknown)| encode_cell(cell, *unknown))
+            .collect()
+    }
+
+    pub fn model(&self) -> FractalModel {
+        self.model
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    fn index(&self, row: usize, column: usize) -> Result<usize, GridError> {
+        if row < self.rows && column < self.columns {
+            Ok(column * self.rows + row)
+        } else {
+            Err(GridError::OutOfBounds { row, column })
+        }
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> Option<&BlockGridBlock> {
+        self.index(row, column).ok().map(|i| &self.cells[i])
+    }
+
+    /// Replaces a cell without producing any messages, returning what was
+    /// there before.
+    pub fn set(
+        &mut self,
+        row: usize,
+        column: usize,
+        block: BlockGridBlock,
+    ) -> Result<BlockGridBlock, GridError> {
+        let i = self.index(row, column)?;
+        Ok(std::mem::replace(&mut self.cells[i], block))
+    }
+
+    /// `(row, column)` of the cell holding `effect`.
+    pub fn find(&self, effect: Effect) -> Option<(usize, usize)> {
+        self.cells
+            .iter()
+            .position(|cell| match cell {
+                BlockGridBlock::EffectBlock { effect: e, .. } => *e == effect,
+                BlockGridBlock::Empty => false,
+            })
+            .map(|i| (i % self.rows, i / self.rows))
+    }
+
+    fn occupied(&self, row: usize, column: usize) -> Result<usize, GridError> {
+        let i = self.index(row, column)?;
+        match self.cells[i] {
+            BlockGridBlock::Empty => Err(GridError::Empty { row, column }),
+            _ => Ok(i),
+        }
+    }
+
+    fn vacant(&self, row: usize, column: usize) -> Result<usize, GridError> {
+        let i = self.index(row, column)?;
+        match self.cells[i] {
+            BlockGridBlock::Empty => Ok(i),
+            _ => Err(GridError::Occupied { row, column }),
+        }
+    }
+
+    /// Clears the connections from `(row, column)` into the next column.
+    fn disconnect_outputs(&mut self, row: usize, column: usize) {
+        if column + 1 >= self.columns {
+            return;
+        }
+        let start = (column + 1) * self.rows;
+        for cell in &mut self.cells[start..start + self.rows] {
+            if let BlockGridBlock::EffectBlock { connections, .. } = cell {
+                *connections &= !(1 << row);
+            }
+        }
+    }
+
+    /// Puts `effect` into an empty cell, unconnected.
+    pub fn place(&mut self, row: usize, column: usize, effect: Effect) -> Result<(), GridError> {
+        let i = self.vacant(row, column)?;
+        // Shunts are plain wire and may be placed any number of times.
+        let placed = match effect {
+            Effect::Shunt => None,
+            _ => self.find(effect),
+        };
+        if let Some((row, column)) = placed {
+            return Err(GridError::AlreadyPlaced {
+                effect,
+                row,
+                column,
+            });
+        }
+        let effect_id = EffectRegistry::for_model(self.model)
+            .id(effect)
+            .ok_or(GridError::UnknownEffect(effect))?;
+        self.cells[i] = BlockGridBlock::EffectBlock {
+            effect_id,
+            effect,
+            connections: 0,
+        };
+        Ok(())
+    }
+
+    /// Empties a cell, dropping its connections in both directions.
+    pub fn remove(&mut self, row: usize, column: usize) -> Result<(), GridError> {
+        let i = self.occupied(row, column)?;
+        self.cells[i] = BlockGridBlock::Empty;
+        self.disconnect_outputs(row, column);
+        Ok(())
+    }
+
+    /// Moves a block to an empty cell. Its connections are dropped, since
+    /// they rarely make sense at the new position.
+    pub fn move_block(
+        &mut self,
+        from: (usize, usize),
+        to: (usize, usize),
+    ) -> Result<(), GridError> {
+        let i = self.occupied(from.0, from.1)?;
+        let j = self.vacant(to.0, to.1)?;
+        let block = match std::mem::take(&mut self.cells[i]) {
+            BlockGridBlock::EffectBlock {
+                effect_id, effect, ..
+            } => BlockGridBlock::EffectBlock {
+                effect_id,
+                effect,
+                connections: 0,
+            },
+            BlockGridBlock::Empty => BlockGridBlock::Empty,
+        };
+        self.cells[j] = block;
+        self.disconnect_outputs(from.0, from.1);
+        Ok(())
+    }
+
+    /// Feeds the output of `from` into `to`, which must be in the next
+    /// column. Both cells must hold blocks.
+    pub fn connect(&mut self, from: (usize, usize), to: (usize, usize)) -> Result<(), GridError> {
+        self.set_connection(from, to, true)
+    }
+
+    /// Feeds the preset's input into a block in the first column.
+    pub fn connect_input(&mut self, row: usize) -> Result<(), GridError> {
+        let i = self.occupied(row, 0)?;
+        if let BlockGridBlock::EffectBlock { connections, .. } = &mut self.cells[i] {
+            *connections |= 1 << row;
+        }
+        Ok(())
+    }
+
+    pub fn disconnect(
+        &mut self,
+        from: (usize, usize),
+        to: (usize, usize),
+    ) -> Result<(), GridError> {
+        self.set_connection(from, to, false)
+    }
+
+    fn set_connection(
+        &mut self,
+        from: (usize, usize),
+        to: (usize, usize),
+        connected: bool,
+    ) -> Result<(), GridError> {
+        self.occupied(from.0, from.1)?;
+        let j = self.occupied(to.0, to.1)?;
+        if to.1 != from.1 + 1 {
+            return Err(GridError::NotAdjacent);
+        }
+        if let BlockGridBlock::EffectBlock { connections, .. } = &mut self.cells[j] {
+            if connected {
+                *connections |= 1 << from.0;
+            } else {
+                *connections &= !(1 << from.0);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Serialized row by row, `{"model": "III", "rows": [[...], ...]}`, rather
+/// than in the column-major wire order.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct GridRows {
+    model: FractalModel,
+    rows: Vec<Vec<BlockGridBlock>>,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Grid {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        GridRows {
+            model: self.model,
+            rows: (0..self.rows)
+                .map(|row| {
+                    (0..self.columns)
+                        .map(|column| self.cells[column * self.rows + row])
+                        .collect()
+                })
+                .collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Grid {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        let GridRows { model, rows } = GridRows::deserialize(deserializer)?;
+        let mut grid = Grid::new(model);
+        if rows.len() != grid.rows || rows.iter().any(|cells| cells.len() != grid.columns) {
+            return Err(D::Error::custom(format!(
+                "a {:?} grid has {} rows of {} cells",
+                model, grid.rows, grid.columns
+            )));
+        }
+        for (row, cells) in rows.into_iter().enumerate() {
+            for (column, block) in cells.into_iter().enumerate() {
+                grid.cells[column * grid.rows + row] = block;
+            }
+        }
+        Ok(grid)
+    }
+}
+
+fn decode_cell(registry: &EffectRegistry, bytes: &[u8]) -> BlockGridBlock {
+    match decode_effect_id(&bytes[0], &bytes[1]) {
+        0 => BlockGridBlock::Empty,
+        effect_id => BlockGridBlock::EffectBlock {
+            effect_id,
+            effect: registry.effect(effect_id),
+            connections: bytes[2],
+        },
+    }
+}
+
+fn encode_cell(block: &BlockGridBlock, unknown: u8) -> Vec<u8> {
+    match block {
+        BlockGridBlock::Empty => vec![0, 0, 0, unknown],
+        BlockGridBlock::EffectBlock {
+            effect_id,
+            connections,
+            ..
+        } => {
+            let (a, b) = encode_effect_id(*effect_id);
+            vec![a, b, *connections, unknown]
+        }
+    }
+}
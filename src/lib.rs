@@ -1,9 +1,45 @@
+mod assembler;
+pub mod cab_file;
+pub mod capture;
+mod describe;
+pub mod firmware_file;
+mod frame;
+mod grid;
+pub mod looper;
+mod parameters;
 mod parse;
+pub mod preset_dump;
+pub mod preset_file;
+mod registry;
+pub mod routing;
+pub mod septet;
+#[cfg(feature = "serde")]
+mod serde_hex;
+mod session;
+pub mod simulator;
+pub mod tempo;
+mod transport;
+mod tuner;
+
+pub use assembler::{SysexAssembler, DEFAULT_MAX_SYSEX_LENGTH};
+pub use describe::{
+    describe, function_name, parse_hex, ChecksumStatus, Description, Direction, Field, HexError,
+};
+pub use frame::{strip_checksum, verify_checksum, SysexFrame};
+pub use grid::{Grid, GridError};
+pub use parameters::{
+    parameter_info, parameters_for_block, BlockType, ParameterInfo, ParameterKind, MAX_RAW_VALUE,
+};
 
 pub use parse::{
-    id_for_effect, parse_message, BlockFlags, BlockGridBlock, Effect, FractalMessage, FractalModel,
-    Parameter, TunerStatus, XYState,
+    parse_message, try_parse_message, BlockFlags, BlockGridBlock, Channel, Effect, EffectID,
+    EffectStatus, FractalMessage, FractalModel, Parameter, ParseError, TunerStatus, XYState,
 };
+pub use preset_dump::{PresetDump, PresetDumpAssembler, PresetTarget};
+pub use registry::EffectRegistry;
+pub use session::{PresetBank, Session, SessionError, DEFAULT_TIMEOUT};
+pub use transport::{MidiTransport, MockTransport};
+pub use tuner::{TunerConfig, TunerReading};
 
 pub type MidiMessage = Vec<u8>;
 
@@ -11,6 +47,8 @@ pub fn guess_model(model_name: &str) -> Option<FractalModel> {
     match model_name {
         "Axe-Fx II" => Some(FractalModel::II),
         "Axe-Fx III" => Some(FractalModel::III),
+        "FM3" => Some(FractalModel::FM3),
+        "FM9" => Some(FractalModel::FM9),
         _ => None,
     }
 }
@@ -28,30 +66,27 @@ fn model_code(model: FractalModel) -> u8 {
         FractalModel::AX8 => 0x08,
         FractalModel::FX8MK2 => 0x0A,
         FractalModel::III => 0x10,
+        FractalModel::FM3 => 0x11,
+        FractalModel::FM9 => 0x12,
     }
 }
 
-pub fn checksum(msg: MidiMessage) -> u8 {
-    let xord = msg
-        .iter()
-        .take(msg.len() - 1)
-        .fold(None, |acc: Option<u8>, x| match acc {
-            Some(y) => Some(y ^ x),
-            None => Some(*x),
-        })
-        .unwrap();
-    0x7F & xord
+/// XOR of every byte before the terminating `F7`, masked to 7 bits.
+pub fn checksum(msg: &[u8]) -> u8 {
+    let body = match msg.split_last() {
+        Some((0xF7, body)) => body,
+        _ => msg,
+    };
+    0x7F & body.iter().fold(0, |acc, x| acc ^ x)
 }
 
 pub fn with_checksum(msg: MidiMessage) -> MidiMessage {
-    let term = msg.iter().last().unwrap();
-    let msg_checksum = checksum(msg.clone());
-    let msg_without_term: MidiMessage = msg
-        .clone()
-        .into_iter()
-        .take(msg.len() - 1)
-        .collect::<Vec<u8>>();
-    [msg_without_term, vec![msg_checksum, *term]].concat()
+    let msg_checksum = checksum(&msg);
+    let mut msg = msg;
+    if msg.last() == Some(&0xF7) {
+        msg.pop();
+    }
+    [msg, vec![msg_checksum, 0xF7]].concat()
 }
 
 fn wrap_msg(msg: MidiMessage) -> MidiMessage {
@@ -60,18 +95,47 @@ fn wrap_msg(msg: MidiMessage) -> MidiMessage {
 }
 
 #[no_mangle]
+#[allow(improper_ctypes_definitions)]
 pub extern "C" fn get_preset_number(model: FractalModel) -> MidiMessage {
     wrap_msg(vec![model_code(model), 0x14])
 }
 
 pub fn get_current_preset_name(model: FractalModel) -> MidiMessage {
-    if model == FractalModel::III {
+    if model.uses_iii_protocol() {
         wrap_msg(vec![model_code(model), 0x0D, 0x7F, 0x7F])
     } else {
         wrap_msg(vec![model_code(model), 0x0F])
     }
 }
 
+/// The messages that ask for the name of any preset.
+///
+/// The III family has a query for any preset number, answered with
+/// `PresetName` (function 0x0D in Fractal's "Axe-Fx III MIDI for 3rd Party
+/// Devices"). Older models can only name the current preset, so for them
+/// this loads the preset with `set_preset_number` and then sends
+/// `get_current_preset_name`, answered with `CurrentPresetNumber` and then
+/// `CurrentPresetName`.
+pub fn get_preset_name(model: FractalModel, preset_number: u32) -> Vec<MidiMessage> {
+    if !model.uses_iii_protocol() {
+        return vec![
+            set_preset_number(model, preset_number),
+            get_current_preset_name(model),
+        ];
+    }
+    let (a, b) = encode_preset_number(preset_number);
+    vec![wrap_msg(vec![model_code(model), 0x0D, b, a])]
+}
+
+/// Number of preset slots in the model's bank.
+pub fn preset_count(model: FractalModel) -> u32 {
+    match model {
+        FractalModel::III => 1024,
+        FractalModel::FM3 | FractalModel::FM9 | FractalModel::AX8 => 512,
+        _ => 768,
+    }
+}
+
 pub fn get_current_scene_name(model: FractalModel) -> MidiMessage {
     get_scene_name(model, 0x7F)
 }
@@ -90,9 +154,10 @@ pub fn set_preset_number(model: FractalModel, n: u32) -> MidiMessage {
 }
 
 pub fn set_current_preset_name(model: FractalModel, name: &str) -> MidiMessage {
-    match model {
-        FractalModel::III => set_current_preset_name_v2(model, name),
-        _ => set_current_preset_name_v1(model, name),
+    if model.uses_iii_protocol() {
+        set_current_preset_name_v2(model, name)
+    } else {
+        set_current_preset_name_v1(model, name)
     }
 }
 
@@ -110,36 +175,27 @@ pub fn set_current_preset_name_v2(model: FractalModel, name: &str) -> MidiMessag
     set_preset_name(model, 0, name)
 }
 
-fn encode_char_iii(i: u32, last: u8, x: u8) -> (u8, u8) {
-    let i = i % 7;
-    println!("i: {}, i % 8: {}", i, i % 8);
-    (last | (x >> (i + 1)), 0x7F & (x << (8 - (i + 1) - 1)))
-}
-
+/// Packs an ASCII name into 7-bit bytes as the III expects it. Non-ASCII
+/// characters are dropped.
 pub fn encode_preset_name_iii(name: &str) -> MidiMessage {
-    println!("----------\nencode_preset_name_iii {}\n----------", name);
-    let mut i = 0;
-    name.chars()
+    let bytes: Vec<u8> = name
+        .chars()
         .filter(|c| c.is_ascii())
         .map(|c| c as u8)
-        .collect::<Vec<u8>>()
-        .chunks(7)
-        .map(|chunk| {
-            chunk.iter().fold(vec![0b00000000], |mut acc, x| {
-                let (last, next) = encode_char_iii(i, *acc.last().unwrap(), *x);
-                println!(
-                    "last: {:b} ({}), x: {:b} {}, next: {:b} ({})",
-                    last, last, x, x, next, next,
-                );
-                let len = acc.len();
-                i = i + 1;
-                acc[len - 1] = last;
-                acc.push(next);
-                acc
-            })
-        })
-        .flatten()
-        .collect::<MidiMessage>()
+        .collect();
+    septet::pack(&bytes)
+}
+
+/// The inverse of `encode_preset_name_iii`, ignoring the NUL padding and
+/// trailing spaces of a name field.
+pub fn decode_preset_name_iii(bytes: &[u8]) -> String {
+    septet::unpack(bytes)
+        .into_iter()
+        .filter(|x| *x > 0)
+        .map(|x| x as char)
+        .collect::<String>()
+        .trim_end()
+        .to_string()
 }
 
 pub fn get_firmware_version(model: FractalModel) -> MidiMessage {
@@ -197,7 +253,7 @@ pub fn get_preset_blocks_flags(model: FractalModel) -> MidiMessage {
 }
 
 pub fn set_scene_number(model: FractalModel, scene_number: u8) -> MidiMessage {
-    let command = if model == FractalModel::III {
+    let command = if model.uses_iii_protocol() {
         0x0C
     } else {
         0x29
@@ -209,18 +265,94 @@ pub fn get_grid_layout_and_routing(model: FractalModel) -> MidiMessage {
     wrap_msg(vec![model_code(model), 0x20])
 }
 
-fn encode_effect_id(id: u8) -> (u8, u8) {
-    (id & 0x7F, (id >> 7) & 0x7F)
+pub(crate) fn encode_effect_id(id: u32) -> (u8, u8) {
+    ((id & 0x7F) as u8, ((id >> 7) & 0x7F) as u8)
+}
+
+/// Queries a block's parameters, using the ID `model` reports the effect
+/// under. Returns `None` if the model has no such effect.
+pub fn get_block_parameters(model: FractalModel, effect: Effect) -> Option<MidiMessage> {
+    let effect_id = EffectRegistry::for_model(model).id(effect)?;
+    let (a, b) = encode_effect_id(effect_id);
+    Some(wrap_msg(vec![model_code(model), 0x01, a, b]))
+}
+
+/// Packs a raw parameter value into three 7-bit bytes, least significant
+/// first. The inverse of the decoding used for `BlockParameters`.
+fn encode_parameter_value(value: u32) -> (u8, u8, u8) {
+    (
+        (value & 0x7F) as u8,
+        ((value >> 7) & 0x7F) as u8,
+        ((value >> 14) & 0x7F) as u8,
+    )
+}
+
+/// Queries whether a block is bypassed. Answered with `BlockBypass`.
+pub fn get_block_bypass(model: FractalModel, effect_id: EffectID) -> MidiMessage {
+    let (a, b) = encode_effect_id(effect_id as u32);
+    wrap_msg(vec![model_code(model), 0x0A, a, b, 0x7F])
+}
+
+pub fn set_block_bypass(model: FractalModel, effect_id: EffectID, bypassed: bool) -> MidiMessage {
+    let (a, b) = encode_effect_id(effect_id as u32);
+    wrap_msg(vec![model_code(model), 0x0A, a, b, bypassed as u8])
+}
+
+/// Queries a block's active channel. Answered with `BlockChannel`.
+pub fn get_block_channel(model: FractalModel, effect_id: EffectID) -> MidiMessage {
+    let (a, b) = encode_effect_id(effect_id as u32);
+    wrap_msg(vec![model_code(model), 0x0B, a, b, 0x7F])
+}
+
+/// Selects a block's channel. `max_channels` is the block's channel count as
+/// reported in `EffectStatus`; returns `None` if the block has no such channel.
+pub fn set_block_channel(
+    model: FractalModel,
+    effect_id: EffectID,
+    channel: Channel,
+    max_channels: u8,
+) -> Option<MidiMessage> {
+    if channel as u8 >= max_channels {
+        return None;
+    }
+    let (a, b) = encode_effect_id(effect_id as u32);
+    Some(wrap_msg(vec![model_code(model), 0x0B, a, b, channel as u8]))
+}
+
+/// Sets a block parameter to a raw value (see `ParameterInfo::display_value`
+/// for the scaling). The device answers with a `BlockParameterValue` holding
+/// the value it actually applied.
+///
+/// Only the Axe-Fx II format is known; there is no documented equivalent
+/// for the III family. Returns `None` for III-family models, if the model has
+/// no such effect, or if `value` is above `MAX_RAW_VALUE`.
+pub fn set_block_parameter(
+    model: FractalModel,
+    effect: Effect,
+    parameter_id: u32,
+    value: u32,
+) -> Option<MidiMessage> {
+    if model.uses_iii_protocol() || value > MAX_RAW_VALUE {
+        return None;
+    }
+    let effect_id = EffectRegistry::for_model(model).id(effect)?;
+    let (a, b) = encode_effect_id(effect_id);
+    let (c, d) = encode_effect_id(parameter_id);
+    let (e, f, g) = encode_parameter_value(value);
+    let msg = vec![model_code(model), 0x02, a, b, c, d, e, f, g, 0x01];
+    Some(wrap_msg(msg))
 }
 
-pub fn get_block_parameters(model: FractalModel, effect: Effect) -> MidiMessage {
-    let (a, b) = encode_effect_id(id_for_effect(effect));
-    wrap_msg(vec![model_code(model), 0x01, a, b])
+/// Requests a dump of the edit buffer or a stored preset. The reply is a run
+/// of `PresetDumpMessage`s to collect with a `PresetDumpAssembler`.
+pub fn request_preset_dump(model: FractalModel, target: PresetTarget) -> MidiMessage {
+    let [a, b] = preset_dump::encode_dump_target(model, target);
+    wrap_msg(vec![model_code(model), 0x03, a, b])
 }
 
 pub fn store_in_preset(model: FractalModel, preset_number: u32) -> MidiMessage {
     let (a, b) = encode_preset_number(preset_number);
-    if model == FractalModel::III {
+    if model.uses_iii_protocol() {
         // 0xF0, 0x00, 0x01, 0x74, 0x10, 0x01, 0x26, 0x00, 0x00, 0x00, 0x00, 0x00, 0x0F, 0x03,
         // 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x3E, 0xF7
         wrap_msg(vec![
@@ -247,6 +379,12 @@ pub fn store_in_preset(model: FractalModel, preset_number: u32) -> MidiMessage {
     }
 }
 
+/// Requests the current tempo (Axe-Fx III family), answered with
+/// `CurrentTempo`.
+pub fn get_tempo(model: FractalModel) -> MidiMessage {
+    wrap_msg(vec![model_code(model), 0x14, 0x7F, 0x7F])
+}
+
 pub fn set_tempo(model: FractalModel, tempo: u32) -> MidiMessage {
     let (a, b) = encode_preset_number(tempo);
     wrap_msg(vec![model_code(model), 0x14, b, a])
@@ -259,7 +397,7 @@ pub fn set_preset_name(model: FractalModel, preset_number: u32, name: &str) -> M
     // 20  02 01 00 40 20 10 08 04  02 01 00 40 20 10 08 04  |   @       @    |
     // 30  02 01 00 40 20 10 08 04  02 00 64 F7              |   @      d |
     let (a, b) = encode_preset_number(preset_number);
-    let name = encode_preset_name_iii(name);
+    let name = encode_preset_name_iii(&name.chars().take(32).collect::<String>());
     let pad: Vec<u8> = (0..(37 - name.len())).map(|_| 0).collect();
     wrap_msg(
         [
@@ -318,10 +456,7 @@ mod tests {
 
     #[test]
     fn test_checksum() {
-        assert_eq!(
-            0x09,
-            checksum(vec![0xF0, 0x00, 0x01, 0x74, 0x03, 0x0F, 0xF7])
-        );
+        assert_eq!(0x09, checksum(&[0xF0, 0x00, 0x01, 0x74, 0x03, 0x0F, 0xF7]));
     }
 
     #[test]
@@ -381,23 +516,56 @@ mod tests {
             assert_eq!(msg.len(), expected.len());
             assert_eq!(msg, expected, "name: {}", name);
         }
+        assert_eq!(
+            set_preset_name(FractalModel::III, 1, &"x".repeat(32)),
+            set_preset_name(FractalModel::III, 1, &"x".repeat(40))
+        );
     }
 
     #[test]
-    fn test_encode_char_iii() {
+    fn test_septet_pack_byte() {
         assert_eq!(
             (0b00110000, 0b01000000),
-            encode_char_iii(0, 0b00000000, 0b01100001)
+            septet::pack_byte(0, 0b00000000, 0b01100001)
         );
         assert_eq!(
             (0b01011000, 0b00100000),
-            encode_char_iii(1, 0b01000000, 0b01100001)
+            septet::pack_byte(1, 0b01000000, 0b01100001)
         );
         assert_eq!(
             (0b00101100, 0b00010000),
-            encode_char_iii(2, 0b00100000, 0b01100001)
+            septet::pack_byte(2, 0b00100000, 0b01100001)
+        );
+        assert_eq!(
+            (0b01001011, 0b01100001),
+            septet::pack_byte(6, 0b01001010, 0b11100001)
         );
-        // assert_eq!((None, None, None), encode_char_iii(6, 0b01001010, None));
+    }
+
+    #[test]
+    fn test_septet_round_trip() {
+        use rand::Rng;
+        let mut rng = rand::thread_rng();
+        for len in 0..64usize {
+            for _ in 0..32 {
+                let bytes: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+                let packed = septet::pack(&bytes);
+                assert_eq!((len * 8).div_ceil(7), packed.len());
+                assert!(packed.iter().all(|x| *x < 0x80), "{:?}", packed);
+                assert_eq!(bytes, septet::unpack(&packed));
+            }
+        }
+        assert_eq!(vec![0xFF; 7], septet::unpack(&[0x7F; 8]));
+        assert_eq!(Vec::<u8>::new(), septet::unpack(&[0x7F]));
+    }
+
+    #[test]
+    fn test_decode_preset_name_iii() {
+        for name in ["a", "aaaaaaa", "Changed!", "AC-20 12AX7 B", &"x".repeat(32)].iter() {
+            assert_eq!(*name, decode_preset_name_iii(&encode_preset_name_iii(name)));
+            let msg = set_preset_name(FractalModel::III, 389, name);
+            assert_eq!(*name, decode_preset_name_iii(&msg[21..msg.len() - 2]));
+        }
     }
 
     #[test]
@@ -498,237 +666,1986 @@ mod tests {
         );
         assert_eq!(
             FractalMessage::CurrentPresetNumber(236),
-            parse_message(vec![240, 0, 1, 116, 3, 20, 1, 108, 121, 247])
+            parse_message(vec![240, 0, 1, 116, 3, 20, 1, 108, 127, 247])
         );
     }
 
     #[test]
-    fn test_get_current_preset_name() {
+    fn test_try_parse_message_errors() {
+        assert_eq!(Err(ParseError::MissingHeader), try_parse_message(&[]));
         assert_eq!(
-            vec![
-                0xF0,
-                0x00,
-                0x01,
-                0x74,
-                model_code(FractalModel::II),
-                0x0F,
-                9,
-                0xF7
-            ],
-            get_current_preset_name(FractalModel::II)
+            Err(ParseError::MissingHeader),
+            try_parse_message(&[240, 0, 1, 116])
         );
-    }
-
-    #[test]
-    fn test_get_current_preset_name_axe_3() {
         assert_eq!(
-            vec![0xF0, 0x00, 0x01, 0x74, 0x10, 0x0D, 0x7F, 0x7F, 24, 0xF7],
-            get_current_preset_name(FractalModel::III)
+            Err(ParseError::MissingHeader),
+            try_parse_message(&[240, 0, 2, 116, 3, 20, 1, 107, 120, 247])
+        );
+        assert_eq!(
+            Err(ParseError::UnknownModel(0x7E)),
+            try_parse_message(&[240, 0, 1, 116, 0x7E, 20, 1, 107, 120, 247])
+        );
+        assert_eq!(
+            Err(ParseError::UnknownFunction {
+                model: FractalModel::II,
+                function_id: 0x7A
+            }),
+            try_parse_message(&[240, 0, 1, 116, 3, 0x7A, 124, 247])
+        );
+        assert_eq!(
+            Err(ParseError::PayloadTooShort {
+                function_id: 0x14,
+                expected: 2,
+                actual: 1
+            }),
+            try_parse_message(&[240, 0, 1, 116, 3, 20, 1, 19, 247])
+        );
+        assert_eq!(
+            Err(ParseError::PayloadTooShort {
+                function_id: 0x01,
+                expected: 7,
+                actual: 3
+            }),
+            try_parse_message(&[240, 0, 1, 116, 3, 1, 106, 0, 0, 109, 247])
+        );
+        assert_eq!(
+            Err(ParseError::PayloadTooShort {
+                function_id: 0x20,
+                expected: 192,
+                actual: 8
+            }),
+            try_parse_message(&[240, 0, 1, 116, 3, 32, 0, 0, 0, 0, 127, 0, 2, 0, 91, 247])
+        );
+        assert_eq!(
+            Err(ParseError::Truncated),
+            try_parse_message(&[240, 0, 1, 116, 3, 20, 1])
+        );
+        assert_eq!(
+            Err(ParseError::InvalidDataByte {
+                index: 7,
+                byte: 0xF8
+            }),
+            try_parse_message(&[240, 0, 1, 116, 3, 20, 1, 0xF8, 120, 247])
+        );
+        assert_eq!(
+            Err(ParseError::InvalidChannel(5)),
+            try_parse_message(&[240, 0, 1, 116, 16, 19, 37, 0, 0b00011010, 57, 247])
         );
     }
 
     #[test]
-    fn test_set_current_preset_name() {
+    fn test_try_parse_message_rejects_bad_checksum() {
         assert_eq!(
-            vec![
-                0xF0,
-                0x00,
-                0x01,
-                0x74,
-                model_code(FractalModel::II),
-                0x09,
-                0x43,
-                0x68,
-                0x61,
-                0x6E,
-                0x67,
-                0x65,
-                0x64,
-                0x21,
-                0x20,
-                0x20,
-                0x20,
-                0x20,
-                0x20,
-                0x20,
-                0x20,
-                0x20,
-                0x20,
-                0x20,
-                0x20,
-                0x20,
-                0x20,
-                0x20,
-                0x20,
-                0x20,
-                0x20,
-                0x20,
-                0x20,
-                0x20,
-                0x20,
-                0x20,
-                0x20,
-                0x20,
-                0x6C,
-                0xF7
-            ],
-            set_current_preset_name(FractalModel::II, "Changed!")
+            Err(ParseError::ChecksumMismatch {
+                expected: 120,
+                actual: 121
+            }),
+            try_parse_message(&[240, 0, 1, 116, 3, 20, 1, 107, 121, 247])
         );
         assert_eq!(
-            set_current_preset_name(FractalModel::II, "O Praise The Name (Anstasis)"),
-            set_current_preset_name(FractalModel::II, "O Praise The Name (Anástasis)")
+            Err(ParseError::MissingChecksum),
+            try_parse_message(&[240, 0, 1, 116, 3, 20, 247])
         );
     }
 
     #[test]
-    fn test_parse_current_preset_name() {
+    fn test_sysex_frame() {
+        let msg = vec![240, 0, 1, 116, 3, 20, 1, 107, 120, 247];
+        let frame = SysexFrame::parse(&msg).unwrap();
         assert_eq!(
-            parse_message(vec![
-                240, 0, 1, 116, 3, 15, 66, 83, 32, 65, 67, 50, 48, 32, 66, 97, 115, 101, 32, 32,
-                32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 0, 13, 247
-            ]),
-            FractalMessage::CurrentPresetName("BS AC20 Base".to_string())
+            SysexFrame {
+                model: FractalModel::II,
+                function_id: 0x14,
+                payload: vec![1, 107],
+                checksum: Some(120),
+            },
+            frame
+        );
+        assert_eq!(Ok(()), frame.verify());
+        assert_eq!(msg, frame.to_message());
+        assert_eq!(frame, SysexFrame::new(FractalModel::II, 0x14, vec![1, 107]));
+        assert_eq!(
+            get_preset_number(FractalModel::III),
+            SysexFrame::new(FractalModel::III, 0x14, vec![]).to_message()
         );
     }
 
     #[test]
-    fn test_parse_preset_name() {
-        assert_eq!(
-            parse_message(vec![
-                240, 0, 1, 116, 16, 13, 15, 3, 66, 83, 32, 65, 67, 50, 48, 32, 66, 97, 115, 101,
-                32, 83, 67, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 0, 0,
-                247
-            ]),
-            FractalMessage::PresetName(399, "BS AC20 Base SC".to_string())
-        );
+    fn test_sysex_frame_without_checksum() {
+        let frame = SysexFrame::parse(&[240, 0, 1, 116, 3, 0x0D, 1, 2, 63, 0xF7]).unwrap();
+        assert_eq!(vec![1, 2, 63], frame.payload);
+        assert_eq!(None, frame.checksum);
+        assert_eq!(Ok(()), frame.verify());
     }
 
     #[test]
-    fn test_parse_scene_name() {
+    fn test_verify_and_strip_checksum() {
         assert_eq!(
-            parse_message(vec![
-                240, 0, 1, 116, 16, 14, 0, 67, 108, 101, 97, 110, 32, 32, 32, 32, 32, 32, 32, 32,
-                32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 0, 94, 247
-            ]),
-            FractalMessage::SceneName(0, "Clean".to_string())
+            Ok(()),
+            verify_checksum(&[240, 0, 1, 116, 3, 20, 1, 107, 120, 247])
         );
         assert_eq!(
-            parse_message(vec![
-                240, 0, 1, 116, 16, 14, 1, 79, 68, 49, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32,
-                32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 0, 32, 247
-            ]),
-            FractalMessage::SceneName(1, "OD1".to_string())
+            Err(ParseError::ChecksumMismatch {
+                expected: 120,
+                actual: 0
+            }),
+            verify_checksum(&[240, 0, 1, 116, 3, 20, 1, 107, 0, 247])
+        );
+        assert_eq!(
+            Ok(vec![240, 0, 1, 116, 3, 20, 1, 107, 247]),
+            strip_checksum(&[240, 0, 1, 116, 3, 20, 1, 107, 120, 247])
         );
+        assert_eq!(Err(ParseError::MissingHeader), strip_checksum(&[]));
+        assert_eq!(0, checksum(&[]));
+        assert_eq!(vec![0, 0xF7], with_checksum(vec![]));
     }
 
     #[test]
-    fn test_get_firmware_version() {
+    fn test_sysex_assembler_chunks() {
+        let mut assembler = SysexAssembler::new();
+        assert_eq!(Vec::<MidiMessage>::new(), assembler.push(&[240, 0, 1]));
+        assert_eq!(Vec::<MidiMessage>::new(), assembler.push(&[116, 3, 20, 1]));
         assert_eq!(
             vec![
-                0xF0,
-                0x00,
-                0x01,
-                0x74,
-                model_code(FractalModel::II),
-                0x08,
-                14,
-                0xF7
+                vec![240, 0, 1, 116, 3, 20, 1, 107, 120, 247],
+                vec![240, 0, 1, 116, 3, 0x10, 247]
             ],
-            get_firmware_version(FractalModel::II)
+            assembler.push(&[107, 120, 247, 240, 0, 1, 116, 3, 0x10, 247, 240, 0])
+        );
+        assert_eq!(
+            vec![FractalMessage::CurrentPresetNumber(235)],
+            assembler.push_fractal(&[1, 116, 3, 20, 1, 107, 120, 247])
         );
     }
 
     #[test]
-    fn test_parse_firmware_version() {
+    fn test_sysex_assembler_realtime_inside_sysex() {
+        let mut assembler = SysexAssembler::new();
         assert_eq!(
-            parse_message(vec![240, 0, 1, 116, 3, 8, 0x08, 0x02, 0, 0, 0, 0, 0, 247]),
-            FractalMessage::FirmwareVersion { major: 8, minor: 2 }
+            vec![
+                vec![0xF8],
+                vec![0xFE],
+                vec![240, 0, 1, 116, 3, 20, 1, 107, 120, 247]
+            ],
+            assembler.push(&[240, 0, 1, 0xF8, 116, 3, 20, 0xFE, 1, 107, 120, 247])
         );
+        assert_eq!(0, assembler.dropped_frames());
     }
 
     #[test]
-    fn test_disconnect_from_controller() {
+    fn test_sysex_assembler_running_status() {
+        let mut assembler = SysexAssembler::new();
         assert_eq!(
             vec![
-                0xF0,
-                0x00,
-                0x01,
-                0x74,
-                model_code(FractalModel::II),
-                0x42,
-                68,
-                0xF7
+                vec![0xB0, 15, 127],
+                vec![0xB0, 16, 0],
+                vec![0xC0, 5],
+                vec![0xC0, 6],
+                vec![240, 0, 1, 116, 3, 0x10, 247],
+                vec![0xB1, 1, 2]
             ],
-            disconnect_from_controller(FractalModel::II)
+            assembler.push(&[
+                0xB0, 15, 127, 16, 0, 0xC0, 5, 6, 240, 0, 1, 116, 3, 0x10, 247, 0xB1, 1, 2
+            ])
+        );
+        // running status is cancelled by SysEx, so these data bytes are dropped
+        assert_eq!(
+            vec![vec![240, 0, 1, 116, 3, 0x10, 247]],
+            assembler.push(&[240, 0, 1, 116, 3, 0x10, 247, 3, 4])
         );
     }
 
     #[test]
-    fn test_parse_front_panel_change_detected() {
+    fn test_sysex_assembler_system_common() {
+        let mut assembler = SysexAssembler::new();
+        assert_eq!(vec![vec![0xF2, 1, 2]], assembler.push(&[0xF2, 1, 2]));
+        assert_eq!(vec![vec![0xF3, 5]], assembler.push(&[0xF3, 5]));
         assert_eq!(
-            parse_message(vec![240, 0, 1, 116, 3, 0x21, 0, 0xF7]),
-            FractalMessage::FrontPanelChangeDetected
+            vec![vec![0xF8], vec![0xF1, 0x23]],
+            assembler.push(&[0xF1, 0xF8, 0x23])
         );
+        // system common leaves no running status, so this data byte is dropped
+        assert_eq!(vec![vec![0xF3, 5]], assembler.push(&[0xF3, 5, 6]));
     }
 
     #[test]
-    fn test_parse_midi_tempo_beat() {
+    fn test_sysex_assembler_interrupted_frame() {
+        let mut assembler = SysexAssembler::new();
         assert_eq!(
-            parse_message(vec![240, 0, 1, 116, 3, 0x10, 0xF7]),
-            FractalMessage::MIDITempoBeat
+            vec![vec![240, 0, 1, 116, 3, 0x10, 247]],
+            assembler.push(&[240, 0, 1, 116, 3, 20, 240, 0, 1, 116, 3, 0x10, 247])
+        );
+        assert_eq!(1, assembler.dropped_frames());
+        assert_eq!(
+            vec![vec![0x90, 60, 100]],
+            assembler.push(&[240, 0, 1, 0x90, 60, 100])
         );
+        assert_eq!(2, assembler.dropped_frames());
     }
 
     #[test]
-    fn test_get_midi_channel() {
+    fn test_sysex_assembler_max_length() {
+        let mut assembler = SysexAssembler::with_max_length(8);
         assert_eq!(
-            vec![240, 0, 1, 116, 3, 0x17, 17, 0xF7],
-            get_midi_channel(FractalModel::II)
+            vec![vec![240, 0, 1, 116, 3, 0x10, 0, 247]],
+            assembler.push(&[240, 0, 1, 116, 3, 0x10, 0, 247])
+        );
+        assert_eq!(
+            Vec::<MidiMessage>::new(),
+            assembler.push(&[240, 0, 1, 116, 3, 20, 1, 107, 120, 247])
+        );
+        assert_eq!(1, assembler.dropped_frames());
+        assert_eq!(
+            vec![vec![240, 0, 1, 116, 3, 0x10, 247]],
+            assembler.push(&[240, 0, 1, 116, 3, 0x10, 247])
         );
     }
 
     #[test]
-    fn test_parse_midi_channel() {
+    fn test_session_current_preset_name() {
+        let mut transport = MockTransport::new();
+        transport.respond_with(|msg| {
+            assert_eq!(get_current_preset_name(FractalModel::II), msg.to_vec());
+            vec![
+                vec![240, 0, 1, 116, 3, 0x10, 247],
+                vec![
+                    240, 0, 1, 116, 3, 15, 66, 83, 32, 65, 67, 50, 48, 32, 66, 97, 115, 101, 32,
+                    32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 0, 13,
+                    247,
+                ],
+            ]
+        });
+        let mut session = Session::new(transport, FractalModel::II);
+        assert_eq!("BS AC20 Base", session.current_preset_name().unwrap());
         assert_eq!(
-            parse_message(vec![240, 0, 1, 116, 3, 0x17, 9, 0, 0xF7]),
-            FractalMessage::MIDIChannel(10)
+            vec![FractalMessage::MIDITempoBeat],
+            session.take_unsolicited()
         );
+        assert_eq!(1, session.transport().sent.len());
     }
 
     #[test]
-    fn test_parse_tuner_info() {
+    fn test_session_scene_name_in_chunks() {
+        let mut transport = MockTransport::new();
+        transport.queue_incoming(&[240, 0, 1, 116, 16, 14, 1, 79, 68, 49, 32, 32, 32]);
+        transport.queue_incoming(&[
+            32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32,
+            32, 32, 32, 0, 32, 247,
+        ]);
+        let mut session = Session::new(transport, FractalModel::III);
+        assert_eq!("OD1", session.scene_name(1).unwrap());
         assert_eq!(
-            parse_message(vec![240, 0, 1, 116, 3, 0x0D, 1, 2, 63, 0xF7]),
-            FractalMessage::TunerInfo {
-                note: 1,
-                string_number: 2,
-                tuner_data: 63
-            }
+            vec![get_scene_name(FractalModel::III, 1)],
+            session.into_transport().sent
         );
     }
 
     #[test]
-    fn test_toggle_tuner() {
-        assert_eq!(vec![176, 15, 0], toggle_tuner(1, TunerStatus::Off));
-        assert_eq!(vec![177, 15, 0], toggle_tuner(2, TunerStatus::Off));
-        assert_eq!(vec![176, 15, 127], toggle_tuner(1, TunerStatus::On));
+    fn test_session_preset_number_axe_3() {
+        let mut transport = MockTransport::new();
+        transport.queue_incoming(&[
+            240, 0, 1, 116, 16, 13, 15, 3, 66, 83, 32, 65, 67, 50, 48, 32, 66, 97, 115, 101, 32,
+            83, 67, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 0, 0, 247,
+        ]);
+        let mut session = Session::new(transport, FractalModel::III);
+        assert_eq!(399, session.current_preset_number().unwrap());
     }
 
     #[test]
-    fn test_toggle_metronome() {
-        assert_eq!(vec![176, 122, 0], toggle_metronome(1, MetronomeStatus::Off));
-        assert_eq!(vec![177, 122, 0], toggle_metronome(2, MetronomeStatus::Off));
-        assert_eq!(
-            vec![176, 122, 127],
-            toggle_metronome(1, MetronomeStatus::On)
-        );
+    fn test_session_errors() {
+        let mut session = Session::new(MockTransport::new(), FractalModel::II);
+        match session.current_preset_number() {
+            Err(SessionError::Timeout) => {}
+            other => panic!("expected timeout, got {:?}", other),
+        }
+        match session.scene_name(1) {
+            Err(SessionError::Unsupported) => {}
+            other => panic!("expected unsupported, got {:?}", other),
+        }
+        session.transport_mut().respond_with(|msg| {
+            vec![SysexFrame::new(FractalModel::II, 0x64, vec![msg[5], 5]).to_message()]
+        });
+        match session.midi_channel() {
+            Err(SessionError::Rejected {
+                function_id: 0x17,
+                response_code: 5,
+            }) => {}
+            other => panic!("expected rejection, got {:?}", other),
+        }
+    }
+
+    fn drain(simulator: &mut simulator::Simulator) -> Vec<FractalMessage> {
+        let mut messages = vec![];
+        loop {
+            let msg = simulator
+                .receive(std::time::Duration::from_secs(0))
+                .unwrap();
+            if msg.is_empty() {
+                return messages;
+            }
+            assert_eq!(Ok(()), verify_checksum(&msg));
+            messages.push(parse_message(msg));
+        }
     }
 
     #[test]
-    fn test_get_preset_blocks_flags() {
+    fn test_simulator_session_axe_2() {
+        let mut device = simulator::Simulator::new(FractalModel::II);
+        device.preset_number = 235;
+        device.current_preset_name = "BS AC20 Base".to_string();
+        device.firmware_version = (8, 2);
+        device.midi_channel = 10;
+        let mut session = Session::new(device, FractalModel::II);
+        assert_eq!(235, session.current_preset_number().unwrap());
+        assert_eq!("BS AC20 Base", session.current_preset_name().unwrap());
+        assert_eq!((8, 2), session.firmware_version().unwrap());
+        assert_eq!(10, session.midi_channel().unwrap());
+        assert_eq!(3, session.set_scene_number(2).unwrap());
+        assert_eq!(2, session.transport().scene);
+
+        session
+            .send(&set_current_preset_name(FractalModel::II, "Changed!"))
+            .unwrap();
+        session
+            .send(&store_in_preset(FractalModel::II, 217))
+            .unwrap();
+        session
+            .send(&set_preset_number(FractalModel::II, 217))
+            .unwrap();
+        let device = session.transport_mut();
         assert_eq!(
-            vec![240, 0, 1, 116, 3, 0x0E, 8, 0xF7],
-            get_preset_blocks_flags(FractalModel::II)
+            vec![
+                FractalMessage::MultipurposeResponse {
+                    function_id: 0x09,
+                    response_code: 0
+                },
+                FractalMessage::MultipurposeResponse {
+                    function_id: 0x1D,
+                    response_code: 0
+                },
+                FractalMessage::CurrentPresetNumber(217)
+            ],
+            drain(device)
+        );
+        assert_eq!("Changed!", device.preset_names[217]);
+        assert_eq!(0, device.scene);
+    }
+
+    #[test]
+    fn test_simulator_session_axe_3() {
+        let mut device = simulator::Simulator::new(FractalModel::III);
+        device.preset_number = 399;
+        device.current_preset_name = "BS AC20 Base SC".to_string();
+        device.scene_names[1] = "OD1".to_string();
+        device.effects = vec![EffectStatus {
+            effect_id: 58,
+            effect_id_iii: Some(EffectID::ID_DISTORT1),
+            effect: Effect::Amp1,
+            bypassed: true,
+            channel: Channel::C,
+            max_channels: 4,
+        }];
+        let mut session = Session::new(device, FractalModel::III);
+        assert_eq!(399, session.current_preset_number().unwrap());
+        assert_eq!("BS AC20 Base SC", session.current_preset_name().unwrap());
+        assert_eq!(1, session.set_scene_number(1).unwrap());
+        assert_eq!("OD1", session.current_scene_name().unwrap());
+        let status = session.status().unwrap();
+        assert_eq!(session.transport().effects, status);
+
+        session
+            .send(&set_preset_name(FractalModel::III, 389, "Changed!"))
+            .unwrap();
+        session.send(&set_tempo(FractalModel::III, 137)).unwrap();
+        let device = session.transport_mut();
+        assert_eq!(
+            vec![
+                FractalMessage::MultipurposeResponse {
+                    function_id: 0x01,
+                    response_code: 0
+                },
+                FractalMessage::CurrentTempo(137)
+            ],
+            drain(device)
+        );
+        assert_eq!("Changed!", device.preset_names[389]);
+
+        device.handle(&set_current_preset_name(FractalModel::III, "Edited"));
+        device.handle(&store_in_preset(FractalModel::III, 12));
+        device.handle(&toggle_tuner_sysex(FractalModel::III, TunerStatus::On));
+        device.handle(&set_looper_state(FractalModel::III, LooperState::Record));
+        device.handle(&set_looper_state(FractalModel::III, LooperState::Record));
+        device.handle(&get_looper_state(FractalModel::III));
+        drain(device);
+        assert_eq!("Edited", device.preset_names[0]);
+        assert_eq!(TunerStatus::On, device.tuner);
+        assert_eq!(
+            vec![FractalMessage::LooperState {
+                record: false,
+                play: true,
+                overdub: true,
+                once: false,
+                reverse: false,
+                half_speed: false
+            }],
+            {
+                device.handle(&set_looper_state(FractalModel::III, LooperState::Record));
+                drain(device)
+            }
+        );
+    }
+
+    #[test]
+    fn test_looper_commands() {
+        use looper::{Looper, LooperCommand::*, LooperError, LooperPhase};
+        let model = FractalModel::III;
+        let codes = |states: Vec<LooperState>| -> Vec<MidiMessage> {
+            states
+                .into_iter()
+                .map(|state| set_looper_state(model, state))
+                .collect()
+        };
+
+        let mut looper = Looper::new(model);
+        assert_eq!(LooperPhase::Empty, looper.phase());
+        assert_eq!(
+            Err(LooperError::InvalidCommand {
+                command: Play,
+                phase: LooperPhase::Empty,
+            }),
+            looper.command(Play)
+        );
+        assert_eq!(Ok(codes(vec![LooperState::Record])), looper.command(Record));
+        assert_eq!(LooperPhase::Recording, looper.phase());
+        assert!(looper.command(Record).is_err());
+        assert_eq!(
+            Ok(codes(vec![LooperState::Record, LooperState::Record])),
+            looper.command(Overdub)
+        );
+        assert_eq!(
+            (LooperPhase::Overdubbing, 2),
+            (looper.phase(), looper.layers())
+        );
+        assert_eq!(Ok(codes(vec![LooperState::Play])), looper.command(Play));
+        assert_eq!(Ok(codes(vec![LooperState::Undo])), looper.command(Undo));
+        assert_eq!((LooperPhase::Playing, 1), (looper.phase(), looper.layers()));
+        assert_eq!(
+            Ok(codes(vec![LooperState::Reverse])),
+            looper.command(Reverse)
+        );
+        assert!(looper.reverse);
+        assert_eq!(
+            Ok(codes(vec![LooperState::Record])),
+            looper.command(Overdub)
+        );
+        assert!(looper.command(Clear).is_err());
+        assert_eq!(
+            Ok(codes(vec![LooperState::Play, LooperState::Play])),
+            looper.command(Stop)
+        );
+        assert_eq!(LooperPhase::Stopped, looper.phase());
+        assert!(looper.command(Stop).is_err());
+        assert_eq!(
+            Ok(codes(vec![LooperState::Undo, LooperState::Undo])),
+            looper.command(Clear)
+        );
+        assert_eq!((LooperPhase::Empty, 0), (looper.phase(), looper.layers()));
+        assert!(looper.command(HalfSpeed).is_err());
+
+        assert_eq!(
+            Err(LooperError::Unsupported(FractalModel::II)),
+            Looper::new(FractalModel::II).command(Record)
+        );
+
+        let mut looper = Looper::new(model);
+        assert_eq!(
+            Ok(vec![
+                (ms(0), set_looper_state(model, LooperState::Record)),
+                (ms(2000), set_looper_state(model, LooperState::Play)),
+            ]),
+            looper.record_beats(4, 120.0)
+        );
+        assert_eq!(LooperPhase::Recording, looper.phase());
+
+        let mut looper = Looper::new(model);
+        for bpm in [0.0, -120.0, f64::NAN, tempo::MAX_TEMPO + 1.0] {
+            match looper.record_beats(4, bpm) {
+                Err(LooperError::InvalidTempo(_)) => {}
+                other => panic!("expected InvalidTempo, got {:?}", other),
+            }
+        }
+        assert_eq!(LooperPhase::Empty, looper.phase());
+    }
+
+    #[test]
+    fn test_looper_follows_device() {
+        use looper::{Looper, LooperPhase};
+        let state = |record, play, overdub| FractalMessage::LooperState {
+            record,
+            play,
+            overdub,
+            once: false,
+            reverse: false,
+            half_speed: true,
+        };
+        let mut looper = Looper::new(FractalModel::III);
+        assert_eq!(None, looper.handle(&state(false, false, false)));
+        assert_eq!(
+            Some(LooperPhase::Recording),
+            looper.handle(&state(true, false, false))
+        );
+        assert!(looper.half_speed);
+        assert_eq!(
+            Some(LooperPhase::Playing),
+            looper.handle(&state(false, true, false))
+        );
+        assert_eq!(None, looper.handle(&state(false, true, false)));
+        assert_eq!(
+            Some(LooperPhase::Overdubbing),
+            looper.handle(&state(false, true, true))
+        );
+        assert_eq!(2, looper.layers());
+        assert_eq!(
+            Some(LooperPhase::Stopped),
+            looper.handle(&state(false, false, false))
+        );
+        assert_eq!(None, looper.handle(&FractalMessage::MIDITempoBeat));
+    }
+
+    #[test]
+    fn test_session_looper() {
+        use looper::{Looper, LooperCommand::*, LooperError, LooperPhase};
+        let model = FractalModel::III;
+        let mut session = Session::new(simulator::Simulator::new(model), model);
+        let mut looper = Looper::new(model);
+        match session.record_loop(&mut looper, 1, 0.0) {
+            Err(SessionError::Looper(LooperError::InvalidTempo(_))) => {}
+            other => panic!("expected an invalid tempo, got {:?}", other),
+        }
+        assert_eq!(LooperPhase::Empty, looper.phase());
+        assert_eq!(
+            LooperPhase::Playing,
+            session
+                .record_loop(&mut looper, 1, tempo::MAX_TEMPO)
+                .unwrap()
+        );
+        assert_eq!(
+            LooperPhase::Overdubbing,
+            session.looper_command(&mut looper, Overdub).unwrap()
+        );
+        assert_eq!(
+            LooperPhase::Stopped,
+            session.looper_command(&mut looper, Stop).unwrap()
+        );
+        assert_eq!(0, session.transport().looper);
+        match session.looper_command(&mut looper, Record) {
+            Err(SessionError::Looper(LooperError::InvalidCommand {
+                command: Record,
+                phase: LooperPhase::Stopped,
+            })) => {}
+            other => panic!("expected an invalid command, got {:?}", other),
+        }
+
+        // Someone started an overdub from the unit; Play drops out of it
+        // and the looper follows the device rather than its own guess.
+        session.transport_mut().looper = 0b110;
+        assert_eq!(
+            LooperPhase::Playing,
+            session.looper_command(&mut looper, Play).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_simulator_grid_and_parameters() {
+        let mut device = simulator::Simulator::new(FractalModel::II);
+        device
+            .grid
+            .set(
+                1,
+                2,
+                BlockGridBlock::EffectBlock {
+                    effect_id: 106,
+                    effect: Effect::Amp1,
+                    connections: 0b10,
+                },
+            )
+            .unwrap();
+        device.parameters.insert((106, 1), 13387);
+        device.parameters.insert((106, 2), 25454);
+        device.handle(&get_grid_layout_and_routing(FractalModel::II));
+        device.handle(&get_block_parameters(FractalModel::II, Effect::Amp1).unwrap());
+        let messages = drain(&mut device);
+        assert_eq!(3, messages.len());
+        match &messages[0] {
+            FractalMessage::BlockGrid(grid) => assert_eq!(device.grid, *grid),
+            other => panic!("expected block grid, got {:?}", other),
+        }
+        assert_eq!(
+            FractalMessage::BlockParameters {
+                effect_id: 106,
+                effect: Effect::Amp1,
+                parameter_id: 2,
+                parameter: Parameter::Bass,
+                value_raw: 25454
+            },
+            messages[2]
+        );
+    }
+
+    #[test]
+    fn test_simulator_unsolicited_messages() {
+        let mut device = simulator::Simulator::new(FractalModel::II);
+        device.inject_front_panel_change();
+        device.inject_tempo_beat();
+        device.inject_tuner_info(1, 2, 63);
+        device.preset_number = 5;
+        let mut session = Session::new(device, FractalModel::II);
+        assert_eq!(5, session.current_preset_number().unwrap());
+        assert_eq!(
+            vec![
+                FractalMessage::FrontPanelChangeDetected,
+                FractalMessage::MIDITempoBeat,
+                FractalMessage::TunerInfo {
+                    note: 1,
+                    string_number: 2,
+                    tuner_data: 63
+                }
+            ],
+            session.take_unsolicited()
+        );
+        session.transport_mut().inject_tempo_beat();
+        assert_eq!(vec![FractalMessage::MIDITempoBeat], session.poll().unwrap());
+    }
+
+    #[test]
+    fn test_parameter_catalog() {
+        assert_eq!(Some(BlockType::Reverb), Effect::Reverb2.block_type());
+        assert_eq!(None, Effect::Tuner.block_type());
+
+        let amp = parameters_for_block(BlockType::Amp);
+        assert_eq!(
+            vec![
+                Parameter::EffectType,
+                Parameter::InputDrive,
+                Parameter::Bass,
+                Parameter::Middle,
+                Parameter::Treble,
+                Parameter::MasterVolume,
+                Parameter::PreampLowCut,
+                Parameter::HighCutFrequency,
+            ],
+            amp[..8]
+                .iter()
+                .map(|info| info.parameter)
+                .collect::<Vec<_>>()
+        );
+        for block in [
+            BlockType::Amp,
+            BlockType::Cab,
+            BlockType::Compressor,
+            BlockType::Delay,
+            BlockType::Drive,
+            BlockType::GraphicEQ,
+            BlockType::ParametricEQ,
+            BlockType::Reverb,
+        ]
+        .iter()
+        {
+            let parameters = parameters_for_block(*block);
+            assert!(!parameters.is_empty(), "{:?} has no parameters", block);
+            assert!(
+                parameters.windows(2).all(|pair| pair[0].id < pair[1].id),
+                "{:?} parameters are not in ID order",
+                block
+            );
+        }
+
+        let bass = parameter_info(Effect::Amp1, 2).unwrap();
+        assert_eq!("Bass", bass.name);
+        assert_eq!(0.0, bass.display_value(0));
+        assert_eq!(10.0, bass.display_value(MAX_RAW_VALUE));
+        assert_eq!(None, bass.choice(0));
+
+        let bypass_mode = parameter_info(Effect::Delay1, 7).unwrap();
+        assert_eq!(Parameter::BypassMode, bypass_mode.parameter);
+        assert_eq!(Some("Mute Out"), bypass_mode.choice(2));
+        assert_eq!(None, bypass_mode.choice(99));
+
+        assert_eq!(None, parameter_info(Effect::Amp1, 1000));
+        assert_eq!(None, parameter_info(Effect::Unknown(0), 0));
+
+        // Their own tables differ from the Chorus and Delay blocks'.
+        assert!(parameters_for_block(BlockType::QuadChorus).is_empty());
+        assert!(parameters_for_block(BlockType::PlexDelay).is_empty());
+        for id in 0..20 {
+            assert_eq!(None, parameter_info(Effect::QuadChorus1, id));
+            assert_eq!(None, parameter_info(Effect::PlexDelay1, id));
+        }
+        let quad_chorus = SysexFrame::new(
+            FractalModel::II,
+            0x01,
+            vec![28, 1, 1, 0, 0, 0, 2, 0, 0, 0, 0, 0],
+        );
+        match parse_message(quad_chorus.to_message()) {
+            FractalMessage::BlockParameters {
+                effect: Effect::QuadChorus1,
+                parameter,
+                ..
+            } => assert_eq!(Parameter::Unknown, parameter),
+            other => panic!("expected QuadChorus1 parameters, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_parse_block_parameters_for_other_blocks() {
+        let reverb_time = SysexFrame::new(
+            FractalModel::II,
+            0x01,
+            vec![110, 0, 1, 0, 0, 0, 2, 0, 0, 0, 0, 0],
+        );
+        assert_eq!(
+            FractalMessage::BlockParameters {
+                effect_id: 110,
+                effect: Effect::Reverb1,
+                parameter_id: 1,
+                parameter: Parameter::Time,
+                value_raw: 32768
+            },
+            parse_message(reverb_time.to_message())
+        );
+        let eq_band = SysexFrame::new(
+            FractalModel::II,
+            0x01,
+            vec![102, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+        );
+        assert_eq!(
+            FractalMessage::BlockParameters {
+                effect_id: 102,
+                effect: Effect::GraphicEQ1,
+                parameter_id: 4,
+                parameter: Parameter::BandGain(5),
+                value_raw: 0
+            },
+            parse_message(eq_band.to_message())
+        );
+    }
+
+    #[test]
+    fn test_parameter_value_round_trip() {
+        for value in 0..=0xFFFF {
+            let (a, b, c) = encode_parameter_value(value);
+            assert!(a < 0x80 && b < 0x80 && c < 0x80);
+            assert_eq!(value, decode_parameter_value(a, b, c));
+        }
+        assert_eq!((0x7F, 0x7F, 0x7F), encode_parameter_value(0x1FFFFF));
+    }
+
+    #[test]
+    fn test_set_block_parameter() {
+        assert_eq!(
+            Some(vec![
+                0xF0, 0x00, 0x01, 0x74, 0x03, 0x02, 106, 0, 2, 0, 110, 70, 1, 0x01, 0x44, 0xF7
+            ]),
+            set_block_parameter(FractalModel::II, Effect::Amp1, 2, 25454)
+        );
+        assert_eq!(
+            None,
+            set_block_parameter(FractalModel::III, Effect::Amp1, 2, 25454)
+        );
+        for value in 0..=MAX_RAW_VALUE {
+            let msg = set_block_parameter(FractalModel::II, Effect::Delay1, 1, value).unwrap();
+            assert_eq!(Ok(()), verify_checksum(&msg));
+            assert_eq!(value, decode_parameter_value(msg[10], msg[11], msg[12]));
+        }
+        assert_eq!(
+            None,
+            set_block_parameter(FractalModel::II, Effect::Delay1, 1, MAX_RAW_VALUE + 1)
+        );
+        assert_eq!(
+            None,
+            set_block_parameter(FractalModel::II, Effect::Amp3, 1, 0)
+        );
+    }
+
+    #[test]
+    fn test_parse_block_parameter_value() {
+        assert_eq!(
+            FractalMessage::BlockParameterValue {
+                effect_id: 106,
+                effect: Effect::Amp1,
+                parameter_id: 1,
+                parameter: Parameter::InputDrive,
+                value_raw: 13387,
+                label: "2.04".to_string()
+            },
+            parse_message(
+                SysexFrame::new(
+                    FractalModel::II,
+                    0x02,
+                    vec![106, 0, 1, 0, 75, 104, 0, 25, 0, 0, 0, 0, 50, 46, 48, 52, 0]
+                )
+                .to_message()
+            )
+        );
+        assert_eq!(
+            FractalMessage::BlockParameterValue {
+                effect_id: 58,
+                effect: Effect::Amp1,
+                parameter_id: 2,
+                parameter: Parameter::Bass,
+                value_raw: 25454,
+                label: String::new()
+            },
+            parse_message(
+                SysexFrame::new(FractalModel::III, 0x02, vec![58, 0, 2, 0, 110, 70, 1])
+                    .to_message()
+            )
+        );
+    }
+
+    #[test]
+    fn test_session_set_block_parameter() {
+        let mut session = Session::new(
+            simulator::Simulator::new(FractalModel::II),
+            FractalModel::II,
+        );
+        assert_eq!(
+            (MAX_RAW_VALUE, "10.00".to_string()),
+            session
+                .set_block_parameter(Effect::Amp1, 2, MAX_RAW_VALUE)
+                .unwrap()
+        );
+        assert_eq!(
+            (2, "Mute Out".to_string()),
+            session.set_block_parameter(Effect::Delay1, 7, 2).unwrap()
+        );
+        assert_eq!(
+            Some(&MAX_RAW_VALUE),
+            session.transport().parameters.get(&(106, 2))
+        );
+        match session.set_block_parameter(Effect::Amp1, 1000, 0) {
+            Err(SessionError::Rejected {
+                function_id: 0x02,
+                response_code: 1,
+            }) => {}
+            other => panic!("expected rejection, got {:?}", other),
+        }
+        match session.set_block_parameter(Effect::Amp1, 2, MAX_RAW_VALUE + 1) {
+            Err(SessionError::InvalidValue(value)) => assert_eq!(MAX_RAW_VALUE + 1, value),
+            other => panic!("expected an invalid value, got {:?}", other),
+        }
+        match session.set_block_parameter(Effect::Amp3, 2, 0) {
+            Err(SessionError::UnknownEffect(Effect::Amp3)) => {}
+            other => panic!("expected an unknown effect, got {:?}", other),
+        }
+        let iii = FractalModel::III;
+        match Session::new(simulator::Simulator::new(iii), iii).set_block_parameter(
+            Effect::Amp1,
+            2,
+            0,
+        ) {
+            Err(SessionError::Unsupported) => {}
+            other => panic!("expected unsupported, got {:?}", other),
+        }
+        assert!(!session.transport().parameters.contains_key(&(0, 2)));
+    }
+
+    #[test]
+    fn test_block_bypass_and_channel_builders() {
+        assert_eq!(
+            SysexFrame::new(FractalModel::III, 0x0A, vec![58, 0, 0x7F]).to_message(),
+            get_block_bypass(FractalModel::III, EffectID::ID_DISTORT1)
+        );
+        assert_eq!(
+            SysexFrame::new(FractalModel::III, 0x0A, vec![58, 0, 1]).to_message(),
+            set_block_bypass(FractalModel::III, EffectID::ID_DISTORT1, true)
+        );
+        assert_eq!(
+            SysexFrame::new(FractalModel::FM3, 0x0A, vec![58, 0, 0]).to_message(),
+            set_block_bypass(FractalModel::FM3, EffectID::ID_DISTORT1, false)
+        );
+        assert_eq!(
+            SysexFrame::new(FractalModel::III, 0x0B, vec![58, 0, 0x7F]).to_message(),
+            get_block_channel(FractalModel::III, EffectID::ID_DISTORT1)
+        );
+        assert_eq!(
+            Some(SysexFrame::new(FractalModel::III, 0x0B, vec![58, 0, 3]).to_message()),
+            set_block_channel(FractalModel::III, EffectID::ID_DISTORT1, Channel::D, 4)
+        );
+        assert_eq!(
+            None,
+            set_block_channel(FractalModel::III, EffectID::ID_DISTORT1, Channel::C, 2)
+        );
+    }
+
+    #[test]
+    fn test_parse_block_bypass_and_channel() {
+        assert_eq!(
+            FractalMessage::BlockBypass {
+                effect_id: 58,
+                effect: Effect::Amp1,
+                bypassed: true
+            },
+            parse_message(SysexFrame::new(FractalModel::III, 0x0A, vec![58, 0, 1]).to_message())
+        );
+        assert_eq!(
+            FractalMessage::BlockChannel {
+                effect_id: 58,
+                effect: Effect::Amp1,
+                channel: Channel::B
+            },
+            parse_message(SysexFrame::new(FractalModel::FM9, 0x0B, vec![58, 0, 1]).to_message())
+        );
+        assert_eq!(
+            Err(ParseError::InvalidChannel(9)),
+            try_parse_message(
+                &SysexFrame::new(FractalModel::III, 0x0B, vec![58, 0, 9]).to_message()
+            )
+        );
+    }
+
+    #[test]
+    fn test_session_block_bypass_and_channel() {
+        let mut device = simulator::Simulator::new(FractalModel::III);
+        device.effects.push(EffectStatus {
+            effect_id: EffectID::ID_DISTORT1 as u32,
+            effect_id_iii: Some(EffectID::ID_DISTORT1),
+            effect: Effect::Amp1,
+            bypassed: false,
+            channel: Channel::A,
+            max_channels: 2,
+        });
+        let mut session = Session::new(device, FractalModel::III);
+        let amp = EffectID::ID_DISTORT1;
+        assert_eq!(false, session.block_bypass(amp).unwrap());
+        assert_eq!(true, session.set_block_bypass(amp, true).unwrap());
+        assert_eq!(
+            Channel::B,
+            session.set_block_channel(amp, Channel::B, 2).unwrap()
+        );
+        assert_eq!(Channel::B, session.block_channel(amp).unwrap());
+        match session.set_block_channel(amp, Channel::C, 2) {
+            Err(SessionError::InvalidChannel(Channel::C)) => {}
+            other => panic!("expected invalid channel, got {:?}", other),
+        }
+        match session.set_block_channel(amp, Channel::C, 4) {
+            Err(SessionError::Rejected {
+                function_id: 0x0B,
+                response_code: 1,
+            }) => {}
+            other => panic!("expected rejection, got {:?}", other),
+        }
+        let status = &session.transport().effects[0];
+        assert!(status.bypassed);
+        assert_eq!(Channel::B, status.channel);
+    }
+
+    const ALL_MODELS: [FractalModel; 13] = [
+        FractalModel::Standard,
+        FractalModel::Ultra,
+        FractalModel::MFC101,
+        FractalModel::II,
+        FractalModel::MFC101MK3,
+        FractalModel::FX8,
+        FractalModel::IIXL,
+        FractalModel::IIXLPlus,
+        FractalModel::AX8,
+        FractalModel::FX8MK2,
+        FractalModel::III,
+        FractalModel::FM3,
+        FractalModel::FM9,
+    ];
+
+    #[test]
+    fn test_effect_registry_round_trip() {
+        for model in ALL_MODELS.iter() {
+            let registry = EffectRegistry::for_model(*model);
+            let entries = registry.entries();
+            assert!(!entries.is_empty());
+            assert!(
+                entries.windows(2).all(|pair| pair[0].0 < pair[1].0),
+                "{:?} IDs are not unique and sorted",
+                model
+            );
+            for (id, effect) in entries.iter() {
+                assert!(*id < 0x4000);
+                assert_eq!(*effect, registry.effect(*id));
+                assert_eq!(Some(*id), registry.id(*effect));
+            }
+        }
+    }
+
+    #[test]
+    fn test_effect_registry_unknown_ids() {
+        let registry = EffectRegistry::for_model(FractalModel::II);
+        assert_eq!(Effect::Unknown(5000), registry.effect(5000));
+        assert_eq!(Some(5000), registry.id(Effect::Unknown(5000)));
+        assert_eq!(None, registry.id(Effect::Amp3));
+        assert_eq!(
+            Effect::Shunt,
+            EffectRegistry::for_model(FractalModel::II).effect(207)
+        );
+        assert_eq!(
+            Effect::Amp1,
+            EffectRegistry::for_model(FractalModel::III).effect(58)
+        );
+    }
+
+    #[test]
+    fn test_get_block_parameters_uses_reported_ids() {
+        assert_eq!(
+            Some(vec![
+                0xF0, 0x00, 0x01, 0x74, 0x03, 0x01, 0x4F, 0x01, 0x49, 0xF7
+            ]),
+            get_block_parameters(FractalModel::II, Effect::Shunt)
+        );
+        assert_eq!(None, get_block_parameters(FractalModel::II, Effect::Amp3));
+        for model in ALL_MODELS.iter() {
+            let registry = EffectRegistry::for_model(*model);
+            for (_, effect) in registry.entries().iter() {
+                let request =
+                    SysexFrame::parse_request(&get_block_parameters(*model, *effect).unwrap())
+                        .unwrap();
+                let mut response = request.payload.clone();
+                response.extend(vec![0, 0, 0, 0, 0]);
+                match parse_message(SysexFrame::new(*model, 0x01, response).to_message()) {
+                    FractalMessage::BlockParameters {
+                        effect: reported, ..
+                    } => assert_eq!(*effect, reported),
+                    other => panic!("expected block parameters, got {:?}", other),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_session_scene_names() {
+        let mut device = simulator::Simulator::new(FractalModel::III);
+        device.scene_names[5] = "Solo".to_string();
+        device.inject_front_panel_change();
+        let mut session = Session::new(device, FractalModel::III);
+        assert_eq!(
+            vec!["", "", "", "", "", "Solo", "", ""],
+            session.scene_names().unwrap()
+        );
+        assert_eq!(
+            vec![FractalMessage::FrontPanelChangeDetected],
+            session.take_unsolicited()
+        );
+        match Session::new(MockTransport::new(), FractalModel::II).scene_names() {
+            Err(SessionError::Unsupported) => {}
+            other => panic!("expected unsupported, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_session_scene_names_timeout() {
+        let mut transport = MockTransport::new();
+        transport.respond_with(|msg| match msg[6] {
+            0 | 1 => vec![SysexFrame::new(
+                FractalModel::III,
+                0x0E,
+                [vec![msg[6]], b"Intro".to_vec()].concat(),
+            )
+            .to_message()],
+            _ => vec![],
+        });
+        let mut session = Session::new(transport, FractalModel::III);
+        match session.scene_names() {
+            Err(SessionError::Timeout) => {}
+            other => panic!("expected timeout, got {:?}", other),
+        }
+        assert_eq!(8, session.transport().sent.len());
+    }
+
+    #[test]
+    fn test_get_preset_name() {
+        assert_eq!(
+            vec![vec![
+                0xF0, 0x00, 0x01, 0x74, 0x10, 0x0D, 0x05, 0x03, 30, 0xF7
+            ]],
+            get_preset_name(FractalModel::III, 389)
+        );
+        assert_eq!(
+            vec![
+                vec![0xF0, 0x00, 0x01, 0x74, 0x03, 0x3C, 0x03, 0x05, 0x3C, 0xF7],
+                vec![0xF0, 0x00, 0x01, 0x74, 0x03, 0x0F, 0x09, 0xF7],
+            ],
+            get_preset_name(FractalModel::II, 389)
+        );
+    }
+
+    #[test]
+    fn test_session_preset_name() {
+        let mut device = simulator::Simulator::new(FractalModel::III);
+        device.preset_names[389] = "Plexi".to_string();
+        let mut session = Session::new(device, FractalModel::III);
+        assert_eq!("Plexi", session.preset_name(389).unwrap());
+        assert_eq!("", session.preset_name(390).unwrap());
+        match session.preset_name(5000) {
+            Err(SessionError::Rejected { .. }) => {}
+            other => panic!("expected rejection, got {:?}", other),
+        }
+
+        let mut device = simulator::Simulator::new(FractalModel::II);
+        device.preset_names[389] = "Plexi".to_string();
+        let mut session = Session::new(device, FractalModel::II).with_timeout(ms(10));
+        assert_eq!("Plexi", session.preset_name(389).unwrap());
+        assert_eq!(389, session.transport().preset_number);
+        // The device stays on the current preset and confirms that instead.
+        match session.preset_name(5000) {
+            Err(SessionError::Timeout) => {}
+            other => panic!("expected timeout, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_session_preset_bank() {
+        let mut device = simulator::Simulator::new(FractalModel::III);
+        device.preset_names[1] = "Clean".to_string();
+        device.preset_names[1023] = "Last".to_string();
+        let mut session = Session::new(device, FractalModel::III);
+        let mut calls = vec![];
+        let bank = session
+            .preset_bank(|done, total| {
+                calls.push((done, total));
+                true
+            })
+            .unwrap();
+        assert_eq!(1024, bank.names.len());
+        assert_eq!((1, "Clean".to_string()), bank.names[1]);
+        assert_eq!((1023, "Last".to_string()), bank.names[1023]);
+        assert!(bank.missing.is_empty());
+        assert!(!bank.cancelled);
+        assert_eq!(1024, calls.len());
+        assert_eq!(Some(&(1024, 1024)), calls.last());
+
+        let bank = session.preset_bank(|done, _| done < 10).unwrap();
+        assert_eq!(10, bank.names.len());
+        assert!(bank.cancelled);
+
+        let mut device = simulator::Simulator::new(FractalModel::II);
+        device.preset_names[767] = "Last".to_string();
+        device.preset_number = 42;
+        let mut session = Session::new(device, FractalModel::II);
+        let bank = session.preset_bank(|_, _| true).unwrap();
+        assert_eq!(768, bank.names.len());
+        assert_eq!((767, "Last".to_string()), bank.names[767]);
+        assert!(bank.missing.is_empty());
+        assert_eq!(42, session.transport().preset_number);
+
+        let bank = session.preset_bank(|done, _| done < 3).unwrap();
+        assert_eq!(3, bank.names.len());
+        assert!(bank.cancelled);
+        assert_eq!(42, session.transport().preset_number);
+    }
+
+    #[test]
+    fn test_session_preset_bank_tolerates_missing_replies() {
+        let mut device = simulator::Simulator::new(FractalModel::III);
+        device.preset_names[2] = "Lead".to_string();
+        let mut transport = MockTransport::new();
+        transport.respond_with(move |msg| {
+            device.handle(msg);
+            let reply = device.receive(std::time::Duration::from_secs(0)).unwrap();
+            if msg[6] % 100 == 7 {
+                vec![]
+            } else {
+                vec![reply]
+            }
+        });
+        let mut session = Session::new(transport, FractalModel::III);
+        let bank = session.preset_bank(|_, _| true).unwrap();
+        assert_eq!(
+            (0..1024)
+                .filter(|n| n % 128 % 100 == 7)
+                .collect::<Vec<u32>>(),
+            bank.missing
+        );
+        assert_eq!(1024 - bank.missing.len(), bank.names.len());
+        assert!(bank.names.contains(&(2, "Lead".to_string())));
+    }
+
+    /// A dump built from `preset_file`'s own layout, not from a real export.
+    /// Use it for edge cases; it can't show that the layout is right.
+    fn preset_dump_bytes(model: FractalModel, preset: (u8, u8), name: &str) -> Vec<u8> {
+        let mut words: Vec<u32> = name.bytes().map(u32::from).collect();
+        words.resize(32, 32);
+        words.extend((0..40).map(|x| x * 1000));
+        let chunks: Vec<Vec<u8>> = words
+            .chunks(24)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .flat_map(|word| {
+                        let (a, b, c) = encode_parameter_value(*word);
+                        vec![a, b, c]
+                    })
+                    .collect()
+            })
+            .collect();
+        [
+            vec![SysexFrame::new(model, 0x77, vec![preset.0, preset.1, 0, 3]).to_message()],
+            chunks
+                .into_iter()
+                .map(|chunk| SysexFrame::new(model, 0x78, chunk).to_message())
+                .collect(),
+            vec![SysexFrame::new(model, 0x79, vec![0, 0]).to_message()],
+        ]
+        .concat()
+        .concat()
+    }
+
+    #[test]
+    fn test_preset_file_parse() {
+        let bytes = preset_dump_bytes(FractalModel::II, (3, 5), "Plexi Drive");
+        let file = preset_file::PresetFile::parse(&bytes).unwrap();
+        assert_eq!(FractalModel::II, file.model);
+        assert_eq!(Some(389), file.preset_number);
+        assert_eq!("Plexi Drive", file.name);
+        assert_eq!(3, file.chunks.len());
+        assert_eq!(bytes, file.to_bytes());
+
+        let bytes = preset_dump_bytes(FractalModel::III, (0x7F, 0x7F), "Edit");
+        let file = preset_file::PresetFile::parse(&bytes).unwrap();
+        assert_eq!(FractalModel::III, file.model);
+        assert_eq!(None, file.preset_number);
+        assert_eq!("Edit", file.name);
+
+        // The III sends the preset number least significant byte first.
+        let bytes = preset_dump_bytes(FractalModel::III, (5, 3), "Plexi Drive");
+        let file = preset_file::PresetFile::parse(&bytes).unwrap();
+        assert_eq!(Some(389), file.preset_number);
+        let bytes = preset_dump_bytes(FractalModel::III, (0x7F, 0), "Last");
+        let file = preset_file::PresetFile::parse(&bytes).unwrap();
+        assert_eq!(Some(127), file.preset_number);
+    }
+
+    #[test]
+    fn test_preset_file_errors() {
+        use preset_file::{PresetFile, PresetFileError};
+        let bytes = preset_dump_bytes(FractalModel::II, (0, 1), "Clean");
+        let messages = preset_file::split_messages(&bytes).unwrap();
+        assert_eq!(5, messages.len());
+        let (chunk_offset, chunk) = messages[2];
+
+        let mut corrupt = bytes.clone();
+        corrupt[chunk_offset + chunk.len() - 2] ^= 1;
+        assert_eq!(
+            Err(PresetFileError::InvalidMessage {
+                index: 2,
+                offset: chunk_offset + chunk.len() - 2,
+                error: ParseError::ChecksumMismatch {
+                    expected: chunk[chunk.len() - 2],
+                    actual: chunk[chunk.len() - 2] ^ 1
+                }
+            }),
+            PresetFile::parse(&corrupt)
+        );
+
+        let mut corrupt = bytes.clone();
+        corrupt[chunk_offset + 10] = 0x80;
+        assert_eq!(
+            Err(PresetFileError::InvalidMessage {
+                index: 2,
+                offset: chunk_offset + 10,
+                error: ParseError::InvalidDataByte {
+                    index: 10,
+                    byte: 0x80
+                }
+            }),
+            PresetFile::parse(&corrupt)
+        );
+
+        assert_eq!(
+            Err(PresetFileError::InvalidMessage {
+                index: 5,
+                offset: bytes.len(),
+                error: ParseError::Truncated
+            }),
+            PresetFile::parse(&[bytes.clone(), vec![0xF0, 0x00]].concat())
+        );
+        assert_eq!(
+            Err(PresetFileError::InvalidMessage {
+                index: 1,
+                offset: messages[1].0,
+                error: ParseError::MissingHeader
+            }),
+            PresetFile::parse(&[messages[0].1, &[0x00], messages[1].1].concat())
+        );
+        assert_eq!(
+            Err(PresetFileError::UnexpectedMessage {
+                index: 0,
+                offset: 0,
+                function_id: 0x78
+            }),
+            PresetFile::parse(&bytes[messages[1].0..])
+        );
+        assert_eq!(
+            Err(PresetFileError::Incomplete),
+            PresetFile::parse(&bytes[..messages[4].0])
+        );
+        let other_model = SysexFrame::new(FractalModel::III, 0x78, vec![0]).to_message();
+        assert_eq!(
+            Err(PresetFileError::ModelMismatch {
+                index: 1,
+                offset: messages[1].0,
+                model: FractalModel::III
+            }),
+            PresetFile::parse(&[messages[0].1, &other_model].concat())
+        );
+    }
+
+    #[test]
+    fn test_request_preset_dump() {
+        assert_eq!(
+            vec![0xF0, 0x00, 0x01, 0x74, 0x03, 0x03, 0x7F, 0x00, 122, 0xF7],
+            request_preset_dump(FractalModel::II, PresetTarget::EditBuffer)
+        );
+        assert_eq!(
+            vec![0xF0, 0x00, 0x01, 0x74, 0x03, 0x03, 0x03, 0x05, 3, 0xF7],
+            request_preset_dump(FractalModel::II, PresetTarget::Preset(389))
+        );
+        assert_eq!(
+            vec![0xF0, 0x00, 0x01, 0x74, 0x10, 0x03, 0x7F, 0x7F, 22, 0xF7],
+            request_preset_dump(FractalModel::III, PresetTarget::EditBuffer)
+        );
+        assert_eq!(
+            vec![0xF0, 0x00, 0x01, 0x74, 0x10, 0x03, 0x05, 0x03, 16, 0xF7],
+            request_preset_dump(FractalModel::III, PresetTarget::Preset(389))
+        );
+    }
+
+    #[test]
+    fn test_session_fetch_preset() {
+        for (model, preset) in [(FractalModel::II, (3, 5)), (FractalModel::III, (5, 3))].iter() {
+            let model = *model;
+            let bytes = preset_dump_bytes(model, *preset, "Plexi Drive");
+            let mut transport = MockTransport::new();
+            let fixture = bytes.clone();
+            transport.respond_with(move |_| fixture.chunks(7).map(|x| x.to_vec()).collect());
+            let mut session = Session::new(transport, model);
+            let dump = session.fetch_preset(PresetTarget::Preset(389)).unwrap();
+            assert_eq!(Some(389), dump.preset_number);
+            assert_eq!("Plexi Drive", dump.name);
+            assert_eq!(bytes, dump.to_bytes());
+            assert_eq!(
+                vec![request_preset_dump(model, PresetTarget::Preset(389))],
+                session.transport().sent
+            );
+        }
+    }
+
+    #[test]
+    fn test_session_fetch_preset_out_of_order() {
+        let bytes = preset_dump_bytes(FractalModel::II, (0x7F, 0), "Edit");
+        let messages = preset_file::split_messages(&bytes).unwrap();
+        let mut transport = MockTransport::new();
+        transport.queue_incoming(messages[1].1);
+        let mut session = Session::new(transport, FractalModel::II);
+        match session.fetch_preset(PresetTarget::EditBuffer) {
+            Err(SessionError::InvalidDump(preset_file::PresetFileError::UnexpectedMessage {
+                index: 0,
+                function_id: 0x78,
+                ..
+            })) => {}
+            other => panic!("expected invalid dump, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_preset_upload_messages() {
+        for (model, slot, edit_buffer) in [
+            (FractalModel::II, (3, 5), vec![0x7F, 0x00]),
+            (FractalModel::III, (5, 3), vec![0x7F, 0x7F]),
+        ]
+        .iter()
+        {
+            let model = *model;
+            let dump =
+                preset_file::PresetFile::parse(&preset_dump_bytes(model, *slot, "Plexi Drive"))
+                    .unwrap();
+            let messages = dump.upload_messages();
+            assert_eq!(dump.messages().len(), messages.len());
+            assert_eq!(edit_buffer, &messages[0][6..8]);
+            assert_eq!(Ok(()), verify_checksum(&messages[0]));
+            let uploaded = preset_file::PresetFile::parse(&messages.concat()).unwrap();
+            assert_eq!(None, uploaded.preset_number);
+            assert_eq!(dump.chunks, uploaded.chunks);
+            assert_eq!(&dump.messages()[1..], &messages[1..]);
+        }
+    }
+
+    #[test]
+    fn test_session_preset_round_trip() {
+        for (model, slot) in [(FractalModel::II, (3, 5)), (FractalModel::III, (5, 3))].iter() {
+            let model = *model;
+            let stored =
+                preset_file::PresetFile::parse(&preset_dump_bytes(model, *slot, "Plexi Drive"))
+                    .unwrap();
+            let mut device = simulator::Simulator::new(model);
+            device.preset_dumps.insert(389, stored.clone());
+            let mut session = Session::new(device, model).with_timeout(ms(10));
+
+            let dump = session.fetch_preset(PresetTarget::Preset(389)).unwrap();
+            assert_eq!(stored, dump);
+            session.upload_preset(&dump, ms(0)).unwrap();
+            assert_eq!(
+                Some(dump.chunks.clone()),
+                session
+                    .transport()
+                    .edit_buffer_dump
+                    .as_ref()
+                    .map(|dump| dump.chunks.clone())
+            );
+            assert_eq!("Plexi Drive", session.transport().current_preset_name);
+
+            let edit_buffer = session.fetch_preset(PresetTarget::EditBuffer).unwrap();
+            assert_eq!(None, edit_buffer.preset_number);
+            assert_eq!(dump.chunks, edit_buffer.chunks);
+
+            session
+                .request(&store_in_preset(model, 130), |message| match message {
+                    FractalMessage::MultipurposeResponse {
+                        response_code: 0, ..
+                    } => Some(()),
+                    _ => None,
+                })
+                .unwrap();
+            let stored = session.fetch_preset(PresetTarget::Preset(130)).unwrap();
+            assert_eq!(Some(130), stored.preset_number);
+            assert_eq!(dump.chunks, stored.chunks);
+
+            match session.fetch_preset(PresetTarget::Preset(5)) {
+                Err(SessionError::Rejected {
+                    function_id: 0x03,
+                    response_code: 1,
+                }) => {}
+                other => panic!("expected rejection, got {:?}", other),
+            }
+        }
+    }
+
+    /// An IR built from `cab_file`'s own layout, not from a real file. Use it
+    /// for edge cases; it can't show that the layout is right.
+    fn cab_ir_bytes(model: FractalModel, slot: (u8, u8), name: &str) -> Vec<u8> {
+        let mut header = vec![slot.0, slot.1, 0, 1];
+        header.extend(format!("{:<32}", name).bytes());
+        [
+            vec![SysexFrame::new(model, 0x7A, header).to_message()],
+            (0..3u8)
+                .map(|i| SysexFrame::new(model, 0x7B, vec![i; 48]).to_message())
+                .collect(),
+            vec![SysexFrame::new(model, 0x7C, vec![]).to_message()],
+        ]
+        .concat()
+        .concat()
+    }
+
+    #[test]
+    fn test_cab_file_parse() {
+        use cab_file::CabFile;
+        use preset_file::PresetFileError;
+
+        let bytes = cab_ir_bytes(FractalModel::III, (1, 2), "4x12 Greenback");
+        let cab = CabFile::parse(&bytes).unwrap();
+        assert_eq!(FractalModel::III, cab.model);
+        assert_eq!(130, cab.slot);
+        assert_eq!("4x12 Greenback", cab.name);
+        assert_eq!(3, cab.data.len());
+        assert_eq!(bytes, cab.to_bytes());
+
+        let mut corrupt = bytes.clone();
+        let header_len = 6 + 36 + 2;
+        corrupt[header_len + 10] ^= 1;
+        assert_eq!(
+            Err(PresetFileError::InvalidMessage {
+                index: 1,
+                offset: header_len + 6 + 48,
+                error: ParseError::ChecksumMismatch {
+                    expected: corrupt[header_len + 6 + 48] ^ 1,
+                    actual: corrupt[header_len + 6 + 48],
+                },
+            }),
+            CabFile::parse(&corrupt)
+        );
+        assert_eq!(
+            Err(PresetFileError::UnexpectedMessage {
+                index: 0,
+                offset: 0,
+                function_id: 0x7B,
+            }),
+            CabFile::parse(&bytes[header_len..])
+        );
+        assert_eq!(
+            Err(PresetFileError::Incomplete),
+            CabFile::parse(&bytes[..bytes.len() - 8])
+        );
+
+        let short_header = SysexFrame::new(FractalModel::III, 0x7A, vec![1, 2, 0]).to_message();
+        assert_eq!(
+            Err(PresetFileError::InvalidMessage {
+                index: 0,
+                offset: 0,
+                error: ParseError::PayloadTooShort {
+                    function_id: 0x7A,
+                    expected: 4,
+                    actual: 3,
+                },
+            }),
+            CabFile::parse(&[short_header, bytes[header_len..].to_vec()].concat())
+        );
+        let other_model = SysexFrame::new(FractalModel::II, 0x7B, vec![0; 48]).to_message();
+        assert_eq!(
+            Err(PresetFileError::ModelMismatch {
+                index: 1,
+                offset: header_len,
+                model: FractalModel::II,
+            }),
+            CabFile::parse(&[&bytes[..header_len], &other_model].concat())
+        );
+    }
+
+    #[test]
+    fn test_session_upload_cab() {
+        let model = FractalModel::II;
+        let bytes = cab_ir_bytes(model, (0, 7), "Room");
+        let cab = cab_file::CabFile::parse(&bytes).unwrap();
+        let mut session = Session::new(MockTransport::new(), model);
+        session
+            .upload_cab(&cab, std::time::Duration::from_secs(0))
+            .unwrap();
+        assert_eq!(bytes, session.transport().sent.concat());
+    }
+
+    /// A stand-in for a firmware update: checksummed messages for one model.
+    /// The real layout isn't known, and `inspect` only looks at the framing.
+    fn firmware_messages(model: FractalModel, count: u8) -> Vec<MidiMessage> {
+        (0..count)
+            .map(|i| SysexFrame::new(model, 0x7E, [vec![0, i], vec![i; 32]].concat()).to_message())
+            .collect()
+    }
+
+    #[test]
+    fn test_firmware_file_inspect() {
+        let bytes = firmware_messages(FractalModel::III, 6).concat();
+        let report = firmware_file::inspect(&bytes);
+        assert_eq!(
+            firmware_file::FirmwareReport {
+                message_count: 6,
+                total_size: bytes.len(),
+                model: Some(FractalModel::III),
+                problems: vec![],
+            },
+            report
+        );
+        assert!(report.is_valid());
+
+        let report = firmware_file::inspect(&[]);
+        assert_eq!((0, None), (report.message_count, report.model));
+    }
+
+    #[test]
+    fn test_firmware_file_problems() {
+        use firmware_file::{inspect, FirmwareProblem};
+
+        let messages = firmware_messages(FractalModel::III, 6);
+        let offset_of = |i: usize| messages[..i].iter().map(Vec::len).sum::<usize>();
+
+        let mut corrupt = messages.clone();
+        corrupt[2][10] ^= 1;
+        let checksum_at = corrupt[2].len() - 2;
+        let actual = corrupt[2][checksum_at];
+        assert_eq!(
+            vec![FirmwareProblem::InvalidMessage {
+                index: 2,
+                offset: offset_of(2) + checksum_at,
+                error: ParseError::ChecksumMismatch {
+                    expected: actual ^ 1,
+                    actual,
+                },
+            }],
+            inspect(&corrupt.concat()).problems
+        );
+
+        let bytes = messages.concat();
+        let cut = offset_of(4) + 10;
+        let report = inspect(&bytes[..cut]);
+        assert_eq!(5, report.message_count);
+        assert_eq!(cut, report.total_size);
+        assert_eq!(
+            vec![FirmwareProblem::InvalidMessage {
+                index: 4,
+                offset: offset_of(4),
+                error: ParseError::Truncated,
+            }],
+            report.problems
+        );
+
+        let mut stray = messages.clone();
+        stray.insert(2, vec![0x00, 0x7E]);
+        let report = inspect(&stray.concat());
+        assert_eq!(6, report.message_count);
+        assert_eq!(
+            vec![FirmwareProblem::StrayBytes {
+                offset: offset_of(2),
+                length: 2,
+            }],
+            report.problems
+        );
+        let mut stray_and_corrupt = stray;
+        stray_and_corrupt[3][10] ^= 1;
+        match &inspect(&stray_and_corrupt.concat()).problems[..] {
+            [FirmwareProblem::StrayBytes { .. }, FirmwareProblem::InvalidMessage {
+                index: 2, offset, ..
+            }] => {
+                assert!(*offset > offset_of(2) + 2)
+            }
+            other => panic!("expected a stray run and a bad message 2, got {:?}", other),
+        }
+
+        let mut mixed = messages.clone();
+        mixed.insert(
+            1,
+            SysexFrame::new(FractalModel::FM3, 0x7E, vec![0, 1]).to_message(),
+        );
+        let problems = inspect(&mixed.concat()).problems;
+        assert_eq!(
+            vec![FirmwareProblem::ModelMismatch {
+                index: 1,
+                offset: messages[0].len(),
+                model: FractalModel::FM3,
+            }],
+            problems
+        );
+        let problem: Box<dyn std::error::Error> = Box::new(problems[0].clone());
+        assert_eq!(
+            format!(
+                "message 1 (byte {}): model FM3 does not match the first message",
+                messages[0].len()
+            ),
+            problem.to_string()
+        );
+    }
+
+    #[test]
+    fn test_parse_message_returns_unknown_on_error() {
+        assert_eq!(
+            FractalMessage::Unknown(vec![240, 0, 1, 116, 3, 20]),
+            parse_message(vec![240, 0, 1, 116, 3, 20])
+        );
+        assert_eq!(
+            FractalMessage::Unknown(vec![240, 0, 1, 116, 3, 8, 247]),
+            parse_message(vec![240, 0, 1, 116, 3, 8, 247])
+        );
+    }
+
+    #[test]
+    fn test_get_current_preset_name() {
+        assert_eq!(
+            vec![
+                0xF0,
+                0x00,
+                0x01,
+                0x74,
+                model_code(FractalModel::II),
+                0x0F,
+                9,
+                0xF7
+            ],
+            get_current_preset_name(FractalModel::II)
+        );
+    }
+
+    #[test]
+    fn test_get_current_preset_name_axe_3() {
+        assert_eq!(
+            vec![0xF0, 0x00, 0x01, 0x74, 0x10, 0x0D, 0x7F, 0x7F, 24, 0xF7],
+            get_current_preset_name(FractalModel::III)
+        );
+    }
+
+    #[test]
+    fn test_set_current_preset_name() {
+        assert_eq!(
+            vec![
+                0xF0,
+                0x00,
+                0x01,
+                0x74,
+                model_code(FractalModel::II),
+                0x09,
+                0x43,
+                0x68,
+                0x61,
+                0x6E,
+                0x67,
+                0x65,
+                0x64,
+                0x21,
+                0x20,
+                0x20,
+                0x20,
+                0x20,
+                0x20,
+                0x20,
+                0x20,
+                0x20,
+                0x20,
+                0x20,
+                0x20,
+                0x20,
+                0x20,
+                0x20,
+                0x20,
+                0x20,
+                0x20,
+                0x20,
+                0x20,
+                0x20,
+                0x20,
+                0x20,
+                0x20,
+                0x20,
+                0x6C,
+                0xF7
+            ],
+            set_current_preset_name(FractalModel::II, "Changed!")
+        );
+        assert_eq!(
+            set_current_preset_name(FractalModel::II, "O Praise The Name (Anstasis)"),
+            set_current_preset_name(FractalModel::II, "O Praise The Name (Anástasis)")
+        );
+    }
+
+    #[test]
+    fn test_parse_current_preset_name() {
+        assert_eq!(
+            parse_message(vec![
+                240, 0, 1, 116, 3, 15, 66, 83, 32, 65, 67, 50, 48, 32, 66, 97, 115, 101, 32, 32,
+                32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 0, 13, 247
+            ]),
+            FractalMessage::CurrentPresetName("BS AC20 Base".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_preset_name() {
+        assert_eq!(
+            parse_message(vec![
+                240, 0, 1, 116, 16, 13, 15, 3, 66, 83, 32, 65, 67, 50, 48, 32, 66, 97, 115, 101,
+                32, 83, 67, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 0, 0,
+                247
+            ]),
+            FractalMessage::PresetName(399, "BS AC20 Base SC".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_scene_name() {
+        assert_eq!(
+            parse_message(vec![
+                240, 0, 1, 116, 16, 14, 0, 67, 108, 101, 97, 110, 32, 32, 32, 32, 32, 32, 32, 32,
+                32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 0, 94, 247
+            ]),
+            FractalMessage::SceneName(0, "Clean".to_string())
+        );
+        assert_eq!(
+            parse_message(vec![
+                240, 0, 1, 116, 16, 14, 1, 79, 68, 49, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32,
+                32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 32, 0, 32, 247
+            ]),
+            FractalMessage::SceneName(1, "OD1".to_string())
+        );
+    }
+
+    #[test]
+    fn test_get_firmware_version() {
+        assert_eq!(
+            vec![
+                0xF0,
+                0x00,
+                0x01,
+                0x74,
+                model_code(FractalModel::II),
+                0x08,
+                14,
+                0xF7
+            ],
+            get_firmware_version(FractalModel::II)
+        );
+    }
+
+    #[test]
+    fn test_parse_firmware_version() {
+        assert_eq!(
+            parse_message(vec![240, 0, 1, 116, 3, 8, 0x08, 0x02, 0, 0, 0, 0, 0, 247]),
+            FractalMessage::FirmwareVersion { major: 8, minor: 2 }
+        );
+    }
+
+    #[test]
+    fn test_disconnect_from_controller() {
+        assert_eq!(
+            vec![
+                0xF0,
+                0x00,
+                0x01,
+                0x74,
+                model_code(FractalModel::II),
+                0x42,
+                68,
+                0xF7
+            ],
+            disconnect_from_controller(FractalModel::II)
+        );
+    }
+
+    #[test]
+    fn test_parse_front_panel_change_detected() {
+        assert_eq!(
+            parse_message(vec![240, 0, 1, 116, 3, 0x21, 0, 0xF7]),
+            FractalMessage::FrontPanelChangeDetected
+        );
+    }
+
+    #[test]
+    fn test_parse_midi_tempo_beat() {
+        assert_eq!(
+            parse_message(vec![240, 0, 1, 116, 3, 0x10, 0xF7]),
+            FractalMessage::MIDITempoBeat
+        );
+    }
+
+    #[test]
+    fn test_get_midi_channel() {
+        assert_eq!(
+            vec![240, 0, 1, 116, 3, 0x17, 17, 0xF7],
+            get_midi_channel(FractalModel::II)
+        );
+    }
+
+    #[test]
+    fn test_parse_midi_channel() {
+        assert_eq!(
+            parse_message(vec![240, 0, 1, 116, 3, 0x17, 9, 0, 0xF7]),
+            FractalMessage::MIDIChannel(10)
+        );
+    }
+
+    #[test]
+    fn test_parse_tuner_info() {
+        assert_eq!(
+            parse_message(vec![240, 0, 1, 116, 3, 0x0D, 1, 2, 63, 0xF7]),
+            FractalMessage::TunerInfo {
+                note: 1,
+                string_number: 2,
+                tuner_data: 63
+            }
+        );
+    }
+
+    #[test]
+    fn test_tuner_reading() {
+        let config = TunerConfig::default();
+        let round = |x: f64| (x * 100.0).round() / 100.0;
+
+        let reading = TunerReading::new(0, 5, 63, &config);
+        assert_eq!("A", reading.note_name);
+        assert_eq!(Some(2), reading.octave);
+        assert_eq!(0.0, reading.cents);
+        assert!(reading.in_tune);
+        assert_eq!(Some(110.0), reading.frequency);
+
+        let reading = TunerReading::new(7, 1, 126, &config);
+        assert_eq!("E", reading.note_name);
+        assert_eq!(Some(4), reading.octave);
+        assert_eq!(50.0, reading.cents);
+        assert!(!reading.in_tune);
+        assert_eq!(Some(339.29), reading.frequency.map(round));
+
+        let reading = TunerReading::new(1, 2, 60, &config);
+        assert_eq!(("A#", Some(3)), (reading.note_name, reading.octave));
+        assert_eq!(-2.38, round(reading.cents));
+        assert!(reading.in_tune);
+
+        let strict = TunerConfig {
+            reference_pitch: 442.0,
+            tolerance_cents: 1.0,
+        };
+        let reading = TunerReading::new(0, 4, 61, &strict);
+        assert_eq!(Some(3), reading.octave);
+        assert!(!reading.in_tune);
+        assert_eq!(Some(220.8), reading.frequency.map(round));
+
+        // On the low E the window runs from C#2 up to C3 at the eighth fret,
+        // so the tenth fret D is read as D2.
+        let octave = |note| TunerReading::new(note, 6, 63, &config).octave;
+        assert_eq!(Some(2), octave(4));
+        assert_eq!(Some(3), octave(3));
+        assert_eq!(Some(2), octave(5));
+
+        let reading = TunerReading::new(3, 0, 63, &config);
+        assert_eq!(
+            ("C", None, None),
+            (reading.note_name, reading.octave, reading.frequency)
+        );
+
+        assert_eq!(
+            Some(TunerReading::new(4, 6, 70, &config)),
+            TunerReading::from_message(
+                &parse_message(vec![240, 0, 1, 116, 3, 0x0D, 4, 6, 70, 0xF7]),
+                &config
+            )
+        );
+        assert_eq!(
+            None,
+            TunerReading::from_message(&FractalMessage::MIDITempoBeat, &config)
+        );
+    }
+
+    #[test]
+    fn test_session_tuner_reading() {
+        let config = TunerConfig::default();
+        let mut device = simulator::Simulator::new(FractalModel::II);
+        device.inject_tuner_info(10, 3, 63);
+        let mut session = Session::new(device, FractalModel::II)
+            .with_timeout(std::time::Duration::from_millis(10));
+        let reading = session.tuner_reading(&config).unwrap();
+        assert_eq!(("G", Some(3)), (reading.note_name, reading.octave));
+        assert_eq!(TunerStatus::On, session.transport().tuner);
+
+        session.transport_mut().inject_tuner_info(0, 5, 63);
+        let reading = session.tuner_reading_cc(1, &config).unwrap();
+        assert_eq!(Some(110.0), reading.frequency);
+        match session.tuner_reading(&config) {
+            Err(SessionError::Timeout) => {}
+            other => panic!("expected timeout, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_toggle_tuner() {
+        assert_eq!(vec![176, 15, 0], toggle_tuner(1, TunerStatus::Off));
+        assert_eq!(vec![177, 15, 0], toggle_tuner(2, TunerStatus::Off));
+        assert_eq!(vec![176, 15, 127], toggle_tuner(1, TunerStatus::On));
+    }
+
+    #[test]
+    fn test_toggle_metronome() {
+        assert_eq!(vec![176, 122, 0], toggle_metronome(1, MetronomeStatus::Off));
+        assert_eq!(vec![177, 122, 0], toggle_metronome(2, MetronomeStatus::Off));
+        assert_eq!(
+            vec![176, 122, 127],
+            toggle_metronome(1, MetronomeStatus::On)
+        );
+    }
+
+    #[test]
+    fn test_get_preset_blocks_flags() {
+        assert_eq!(
+            vec![240, 0, 1, 116, 3, 0x0E, 8, 0xF7],
+            get_preset_blocks_flags(FractalModel::II)
         );
     }
 
@@ -823,263 +2740,446 @@ mod tests {
     }
 
     #[test]
-    fn test_set_scene_number() {
+    fn test_set_scene_number() {
+        assert_eq!(
+            vec![240, 0, 1, 116, 3, 41, 0, 47, 247],
+            set_scene_number(FractalModel::II, 0)
+        );
+        assert_eq!(
+            vec![0xF0, 0x00, 0x01, 0x74, 0x03, 0x29, 0x00, 0x2F, 0xF7],
+            set_scene_number(FractalModel::II, 0)
+        );
+    }
+
+    #[test]
+    fn test_set_scene_number_axe_3() {
+        assert_eq!(
+            vec![0xF0, 0x00, 0x01, 0x74, 0x10, 0x0C, 0x01, 0x18, 0xF7],
+            set_scene_number(FractalModel::III, 1)
+        );
+    }
+
+    #[test]
+    fn test_get_grid_layout_and_routing() {
+        assert_eq!(
+            vec![240, 0, 1, 116, 3, 0x20, 38, 0xF7],
+            get_grid_layout_and_routing(FractalModel::II)
+        );
+    }
+
+    #[test]
+    fn test_parse_scene_number() {
+        assert_eq!(
+            parse_message(vec![240, 0, 1, 116, 3, 41, 0, 47, 247]),
+            FractalMessage::CurrentSceneNumber(1)
+        );
+        assert_eq!(
+            parse_message(vec![240, 0, 1, 116, 3, 41, 1, 46, 247]),
+            FractalMessage::CurrentSceneNumber(2)
+        );
+        assert_eq!(
+            parse_message(vec![240, 0, 1, 116, 3, 41, 7, 40, 247]),
+            FractalMessage::CurrentSceneNumber(8)
+        );
+        assert_eq!(
+            parse_message(vec![0xF0, 0x00, 0x01, 0x74, 0x10, 0x0C, 0x01, 0x18, 0xF7,]),
+            FractalMessage::CurrentSceneNumber(1)
+        )
+    }
+
+    #[test]
+    fn test_parse_grid_layout_and_routing() {
+        let mut expected = Grid::new(FractalModel::II);
+        let row_2 = [
+            (127, Effect::VolumePan1),
+            (100, Effect::Compressor1),
+            (128, Effect::TremoloPanner1),
+            (133, Effect::Drive1),
+            (134, Effect::Drive2),
+            (106, Effect::Amp1),
+            (108, Effect::Cab1),
+            (207, Effect::Shunt),
+            (112, Effect::Delay1),
+            (114, Effect::MultiDelay1),
+            (110, Effect::Reverb1),
+            (135, Effect::Enhancer1),
+        ];
+        for (column, (effect_id, effect)) in row_2.iter().enumerate() {
+            let block = BlockGridBlock::EffectBlock {
+                effect_id: *effect_id,
+                effect: *effect,
+                connections: 0b10,
+            };
+            expected.set(1, column, block).unwrap();
+        }
+        assert_eq!(
+            parse_message(vec![
+                240, 0, 1, 116, 3, 32, 0, 0, 0, 0, 127, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                0, 100, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 0, 0, 0, 0, 0, 0, 0,
+                0, 0, 0, 0, 0, 0, 5, 1, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 6, 1, 2, 0, 0, 0,
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 106, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                108, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 79, 1, 2, 0, 0, 0, 0, 0, 0, 0, 0,
+                0, 0, 0, 0, 0, 112, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 114, 0, 2, 0, 0,
+                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 110, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                7, 1, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 29, 247
+            ]),
+            FractalMessage::BlockGrid(expected)
+        );
+    }
+
+    #[test]
+    fn test_grid_dimensions_and_lookup() {
+        for (model, rows, columns) in [
+            (FractalModel::II, 4, 12),
+            (FractalModel::III, 6, 14),
+            (FractalModel::FM3, 4, 12),
+        ]
+        .iter()
+        {
+            let mut grid = Grid::new(*model);
+            assert_eq!((*rows, *columns), (grid.rows(), grid.columns()));
+            assert_eq!(
+                Some(&BlockGridBlock::Empty),
+                grid.get(rows - 1, columns - 1)
+            );
+            assert_eq!(None, grid.get(*rows, 0));
+            assert_eq!(None, grid.get(0, *columns));
+
+            grid.place(rows - 1, columns - 1, Effect::Reverb1).unwrap();
+            assert_eq!(Some((rows - 1, columns - 1)), grid.find(Effect::Reverb1));
+            assert_eq!(None, grid.find(Effect::Delay1));
+
+            let reply = SysexFrame::new(*model, 0x20, grid.encode()).to_message();
+            assert_eq!(FractalMessage::BlockGrid(grid), parse_message(reply));
+        }
+    }
+
+    #[test]
+    fn test_grid_keeps_fourth_byte() {
+        let mut payload = vec![0; 4 * 4 * 12];
+        for (i, cell) in payload.chunks_exact_mut(4).enumerate() {
+            cell[3] = (i % 0x80) as u8;
+        }
+        // Amp 1 at (1, 2), taking input from rows 1 and 3.
+        payload[(2 * 4 + 1) * 4..(2 * 4 + 1) * 4 + 3].copy_from_slice(&[106, 0, 0b1010]);
+        let reply = SysexFrame::new(FractalModel::II, 0x20, payload.clone()).to_message();
+        let mut grid = match parse_message(reply) {
+            FractalMessage::BlockGrid(grid) => grid,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(payload, grid.encode());
+
+        #[allow(deprecated)]
+        {
+            let amp = grid.get(1, 2).unwrap();
+            assert!(!amp.connect_row_1());
+            assert!(amp.connect_row_2());
+            assert!(!amp.connect_row_3());
+            assert!(amp.connect_row_4());
+            assert!(!BlockGridBlock::Empty.connect_row_2());
+        }
+
+        grid.remove(1, 2).unwrap();
+        grid.place(0, 0, Effect::Reverb1).unwrap();
+        let encoded = grid.encode();
+        for (i, cell) in encoded.chunks_exact(4).enumerate() {
+            assert_eq!((i % 0x80) as u8, cell[3]);
+        }
+    }
+
+    #[test]
+    fn test_grid_edits() {
+        let mut grid = Grid::new(FractalModel::II);
+        grid.place(1, 2, Effect::Amp1).unwrap();
+        assert_eq!(
+            Some(&BlockGridBlock::EffectBlock {
+                effect_id: 106,
+                effect: Effect::Amp1,
+                connections: 0,
+            }),
+            grid.get(1, 2)
+        );
+        grid.place(1, 3, Effect::Cab1).unwrap();
+        grid.place(2, 3, Effect::Delay1).unwrap();
+        assert_eq!(
+            Err(GridError::Occupied { row: 1, column: 2 }),
+            grid.place(1, 2, Effect::Reverb1)
+        );
+        assert_eq!(
+            Err(GridError::AlreadyPlaced {
+                effect: Effect::Amp1,
+                row: 1,
+                column: 2
+            }),
+            grid.place(0, 0, Effect::Amp1)
+        );
+        assert_eq!(
+            Err(GridError::OutOfBounds { row: 4, column: 0 }),
+            grid.place(4, 0, Effect::Reverb1)
+        );
+
+        grid.connect((1, 2), (1, 3)).unwrap();
+        grid.connect((1, 2), (2, 3)).unwrap();
+        assert_eq!(
+            Some(&BlockGridBlock::EffectBlock {
+                effect_id: 108,
+                effect: Effect::Cab1,
+                connections: 0b10,
+            }),
+            grid.get(1, 3)
+        );
+        assert_eq!(Err(GridError::NotAdjacent), grid.connect((1, 2), (1, 2)));
+        assert_eq!(
+            Err(GridError::Empty { row: 0, column: 3 }),
+            grid.connect((1, 2), (0, 3))
+        );
+
+        grid.move_block((1, 2), (0, 1)).unwrap();
+        assert_eq!(Some((0, 1)), grid.find(Effect::Amp1));
+        assert_eq!(Some(&BlockGridBlock::Empty), grid.get(1, 2));
+        for row in 1..3 {
+            match grid.get(row, 3) {
+                Some(BlockGridBlock::EffectBlock { connections: 0, .. }) => {}
+                other => panic!("expected a disconnected block, got {:?}", other),
+            }
+        }
+
+        grid.connect((0, 1), (0, 2)).unwrap_err();
+        grid.place(0, 2, Effect::Reverb1).unwrap();
+        grid.connect((0, 1), (0, 2)).unwrap();
+        grid.remove(0, 1).unwrap();
+        assert_eq!(None, grid.find(Effect::Amp1));
+        match grid.get(0, 2) {
+            Some(BlockGridBlock::EffectBlock { connections: 0, .. }) => {}
+            other => panic!("expected a disconnected block, got {:?}", other),
+        }
+        assert_eq!(
+            Err(GridError::Empty { row: 0, column: 1 }),
+            grid.remove(0, 1)
+        );
+
+        grid.place(3, 0, Effect::Shunt).unwrap();
+        grid.place(3, 1, Effect::Shunt).unwrap();
+        assert_eq!(
+            Err(GridError::Empty { row: 0, column: 0 }),
+            grid.connect_input(0)
+        );
+        grid.connect_input(3).unwrap();
+        match grid.get(3, 0) {
+            Some(BlockGridBlock::EffectBlock {
+                connections: 0b1000,
+                ..
+            }) => {}
+            other => panic!("expected a block fed from the input, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_session_grid() {
+        let model = FractalModel::III;
+        let mut device = simulator::Simulator::new(model);
+        device.grid.place(5, 13, Effect::Reverb1).unwrap();
+        device.grid.place(5, 12, Effect::Delay1).unwrap();
+        device.grid.connect((5, 12), (5, 13)).unwrap();
+        let expected = device.grid.clone();
+        let mut session = Session::new(device, model);
+        assert_eq!(expected, session.grid().unwrap());
+    }
+
+    fn routed_grid() -> Grid {
+        let mut grid = Grid::new(FractalModel::II);
+        let row_1 = [
+            Effect::Compressor1,
+            Effect::Amp1,
+            Effect::Cab1,
+            Effect::Reverb1,
+        ];
+        for column in 0..12 {
+            let effect = row_1.get(column).copied().unwrap_or(Effect::Shunt);
+            grid.place(0, column, effect).unwrap();
+            if column > 0 {
+                grid.connect((0, column - 1), (0, column)).unwrap();
+            }
+        }
+        grid.connect_input(0).unwrap();
+        grid.place(1, 2, Effect::Delay1).unwrap();
+        grid.connect((0, 1), (1, 2)).unwrap();
+        grid.connect((1, 2), (0, 3)).unwrap();
+        grid.place(2, 5, Effect::Chorus1).unwrap();
+        grid.connect((0, 4), (2, 5)).unwrap();
+        grid.place(3, 8, Effect::Flanger1).unwrap();
+        grid
+    }
+
+    #[test]
+    fn test_grid_routing() {
+        use routing::{Cell, ParallelSection};
+        let cell = |row, column| Cell { row, column };
+
+        let routing = routed_grid().routing();
+        assert_eq!(2, routing.paths.len());
         assert_eq!(
-            vec![240, 0, 1, 116, 3, 41, 0, 47, 247],
-            set_scene_number(FractalModel::II, 0)
+            (0..12).map(|column| cell(0, column)).collect::<Vec<_>>(),
+            routing.paths[0]
         );
+        assert_eq!(cell(1, 2), routing.paths[1][2]);
+        assert!(!routing.truncated);
+        assert_eq!(vec![cell(3, 8)], routing.disconnected);
+        assert_eq!(vec![cell(2, 5)], routing.dead_ends);
         assert_eq!(
-            vec![0xF0, 0x00, 0x01, 0x74, 0x03, 0x29, 0x00, 0x2F, 0xF7],
-            set_scene_number(FractalModel::II, 0)
+            vec![ParallelSection {
+                first_column: 2,
+                last_column: 2,
+                rows: vec![0, 1],
+            }],
+            routing.parallel
         );
+        assert_eq!(Some(Effect::Delay1), routing.effect(cell(1, 2)));
+
+        let mut grid = Grid::new(FractalModel::III);
+        grid.place(0, 0, Effect::Amp1).unwrap();
+        grid.connect_input(0).unwrap();
+        let routing = grid.routing();
+        assert!(routing.paths.is_empty());
+        assert_eq!(vec![cell(0, 0)], routing.dead_ends);
     }
 
     #[test]
-    fn test_set_scene_number_axe_3() {
-        assert_eq!(
-            vec![0xF0, 0x00, 0x01, 0x74, 0x10, 0x0C, 0x01, 0x18, 0xF7],
-            set_scene_number(FractalModel::III, 1)
+    fn test_grid_routing_rendering() {
+        let routing = routed_grid().routing();
+        let text = routing.to_text();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(6, lines.len());
+        assert!(
+            lines[0].starts_with("in ─[Compressor1]───[Amp1       ]─┬─[Cab1       ]─┬─[Reverb1")
         );
+        assert!(lines[0].ends_with("[Shunt      ]─ out"));
+        assert!(lines[1].starts_with("                                  └─[Delay1     ]─┘    "));
+        assert!(lines[1].ends_with("│"));
+        assert_eq!("└─[Chorus1    ]", lines[2].trim_start());
+        assert_eq!("[Flanger1   ]", lines[3].trim_start());
+        assert_eq!("disconnected: Flanger1 (row 4, column 9)", lines[4]);
+        assert_eq!("dead end: Chorus1 (row 3, column 6)", lines[5]);
+
+        let dot = routing.to_dot();
+        assert!(dot.starts_with("digraph preset {\n    rankdir=LR;\n"));
+        assert!(dot.ends_with("    r1c12 -> output;\n}\n"));
+        for line in [
+            "    input -> r1c1;",
+            "    r1c2 -> r2c3;",
+            "    r2c3 -> r1c4;",
+            "    r1c5 -> r3c6;",
+            "    r2c3 [label=\"Delay1\", shape=box];",
+            "    r4c9 [label=\"Flanger1\", shape=box, style=dashed, color=red];",
+        ]
+        .iter()
+        {
+            assert!(dot.lines().any(|l| l == *line), "missing {}", line);
+        }
+        assert!(!dot.contains("r4c9 ->"));
     }
 
-    #[test]
-    fn test_get_grid_layout_and_routing() {
-        assert_eq!(
-            vec![240, 0, 1, 116, 3, 0x20, 38, 0xF7],
-            get_grid_layout_and_routing(FractalModel::II)
-        );
+    fn ms(millis: u64) -> std::time::Duration {
+        std::time::Duration::from_millis(millis)
+    }
+
+    fn round_bpm(bpm: Option<f64>) -> Option<f64> {
+        bpm.map(|bpm| (bpm * 100.0).round() / 100.0)
     }
 
     #[test]
-    fn test_parse_scene_number() {
-        assert_eq!(
-            parse_message(vec![240, 0, 1, 116, 3, 41, 0, 47, 247]),
-            FractalMessage::CurrentSceneNumber(1)
-        );
+    fn test_tempo_tracker() {
+        let mut tracker = tempo::TempoTracker::new();
+        assert_eq!(None, tracker.beat(ms(0)));
+        let mut at = 0;
+        for i in 0..16 {
+            at += if i % 2 == 0 { 490 } else { 510 };
+            tracker.beat(ms(at));
+        }
+        let bpm = tracker.bpm().unwrap();
+        assert!((bpm - 120.0).abs() < 1.5, "{}", bpm);
+
+        // A missed beat message doesn't move the estimate.
+        at += 1000;
+        assert_eq!(Some(bpm), tracker.beat(ms(at)));
+
+        // Two agreeing intervals at a new tempo replace it.
+        at += 750;
+        assert_eq!(Some(bpm), tracker.beat(ms(at)));
+        at += 750;
+        assert_eq!(Some(80.0), round_bpm(tracker.beat(ms(at))));
         assert_eq!(
-            parse_message(vec![240, 0, 1, 116, 3, 41, 1, 47, 247]),
-            FractalMessage::CurrentSceneNumber(2)
+            Some(80.0),
+            round_bpm(tracker.handle(&FractalMessage::CurrentTempo(140), ms(at + 10)))
         );
+        at += 750;
         assert_eq!(
-            parse_message(vec![240, 0, 1, 116, 3, 41, 7, 47, 247]),
-            FractalMessage::CurrentSceneNumber(8)
+            Some(80.0),
+            round_bpm(tracker.handle(&FractalMessage::MIDITempoBeat, ms(at)))
         );
-        assert_eq!(
-            parse_message(vec![0xF0, 0x00, 0x01, 0x74, 0x10, 0x0C, 0x01, 0x18, 0xF7,]),
-            FractalMessage::CurrentSceneNumber(1)
-        )
+
+        // Beats stopping clears it.
+        at += 5000;
+        assert_eq!(None, tracker.beat(ms(at)));
+        at += 400;
+        assert_eq!(Some(150.0), round_bpm(tracker.beat(ms(at))));
+
+        tracker.reset();
+        assert_eq!(None, tracker.bpm());
     }
 
     #[test]
-    fn test_parse_grid_layout_and_routing() {
+    fn test_tap_tempo() {
+        use tempo::TapTempo;
+        let tap_all = |taps: &[u64]| {
+            let mut tapper = TapTempo::new();
+            for at in taps {
+                tapper.tap(ms(*at));
+            }
+            round_bpm(tapper.bpm())
+        };
+        assert_eq!(None, tap_all(&[]));
+        assert_eq!(None, tap_all(&[100]));
+        assert_eq!(Some(120.0), tap_all(&[0, 500, 1000, 1500]));
+        // A skipped tap.
+        assert_eq!(Some(120.0), tap_all(&[0, 500, 1500, 2000, 2500]));
+        // A doubled tap.
+        assert_eq!(Some(100.0), tap_all(&[0, 600, 900, 1200, 1800, 2400]));
+        // A late tap is dropped.
+        assert_eq!(Some(120.0), tap_all(&[0, 500, 1000, 1350, 1850]));
+        // Too fast for the device: counted in half time.
+        assert_eq!(Some(150.0), tap_all(&[0, 200, 400, 600]));
+        // A pause starts over.
+        assert_eq!(Some(75.0), tap_all(&[0, 500, 1000, 4000, 4800]));
+        // Only the latest taps count.
+        let mut taps: Vec<u64> = (0..8).map(|i| i * 400).collect();
+        taps.extend((1..=8).map(|i| 2800 + i * 500));
+        assert_eq!(Some(120.0), tap_all(&taps));
+
+        let mut tapper = TapTempo::new();
+        assert_eq!(None, tapper.message(FractalModel::III));
+        tapper.tap(ms(10_000));
+        tapper.tap(ms(10_500));
         assert_eq!(
-            parse_message(vec![
-                240, 0, 1, 116, 3, 32, 0, 0, 0, 0, 127, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                0, 100, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 2, 0, 0, 0, 0, 0, 0, 0,
-                0, 0, 0, 0, 0, 0, 5, 1, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 6, 1, 2, 0, 0, 0,
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 106, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                108, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 79, 1, 2, 0, 0, 0, 0, 0, 0, 0, 0,
-                0, 0, 0, 0, 0, 112, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 114, 0, 2, 0, 0,
-                0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 110, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                7, 1, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 29, 247
-            ]),
-            FractalMessage::BlockGrid([
-                [
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::EffectBlock {
-                        effect_id: 127,
-                        effect: Effect::VolumePan1,
-                        connect_row_1: false,
-                        connect_row_2: true,
-                        connect_row_3: false,
-                        connect_row_4: false,
-                    },
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                ],
-                [
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::EffectBlock {
-                        effect_id: 100,
-                        effect: Effect::Compressor1,
-                        connect_row_1: false,
-                        connect_row_2: true,
-                        connect_row_3: false,
-                        connect_row_4: false,
-                    },
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                ],
-                [
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::EffectBlock {
-                        effect_id: 128,
-                        effect: Effect::TremoloPanner1,
-                        connect_row_1: false,
-                        connect_row_2: true,
-                        connect_row_3: false,
-                        connect_row_4: false,
-                    },
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                ],
-                [
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::EffectBlock {
-                        effect_id: 133,
-                        effect: Effect::Drive1,
-                        connect_row_1: false,
-                        connect_row_2: true,
-                        connect_row_3: false,
-                        connect_row_4: false,
-                    },
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                ],
-                [
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::EffectBlock {
-                        effect_id: 134,
-                        effect: Effect::Drive2,
-                        connect_row_1: false,
-                        connect_row_2: true,
-                        connect_row_3: false,
-                        connect_row_4: false,
-                    },
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                ],
-                [
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::EffectBlock {
-                        effect_id: 106,
-                        effect: Effect::Amp1,
-                        connect_row_1: false,
-                        connect_row_2: true,
-                        connect_row_3: false,
-                        connect_row_4: false,
-                    },
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                ],
-                [
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::EffectBlock {
-                        effect_id: 108,
-                        effect: Effect::Cab1,
-                        connect_row_1: false,
-                        connect_row_2: true,
-                        connect_row_3: false,
-                        connect_row_4: false,
-                    },
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                ],
-                [
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::EffectBlock {
-                        effect_id: 207,
-                        effect: Effect::Shunt,
-                        connect_row_1: false,
-                        connect_row_2: true,
-                        connect_row_3: false,
-                        connect_row_4: false,
-                    },
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                ],
-                [
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::EffectBlock {
-                        effect_id: 112,
-                        effect: Effect::Delay1,
-                        connect_row_1: false,
-                        connect_row_2: true,
-                        connect_row_3: false,
-                        connect_row_4: false,
-                    },
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                ],
-                [
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::EffectBlock {
-                        effect_id: 114,
-                        effect: Effect::MultiDelay1,
-                        connect_row_1: false,
-                        connect_row_2: true,
-                        connect_row_3: false,
-                        connect_row_4: false,
-                    },
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                ],
-                [
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::EffectBlock {
-                        effect_id: 110,
-                        effect: Effect::Reverb1,
-                        connect_row_1: false,
-                        connect_row_2: true,
-                        connect_row_3: false,
-                        connect_row_4: false,
-                    },
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                ],
-                [
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::EffectBlock {
-                        effect_id: 135,
-                        effect: Effect::Enhancer1,
-                        connect_row_1: false,
-                        connect_row_2: true,
-                        connect_row_3: false,
-                        connect_row_4: false,
-                    },
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                ],
-                [
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                ],
-                [
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                ],
-                [
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                ],
-                [
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                    BlockGridBlock::Empty,
-                ],
-            ])
+            Some(set_tempo(FractalModel::III, 120)),
+            tapper.message(FractalModel::III)
         );
+        tapper.reset();
+        assert_eq!(None, tapper.bpm());
     }
 
     #[test]
     fn test_get_block_parameters() {
         assert_eq!(
-            vec![240, 0, 1, 116, 3, 0x01, 0, 1, 6, 0xF7],
+            Some(vec![240, 0, 1, 116, 3, 0x01, 0, 1, 6, 0xF7]),
             get_block_parameters(FractalModel::II, Effect::TremoloPanner1)
         );
         assert_eq!(
-            vec![240, 0, 1, 116, 3, 0x01, 127, 0, 120, 0xF7],
+            Some(vec![240, 0, 1, 116, 3, 0x01, 127, 0, 120, 0xF7]),
             get_block_parameters(FractalModel::II, Effect::VolumePan1)
         );
         assert_eq!(
-            vec![240, 0, 1, 116, 3, 0x01, 1, 1, 7, 0xF7],
+            Some(vec![240, 0, 1, 116, 3, 0x01, 1, 1, 7, 0xF7]),
             get_block_parameters(FractalModel::II, Effect::TremoloPanner2)
         );
     }
@@ -1219,6 +3319,499 @@ mod tests {
         );
     }
 
+    fn vlq(mut value: u32) -> Vec<u8> {
+        let mut bytes = vec![(value & 0x7F) as u8];
+        value >>= 7;
+        while value > 0 {
+            bytes.insert(0, (value & 0x7F) as u8 | 0x80);
+            value >>= 7;
+        }
+        bytes
+    }
+
+    /// A Standard MIDI File holding `tracks`, each a list of
+    /// `(delta, event)` pairs; the end of track event is added.
+    fn smf_bytes(format: u16, division: [u8; 2], tracks: &[Vec<(u32, Vec<u8>)>]) -> Vec<u8> {
+        let mut bytes = b"MThd".to_vec();
+        bytes.extend(&6u32.to_be_bytes());
+        bytes.extend(&format.to_be_bytes());
+        bytes.extend(&(tracks.len() as u16).to_be_bytes());
+        bytes.extend(&division);
+        for track in tracks {
+            let mut events: Vec<u8> = track
+                .iter()
+                .flat_map(|(delta, event)| [vlq(*delta), event.clone()].concat())
+                .collect();
+            events.extend(&[0x00, 0xFF, 0x2F, 0x00]);
+            bytes.extend(b"MTrk");
+            bytes.extend(&(events.len() as u32).to_be_bytes());
+            bytes.extend(events);
+        }
+        bytes
+    }
+
+    fn sysex_event(msg: &[u8]) -> Vec<u8> {
+        [vec![0xF0], vlq(msg.len() as u32 - 1), msg[1..].to_vec()].concat()
+    }
+
+    fn tempo_event(bpm: u32) -> Vec<u8> {
+        let micros = 60_000_000 / bpm;
+        vec![
+            0xFF,
+            0x51,
+            0x03,
+            (micros >> 16) as u8,
+            (micros >> 8) as u8,
+            micros as u8,
+        ]
+    }
+
+    #[test]
+    fn test_read_smf_format_0() {
+        let iii = FractalModel::III;
+        // 480 ticks per quarter: half a second per beat at 120 BPM, a second
+        // at 60.
+        let bytes = smf_bytes(
+            0,
+            [0x01, 0xE0],
+            &[vec![
+                (0, tempo_event(120)),
+                (0, sysex_event(&set_scene_number(iii, 1))),
+                (480, vec![0x90, 0x40, 0x7F]),
+                (0, sysex_event(&set_tempo(iii, 60))),
+                (480, tempo_event(60)),
+                (480, sysex_event(&set_scene_number(iii, 2))),
+            ]],
+        );
+        let captured = capture::read_smf(&bytes).unwrap();
+        let timeline: Vec<(Option<std::time::Duration>, &FractalMessage)> = captured
+            .iter()
+            .map(|captured| (captured.time, &captured.message))
+            .collect();
+        assert_eq!(
+            vec![
+                (Some(ms(0)), &FractalMessage::CurrentSceneNumber(1)),
+                (Some(ms(500)), &FractalMessage::CurrentTempo(60)),
+                (Some(ms(2000)), &FractalMessage::CurrentSceneNumber(2)),
+            ],
+            timeline
+        );
+        assert_eq!(set_tempo(iii, 60), captured[1].bytes);
+    }
+
+    #[test]
+    fn test_read_smf_format_1() {
+        let iii = FractalModel::III;
+        let mut payload = vec![0x10, 0x0E, 0x02];
+        payload.extend(format!("{:<32}", "Lead solo").bytes());
+        let name = wrap_msg(payload);
+        let (first, rest) = name.split_at(20);
+        let (middle, last) = rest.split_at(10);
+        let bytes = smf_bytes(
+            1,
+            [0x00, 0x60],
+            &[
+                vec![(0, tempo_event(120)), (192, tempo_event(240))],
+                vec![
+                    (96, vec![0xB0, 0x07, 0x64]),
+                    // Running status, then a message split over three
+                    // events with a realtime escape between two of them.
+                    (0, vec![0x07, 0x65]),
+                    (
+                        96,
+                        [vec![0xF0], vlq(first.len() as u32 - 1), first[1..].to_vec()].concat(),
+                    ),
+                    (
+                        10,
+                        [vec![0xF7], vlq(middle.len() as u32), middle.to_vec()].concat(),
+                    ),
+                    (0, vec![0xF7, 0x01, 0xF8]),
+                    (
+                        10,
+                        [vec![0xF7], vlq(last.len() as u32), last.to_vec()].concat(),
+                    ),
+                ],
+                vec![(288, sysex_event(&get_tempo(iii)))],
+            ],
+        );
+        let captured = capture::read_smf(&bytes).unwrap();
+        assert_eq!(2, captured.len());
+        assert_eq!(Some(ms(1000)), captured[0].time);
+        assert_eq!(1, captured[0].track);
+        assert_eq!(name, captured[0].bytes);
+        assert_eq!(
+            FractalMessage::SceneName(2, String::from("Lead solo")),
+            captured[0].message
+        );
+        // 192 ticks at 120 BPM, then 96 at 240.
+        assert_eq!(Some(ms(1250)), captured[1].time);
+        assert_eq!(2, captured[1].track);
+    }
+
+    #[test]
+    fn test_read_smf_timecode_and_errors() {
+        // 25 fps with 40 ticks per frame: a millisecond per tick, whatever
+        // the tempo.
+        let bytes = smf_bytes(
+            0,
+            [0xE7, 40],
+            &[vec![
+                (0, tempo_event(60)),
+                (1500, sysex_event(&get_tempo(FractalModel::III))),
+            ]],
+        );
+        let captured = capture::read_smf(&bytes).unwrap();
+        assert_eq!(Some(ms(1500)), captured[0].time);
+
+        assert_eq!(Err(capture::SmfError::NotSmf), capture::read_smf(b"RIFF"));
+        assert_eq!(
+            Err(capture::SmfError::UnsupportedFormat(2)),
+            capture::read_smf(&smf_bytes(2, [0, 96], &[]))
+        );
+        let mut truncated = smf_bytes(0, [0, 96], &[vec![(0, tempo_event(120))]]);
+        truncated.truncate(truncated.len() - 6);
+        assert_eq!(
+            Err(capture::SmfError::Truncated { offset: 14 }),
+            capture::read_smf(&truncated)
+        );
+        let no_status = smf_bytes(0, [0, 96], &[vec![(0, vec![0x40, 0x7F])]]);
+        assert_eq!(
+            Err(capture::SmfError::InvalidEvent {
+                offset: 23,
+                byte: 0x40,
+            }),
+            capture::read_smf(&no_status)
+        );
+    }
+
+    #[test]
+    fn test_read_raw_log() {
+        let iii = FractalModel::III;
+        let scene = set_scene_number(iii, 3);
+        let mut interrupted = scene.clone();
+        interrupted.insert(4, 0xF8);
+        let log = [
+            vec![0xB0, 0x07, 0x64],
+            interrupted,
+            vec![0xF0, 0x00, 0x01],
+            get_tempo(iii),
+        ]
+        .concat();
+        let captured = capture::read_capture(&log).unwrap();
+        let messages: Vec<(&MidiMessage, &FractalMessage)> = captured
+            .iter()
+            .map(|captured| (&captured.bytes, &captured.message))
+            .collect();
+        let tempo = get_tempo(iii);
+        assert_eq!(
+            vec![
+                (&scene, &FractalMessage::CurrentSceneNumber(3)),
+                (&tempo, &FractalMessage::CurrentTempo(0x3FFF)),
+            ],
+            messages
+        );
+        assert!(captured.iter().all(|captured| captured.time.is_none()));
+    }
+
+    #[test]
+    fn test_get_tempo() {
+        assert_eq!(
+            vec![0xF0, 0x00, 0x01, 0x74, 0x10, 0x14, 0x7F, 0x7F, 0x01, 0xF7],
+            get_tempo(FractalModel::III)
+        );
+    }
+
+    #[test]
+    fn test_session_tempo() {
+        let mut session = Session::new(
+            simulator::Simulator::new(FractalModel::III),
+            FractalModel::III,
+        );
+        session.send(&set_tempo(FractalModel::III, 132)).unwrap();
+        assert_eq!(132, session.tempo().unwrap());
+
+        let mut session = Session::new(
+            simulator::Simulator::new(FractalModel::II),
+            FractalModel::II,
+        );
+        assert!(matches!(session.tempo(), Err(SessionError::Unsupported)));
+    }
+
+    #[test]
+    fn test_parse_hex() {
+        let expected = vec![0xF0, 0x00, 0x01, 0x74, 0x10, 0x14, 0x46, 0x00, 0x47, 0xF7];
+        assert_eq!(
+            Ok(expected.clone()),
+            parse_hex("F0 00 01 74 10 14 46 00 47 F7")
+        );
+        assert_eq!(
+            Ok(expected.clone()),
+            parse_hex("0xF0, 0x00, 0x01, 0x74,\n0x10, 0x14, 0x46, 0x00, 0x47, 0xF7")
+        );
+        assert_eq!(Ok(expected.clone()), parse_hex("f0000174 10144600 47f7"));
+        assert_eq!(
+            Ok(expected.clone()),
+            parse_hex("00000000  f0 00 01 74 10 14 46 00  47 f7                    |...t..F.G.|\n")
+        );
+        assert_eq!(
+            Ok(expected),
+            parse_hex("00000000: f000 0174 1014 4600 47f7            ...t..F.G.\n")
+        );
+        assert_eq!(
+            Err(HexError {
+                line: 2,
+                token: String::from("F"),
+            }),
+            parse_hex("F0 00\nF 7F")
+        );
+    }
+
+    #[test]
+    fn test_describe_request() {
+        // The set_preset_name capture, pasted as it appears in the source.
+        let bytes = parse_hex(
+            "// 00  F0 00 01 74 10 01 28 00  00 00 00 00 05 03 00 00  |   t  (         |
+             // 10  00 00 00 20 00 30 48 04  02 01 00 40 20 10 08 04  |     0H    @    |
+             // 20  02 01 00 40 20 10 08 04  02 01 00 40 20 10 08 04  |   @       @    |
+             // 30  02 01 00 40 20 10 08 04  02 00 64 F7              |   @      d |",
+        )
+        .unwrap();
+        let descriptions = describe(&bytes);
+        assert_eq!(1, descriptions.len());
+        let description = &descriptions[0];
+        assert_eq!(Some(FractalModel::III), description.model);
+        assert_eq!(Some(Direction::Request), description.direction);
+        assert_eq!(ChecksumStatus::Ok, description.checksum);
+        assert!(description.is_clean());
+        let meanings: Vec<(&str, &str)> = description
+            .fields
+            .iter()
+            .filter(|field| field.name != "padding")
+            .map(|field| (field.name, field.meaning.as_deref().unwrap()))
+            .collect();
+        assert_eq!(
+            vec![
+                ("editor command", "rename preset"),
+                ("preset", "preset 389"),
+                ("name length", "32 characters"),
+                ("name", "\"a\""),
+                ("checksum", "ok"),
+            ],
+            meanings
+        );
+    }
+
+    #[test]
+    fn test_describe_response() {
+        let status = describe(&wrap_msg(vec![
+            0x10, 0x13, 0x3A, 0x00, 0x40, 0x3E, 0x00, 0x42, 0x7A, 0x00, 0x01,
+        ]));
+        let description = &status[0];
+        assert_eq!("status dump", description.function);
+        assert_eq!(Some(Direction::Response), description.direction);
+        assert_eq!(
+            Some("Amp1 (ID 58), engaged, channel A, 4 channels"),
+            description.fields[0].meaning.as_deref()
+        );
+        assert_eq!(
+            Some("Cab1 (ID 62), engaged, channel B, 4 channels"),
+            description.fields[1].meaning.as_deref()
+        );
+        assert_eq!(
+            Some("Enhancer1 (ID 122), bypassed, channel A, 0 channels"),
+            description.fields[2].meaning.as_deref()
+        );
+
+        // The II sends tuner info without a checksum.
+        let tuner = &describe(&[0xF0, 0x00, 0x01, 0x74, 0x03, 0x0D, 0x00, 0x05, 0x3F, 0xF7])[0];
+        assert_eq!(ChecksumStatus::NotSent, tuner.checksum);
+        assert_eq!(Some(Direction::Response), tuner.direction);
+        let meanings: Vec<&str> = tuner
+            .fields
+            .iter()
+            .map(|field| field.meaning.as_deref().unwrap())
+            .collect();
+        assert_eq!(vec!["A", "string 5", "+0.0 cents"], meanings);
+
+        let ack = &describe(&wrap_msg(vec![0x10, 0x64, 0x0C, 0x00]))[0];
+        assert_eq!(
+            "III 0x64 multipurpose response (response)\n\
+             \x20   6  0C                       function: 0x0C scene number\n\
+             \x20   7  00                       result: ok\n\
+             \x20   8  7D                       checksum: ok\n",
+            ack.to_string()
+        );
+    }
+
+    #[test]
+    fn test_describe_flags_problems() {
+        let mut bad_checksum = set_tempo(FractalModel::III, 120);
+        bad_checksum[8] ^= 1;
+        let description = &describe(&bad_checksum)[0];
+        assert_eq!(
+            ChecksumStatus::Mismatch {
+                expected: bad_checksum[8] ^ 1,
+                actual: bad_checksum[8],
+            },
+            description.checksum
+        );
+        assert!(!description.is_clean());
+
+        let extra = wrap_msg(vec![0x10, 0x0C, 0x02, 0x55, 0x66]);
+        let description = &describe(&extra)[0];
+        let unexplained: Vec<&Field> = description.unexplained().collect();
+        assert_eq!(1, unexplained.len());
+        assert_eq!(7, unexplained[0].offset);
+        assert_eq!(vec![0x55, 0x66], unexplained[0].bytes);
+
+        let unknown = &describe(&wrap_msg(vec![0x10, 0x55, 0x01]))[0];
+        assert_eq!("unknown function", unknown.function);
+        assert_eq!(1, unknown.unexplained().count());
+
+        // A stray byte, a message cut off by the next one, then a short one.
+        let stream = [
+            &[0x90][..],
+            &[0xF0, 0x00, 0x01, 0x74, 0x10, 0x0C],
+            &wrap_msg(vec![0x10, 0x64, 0x0C]),
+        ]
+        .concat();
+        let descriptions = describe(&stream);
+        assert_eq!(3, descriptions.len());
+        assert_eq!("bytes outside a SysEx message", descriptions[0].function);
+        assert_eq!(
+            vec![
+                String::from("message is not terminated by 0xF7"),
+                String::from("message ends before the checksum"),
+                String::from("payload ends before the scene"),
+            ],
+            descriptions[1].problems
+        );
+        assert_eq!(
+            vec![String::from("payload ends before the result")],
+            descriptions[2].problems
+        );
+    }
+
+    #[test]
+    fn test_describe_out_of_range_bytes() {
+        let description = &describe(&[0xF0, 0x00, 0x01, 0x74, 0x03, 0x17, 0xFF, 0xF7])[0];
+        assert!(description.problems.contains(&String::from(
+            "byte 0xFF at offset 6 is not a 7-bit data byte"
+        )));
+
+        let meaning = |message: Vec<u8>, name| {
+            describe(&message)[0]
+                .fields
+                .iter()
+                .find(|field| field.name == name)
+                .and_then(|field| field.meaning.clone())
+        };
+        assert_eq!(
+            Some(String::from("channel 256")),
+            meaning(wrap_msg(vec![0x03, 0x17, 0xFF, 0x00]), "channel")
+        );
+        assert_eq!(
+            Some(String::from("scene 256")),
+            meaning(wrap_msg(vec![0x10, 0x0C, 0xFF, 0x00]), "scene")
+        );
+
+        let grid = wrap_msg(vec![0x03, 0x20, 0xFF, 0x00, 0x6A, 0x00, 0x00, 0x00, 0x00]);
+        assert_eq!(1, describe(&grid).len());
+    }
+
+    #[test]
+    fn test_describe_explains_every_reply() {
+        fn replies(model: FractalModel, requests: &[MidiMessage]) -> Vec<u8> {
+            let mut device = simulator::Simulator::new(model);
+            device.effects = vec![EffectStatus {
+                effect_id: 58,
+                effect_id_iii: Some(EffectID::ID_DISTORT1),
+                effect: Effect::Amp1,
+                bypassed: false,
+                channel: Channel::B,
+                max_channels: 4,
+            }];
+            device.inject_front_panel_change();
+            device.inject_tempo_beat();
+            let mut bytes = vec![];
+            for request in requests {
+                device.send(request).unwrap();
+                loop {
+                    let reply = device.receive(ms(0)).unwrap();
+                    if reply.is_empty() {
+                        break;
+                    }
+                    bytes.extend(reply);
+                }
+            }
+            bytes
+        }
+
+        let iii = FractalModel::III;
+        let ii = FractalModel::II;
+        let bytes = [
+            replies(
+                iii,
+                &[
+                    get_current_preset_name(iii),
+                    get_scene_name(iii, 0),
+                    set_scene_number(iii, 1),
+                    get_tempo(iii),
+                    status_dump(iii),
+                    get_grid_layout_and_routing(iii),
+                    toggle_tuner_sysex(iii, TunerStatus::On),
+                    get_firmware_version(iii),
+                    get_midi_channel(iii),
+                    get_looper_state(iii),
+                    get_block_bypass(iii, EffectID::ID_DISTORT1),
+                    get_block_channel(iii, EffectID::ID_DISTORT1),
+                    get_block_parameters(iii, Effect::Amp1).unwrap(),
+                    store_in_preset(iii, 3),
+                ],
+            ),
+            replies(
+                ii,
+                &[
+                    get_preset_number(ii),
+                    get_current_preset_name(ii),
+                    set_scene_number(ii, 2),
+                    get_preset_blocks_flags(ii),
+                    get_grid_layout_and_routing(ii),
+                    set_block_parameter(ii, Effect::Amp1, 2, 25454).unwrap(),
+                    get_block_parameters(ii, Effect::Amp1).unwrap(),
+                    store_in_preset(ii, 3),
+                ],
+            ),
+            preset_dump_bytes(iii, (0x7F, 0x7F), "Dump"),
+        ]
+        .concat();
+        let descriptions = describe(&bytes);
+        for description in &descriptions {
+            // Nobody knows what the bytes before a parameter's label mean.
+            if description.function_id != Some(0x02) {
+                assert!(description.is_clean(), "{}", description);
+            }
+            assert!(description.problems.is_empty(), "{}", description);
+            assert_ne!(
+                Some(Direction::Request),
+                description.direction,
+                "{}",
+                description
+            );
+        }
+        let functions: std::collections::BTreeSet<u8> = descriptions
+            .iter()
+            .filter_map(|description| description.function_id)
+            .collect();
+        assert_eq!(
+            vec![
+                0x01, 0x02, 0x08, 0x0A, 0x0B, 0x0C, 0x0D, 0x0E, 0x0F, 0x10, 0x11, 0x13, 0x14, 0x17,
+                0x20, 0x21, 0x29, 0x64, 0x77, 0x78, 0x79
+            ],
+            functions.into_iter().collect::<Vec<u8>>()
+        );
+    }
+
     #[test]
     fn test_parse_status_dump() {
         assert_eq!(
@@ -1384,7 +3977,7 @@ mod tests {
                 EffectStatus {
                     effect_id: 201,
                     effect_id_iii: None,
-                    effect: Effect::Unknown,
+                    effect: Effect::Unknown(201),
                     bypassed: false,
                     channel: Channel::A,
                     max_channels: 1
@@ -1400,6 +3993,161 @@ mod tests {
             ]),
         );
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_json_shape() {
+        use serde_json::json;
+        let to_json = |message: &FractalMessage| serde_json::to_value(message).unwrap();
+        assert_eq!(
+            json!({"type": "CurrentPresetNumber", "data": 12}),
+            to_json(&FractalMessage::CurrentPresetNumber(12))
+        );
+        assert_eq!(
+            json!({"type": "PresetName", "data": [12, "Clean"]}),
+            to_json(&FractalMessage::PresetName(12, "Clean".to_string()))
+        );
+        assert_eq!(
+            json!({"type": "FrontPanelChangeDetected"}),
+            to_json(&FractalMessage::FrontPanelChangeDetected)
+        );
+        assert_eq!(
+            json!({"type": "Unknown", "data": "F0 43 10 F7"}),
+            to_json(&FractalMessage::Unknown(vec![0xF0, 0x43, 0x10, 0xF7]))
+        );
+        assert_eq!(
+            json!({
+                "type": "BlockChannel",
+                "data": {"effect_id": 106, "effect": "Amp1", "channel": "B"}
+            }),
+            to_json(&FractalMessage::BlockChannel {
+                effect_id: 106,
+                effect: Effect::Amp1,
+                channel: Channel::B,
+            })
+        );
+        assert_eq!(
+            json!({"type": "TunerStatus", "data": "On"}),
+            to_json(&FractalMessage::TunerStatus(TunerStatus::On))
+        );
+        assert_eq!(
+            json!({
+                "type": "PresetDumpMessage",
+                "data": {
+                    "model": "III",
+                    "function_id": 0x79,
+                    "payload": "00 00",
+                    "checksum": 0x6C
+                }
+            }),
+            to_json(&FractalMessage::PresetDumpMessage(SysexFrame::new(
+                FractalModel::III,
+                0x79,
+                vec![0, 0]
+            )))
+        );
+        assert_eq!(
+            json!({"Unknown": 201}),
+            serde_json::to_value(Effect::Unknown(201)).unwrap()
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_grid_rows() {
+        use serde_json::json;
+        let json = serde_json::to_value(routed_grid()).unwrap();
+        assert_eq!(json!("II"), json["model"]);
+        let rows = json["rows"].as_array().unwrap();
+        assert_eq!(4, rows.len());
+        assert!(rows.iter().all(|row| row.as_array().unwrap().len() == 12));
+        assert_eq!(json!("Delay1"), rows[1][2]["effect"]);
+        assert_eq!(json!("EffectBlock"), rows[1][2]["type"]);
+        assert_eq!(json!(1), rows[1][2]["connections"]);
+        assert_eq!(json!({"type": "Empty"}), rows[1][3]);
+
+        let mut short = json.clone();
+        short["rows"].as_array_mut().unwrap().pop();
+        assert!(serde_json::from_value::<Grid>(short).is_err());
+        let mut wrong_model = json;
+        wrong_model["model"] = json!("III");
+        assert!(serde_json::from_value::<Grid>(wrong_model).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let mut grid = Grid::new(FractalModel::III);
+        grid.place(5, 13, Effect::Reverb1).unwrap();
+        grid.place(0, 0, Effect::Amp1).unwrap();
+        let messages = vec![
+            FractalMessage::Unknown(vec![0xF0, 0x43, 0x10, 0xF7]),
+            FractalMessage::StatusDump(vec![EffectStatus {
+                effect_id: 58,
+                effect_id_iii: Some(EffectID::ID_DISTORT1),
+                effect: Effect::Amp1,
+                bypassed: true,
+                channel: Channel::D,
+                max_channels: 4,
+            }]),
+            FractalMessage::LooperState {
+                record: true,
+                play: false,
+                overdub: false,
+                once: true,
+                reverse: false,
+                half_speed: true,
+            },
+            FractalMessage::SceneName(3, "Lead".to_string()),
+            FractalMessage::CurrentTempo(120),
+            FractalMessage::FirmwareVersion { major: 8, minor: 2 },
+            FractalMessage::MIDITempoBeat,
+            FractalMessage::TunerInfo {
+                note: 4,
+                string_number: 6,
+                tuner_data: 63,
+            },
+            FractalMessage::PresetBlocksFlags(vec![BlockFlags {
+                is_bypassed: false,
+                xy_state: XYState::Y,
+                cc: 37,
+                effect_id: 106,
+                effect: Effect::Amp1,
+            }]),
+            FractalMessage::BlockGrid(routed_grid()),
+            FractalMessage::BlockGrid(grid),
+            FractalMessage::BlockParameterValue {
+                effect_id: 106,
+                effect: Effect::Amp1,
+                parameter_id: 1,
+                parameter: Parameter::InputDrive,
+                value_raw: 32768,
+                label: "5.00".to_string(),
+            },
+            FractalMessage::PresetDumpMessage(SysexFrame::new(
+                FractalModel::II,
+                0x77,
+                vec![0, 12, 0, 3],
+            )),
+            FractalMessage::TunerStatus(TunerStatus::Off),
+            FractalMessage::MultipurposeResponse {
+                function_id: 0x0C,
+                response_code: 0,
+            },
+        ];
+        for message in messages {
+            let json = serde_json::to_string(&message).unwrap();
+            let back: FractalMessage = serde_json::from_str(&json).unwrap();
+            assert_eq!(message, back, "{}", json);
+        }
+
+        let parsed = parse_message(get_preset_number(FractalModel::III));
+        let json = serde_json::to_string(&parsed).unwrap();
+        assert_eq!(parsed, serde_json::from_str(&json).unwrap());
+        assert!(
+            serde_json::from_str::<FractalMessage>(r#"{"type":"Unknown","data":"F0 zz"}"#).is_err()
+        );
+    }
 }
 
 #[cfg(target_os = "macos")]
@@ -1462,24 +4210,17 @@ mod test_integration {
                             return;
                         }
                         let source = maybe_source.unwrap();
-                        let mut currently_receiving_msg = vec![];
+                        let mut assembler = SysexAssembler::new();
                         let received_messages: Arc<Mutex<Vec<MidiMessage>>> =
                             Arc::new(Mutex::new(vec![]));
                         let received_messages_writer = Arc::clone(&received_messages);
                         let callback = move |packet_list: &coremidi::PacketList| {
                             // println!("Received Packet: {}", packet_list);
                             for packet in packet_list.iter() {
-                                for byte in packet.data() {
-                                    currently_receiving_msg.push(byte.clone());
-                                    if *byte == 0xF7 as u8 {
-                                        // sysex message end, flush and parse
-                                        received_messages_writer
-                                            .lock()
-                                            .unwrap()
-                                            .push(currently_receiving_msg.clone());
-                                        currently_receiving_msg = vec![];
-                                    }
-                                }
+                                received_messages_writer
+                                    .lock()
+                                    .unwrap()
+                                    .extend(assembler.push(packet.data()));
                             }
                         };
                         let input_port = client.input_port("example-port", callback).unwrap();
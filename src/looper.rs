@@ -0,0 +1,248 @@
+//! Driving the looper without losing track of what it's doing.
+//!
+//! The looper only takes the six raw codes of `LooperState`, and several of
+//! them mean different things depending on the current state: Record while
+//! playing starts an overdub, Play while playing stops. `Looper` keeps track
+//! of the state, turns commands into the right codes, and refuses commands
+//! that don't apply. It follows the device through `handle`, so presses on
+//! the unit's own footswitches are picked up too.
+//!
+//! Only the Axe-Fx III family reports looper state over SysEx.
+
+use crate::tempo::{MAX_TEMPO, MIN_TEMPO};
+use crate::{set_looper_state, FractalMessage, FractalModel, LooperState, MidiMessage};
+use std::fmt;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LooperPhase {
+    /// Nothing recorded.
+    Empty,
+    Recording,
+    Playing,
+    Overdubbing,
+    /// A loop is recorded but not playing.
+    Stopped,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LooperCommand {
+    Record,
+    Play,
+    Stop,
+    Overdub,
+    /// Removes the last overdub, or the loop itself once none are left.
+    Undo,
+    /// Erases a stopped loop and all its overdubs.
+    Clear,
+    Once,
+    Reverse,
+    HalfSpeed,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LooperError {
+    /// The model doesn't report looper state over SysEx.
+    Unsupported(FractalModel),
+    /// The command doesn't apply in the looper's current phase.
+    InvalidCommand {
+        command: LooperCommand,
+        phase: LooperPhase,
+    },
+    /// The tempo is outside `MIN_TEMPO..=MAX_TEMPO`, or not a number.
+    InvalidTempo(f64),
+}
+
+impl fmt::Display for LooperError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LooperError::Unsupported(model) => {
+                write!(f, "{:?} does not report looper state", model)
+            }
+            LooperError::InvalidCommand { command, phase } => {
+                write!(f, "cannot {:?} while the looper is {:?}", command, phase)
+            }
+            LooperError::InvalidTempo(bpm) => write!(f, "tempo {} BPM is out of range", bpm),
+        }
+    }
+}
+
+impl std::error::Error for LooperError {}
+
+/// How long `beats` beats last at `bpm`.
+pub(crate) fn loop_length(beats: u32, bpm: f64) -> Result<Duration, LooperError> {
+    if !(MIN_TEMPO..=MAX_TEMPO).contains(&bpm) {
+        return Err(LooperError::InvalidTempo(bpm));
+    }
+    Ok(Duration::from_secs_f64(f64::from(beats) * 60.0 / bpm))
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Looper {
+    model: FractalModel,
+    phase: LooperPhase,
+    /// The recorded loop plus each overdub since; Undo peels them off.
+    layers: u32,
+    pub once: bool,
+    pub reverse: bool,
+    pub half_speed: bool,
+}
+
+impl Looper {
+    pub fn new(model: FractalModel) -> Self {
+        Looper {
+            model,
+            phase: LooperPhase::Empty,
+            layers: 0,
+            once: false,
+            reverse: false,
+            half_speed: false,
+        }
+    }
+
+    pub fn phase(&self) -> LooperPhase {
+        self.phase
+    }
+
+    /// Recorded layers: the loop and its overdubs.
+    pub fn layers(&self) -> u32 {
+        self.layers
+    }
+
+    fn enter(&mut self, next: LooperPhase) {
+        use LooperPhase::*;
+        match (self.phase, next) {
+            (Recording, Playing) | (Recording, Stopped) => self.layers = 1,
+            (Recording, Overdubbing) => self.layers = 2,
+            (Playing, Overdubbing) | (Stopped, Overdubbing) => self.layers += 1,
+            (_, Empty) => self.layers = 0,
+            _ => {}
+        }
+        self.phase = next;
+    }
+
+    /// Follows a `LooperState` message from the device, returning the new
+    /// phase when it changed.
+    pub fn handle(&mut self, message: &FractalMessage) -> Option<LooperPhase> {
+        let (record, play, overdub) = match *message {
+            FractalMessage::LooperState {
+                record,
+                play,
+                overdub,
+                once,
+                reverse,
+                half_speed,
+            } => {
+                self.once = once;
+                self.reverse = reverse;
+                self.half_speed = half_speed;
+                (record, play, overdub)
+            }
+            _ => return None,
+        };
+        let next = if record {
+            LooperPhase::Recording
+        } else if overdub {
+            LooperPhase::Overdubbing
+        } else if play {
+            LooperPhase::Playing
+        } else if self.layers > 0 || self.phase == LooperPhase::Recording {
+            LooperPhase::Stopped
+        } else {
+            LooperPhase::Empty
+        };
+        if next == self.phase {
+            return None;
+        }
+        self.enter(next);
+        Some(next)
+    }
+
+    /// The codes that carry out `command` from the current phase, and the
+    /// phase they lead to.
+    fn plan(&self, command: LooperCommand) -> Option<(Vec<LooperState>, LooperPhase)> {
+        use LooperCommand::*;
+        use LooperPhase::*;
+        let phase = self.phase;
+        Some(match (command, phase) {
+            (Record, Empty) => (vec![LooperState::Record], Recording),
+            (Play, Recording) | (Play, Overdubbing) | (Play, Stopped) => {
+                (vec![LooperState::Play], Playing)
+            }
+            (Stop, Playing) => (vec![LooperState::Play], Stopped),
+            // Play first closes the recording or drops out of the overdub,
+            // the second press stops.
+            (Stop, Recording) | (Stop, Overdubbing) => {
+                (vec![LooperState::Play, LooperState::Play], Stopped)
+            }
+            (Overdub, Playing) => (vec![LooperState::Record], Overdubbing),
+            // Record while recording closes the loop and starts playing it.
+            (Overdub, Recording) => (vec![LooperState::Record, LooperState::Record], Overdubbing),
+            (Undo, Playing) | (Undo, Overdubbing) | (Undo, Stopped) => {
+                let next = match phase {
+                    _ if self.layers <= 1 => Empty,
+                    Overdubbing => Playing,
+                    _ => phase,
+                };
+                (vec![LooperState::Undo], next)
+            }
+            (Clear, Stopped) => ((0..self.layers).map(|_| LooperState::Undo).collect(), Empty),
+            (Once, _) | (Reverse, _) | (HalfSpeed, _) if phase != Empty => {
+                let code = match command {
+                    Once => LooperState::Once,
+                    Reverse => LooperState::Reverse,
+                    _ => LooperState::HalfSpeed,
+                };
+                (vec![code], phase)
+            }
+            _ => return None,
+        })
+    }
+
+    /// Checks `command` against the current phase and returns the messages
+    /// that carry it out. The looper assumes they succeed; the device's next
+    /// `LooperState` corrects it if not.
+    pub fn command(&mut self, command: LooperCommand) -> Result<Vec<MidiMessage>, LooperError> {
+        if !self.model.uses_iii_protocol() {
+            return Err(LooperError::Unsupported(self.model));
+        }
+        let (codes, next) = self.plan(command).ok_or(LooperError::InvalidCommand {
+            command,
+            phase: self.phase,
+        })?;
+        match command {
+            LooperCommand::Once => self.once = !self.once,
+            LooperCommand::Reverse => self.reverse = !self.reverse,
+            LooperCommand::HalfSpeed => self.half_speed = !self.half_speed,
+            LooperCommand::Undo => self.layers = self.layers.saturating_sub(1),
+            _ => {}
+        }
+        self.enter(next);
+        Ok(codes
+            .into_iter()
+            .map(|code| set_looper_state(self.model, code))
+            .collect())
+    }
+
+    /// Records exactly `beats` beats at `bpm` and then plays the loop: the
+    /// messages to send, each with its delay from the start.
+    ///
+    /// The looper is left Recording, since that is what the device is doing
+    /// until the delayed Play goes out; `handle` picks up the change from the
+    /// device's next `LooperState`.
+    pub fn record_beats(
+        &mut self,
+        beats: u32,
+        bpm: f64,
+    ) -> Result<Vec<(Duration, MidiMessage)>, LooperError> {
+        let length = loop_length(beats, bpm)?;
+        let record = self.command(LooperCommand::Record)?;
+        // Play closes the recording; the phase moves on when it is sent.
+        let play = set_looper_state(self.model, LooperState::Play);
+        Ok(record
+            .into_iter()
+            .map(|msg| (Duration::from_secs(0), msg))
+            .chain(std::iter::once((length, play)))
+            .collect())
+    }
+}
@@ -0,0 +1,595 @@
+//! Catalog of block parameters, keyed by the type of effect block.
+//!
+//! Parameter IDs follow the Axe-Fx II third-party SysEx table. Raw values in
+//! `FractalMessage::BlockParameters` are 16-bit; knobs scale that range
+//! linearly onto `min..=max`, while switches and lists use the raw value as
+//! the index of the selected choice.
+
+use crate::{Effect, Parameter};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BlockType {
+    Amp,
+    Cab,
+    Chorus,
+    Compressor,
+    Crossover,
+    Delay,
+    Drive,
+    Enhancer,
+    FeedbackReturn,
+    FeedbackSend,
+    Filter,
+    Flanger,
+    Formant,
+    FXLoop,
+    Gate,
+    GraphicEQ,
+    Input,
+    IRPlayer,
+    Looper,
+    MegatapDelay,
+    Mixer,
+    MultibandCompressor,
+    MultiDelay,
+    Multiplexer,
+    Output,
+    ParametricEQ,
+    Phaser,
+    Pitch,
+    PlexDelay,
+    QuadChorus,
+    RealtimeAnalyzer,
+    Resonator,
+    Reverb,
+    RingModulator,
+    Rotary,
+    Synth,
+    TenTapDelay,
+    ToneMatch,
+    Tremolo,
+    Vocoder,
+    VolumePan,
+    Wah,
+}
+
+impl Effect {
+    /// The kind of block this effect instance is, or `None` for blocks that
+    /// don't process audio (controllers, tuner, MIDI block...).
+    pub fn block_type(&self) -> Option<BlockType> {
+        use Effect::*;
+        Some(match self {
+            Amp1 | Amp2 | Amp3 | Amp4 => BlockType::Amp,
+            Cab1 | Cab2 | Cab3 | Cab4 => BlockType::Cab,
+            Chorus1 | Chorus2 | Chorus3 | Chorus4 => BlockType::Chorus,
+            Compressor1 | Compressor2 | Compressor3 | Compressor4 => BlockType::Compressor,
+            Crossover1 | Crossover2 | Crossover3 | Crossover4 => BlockType::Crossover,
+            Delay1 | Delay2 | Delay3 | Delay4 => BlockType::Delay,
+            Drive1 | Drive2 | Drive3 | Drive4 => BlockType::Drive,
+            Enhancer1 | Enhancer2 | Enhancer3 | Enhancer4 => BlockType::Enhancer,
+            FeedbackReturn1 | FeedbackReturn2 | FeedbackReturn3 | FeedbackReturn4 => {
+                BlockType::FeedbackReturn
+            }
+            FeedbackSend1 | FeedbackSend2 | FeedbackSend3 | FeedbackSend4 => {
+                BlockType::FeedbackSend
+            }
+            Filter1 | Filter2 | Filter3 | Filter4 => BlockType::Filter,
+            Flanger1 | Flanger2 | Flanger3 | Flanger4 => BlockType::Flanger,
+            Formant | Formant2 | Formant3 | Formant4 => BlockType::Formant,
+            FXLoop => BlockType::FXLoop,
+            GateExpander1 | GateExpander2 | GateExpander3 | GateExpander4 | InputNoiseGate => {
+                BlockType::Gate
+            }
+            GraphicEQ1 | GraphicEQ2 | GraphicEQ3 | GraphicEQ4 => BlockType::GraphicEQ,
+            Input1 | Input2 | Input3 | Input4 | Input5 => BlockType::Input,
+            IRPlayer1 | IRPlayer2 | IRPlayer3 | IRPlayer4 => BlockType::IRPlayer,
+            Looper1 | Looper2 | Looper3 | Looper4 => BlockType::Looper,
+            MegatapDelay1 | MegatapDelay2 | MegatapDelay3 | MegatapDelay4 => {
+                BlockType::MegatapDelay
+            }
+            Mixer1 | Mixer2 | Mixer3 | Mixer4 => BlockType::Mixer,
+            MultibandCompressor1 | MultibandCompressor2 | MultibandCompressor3
+            | MultibandCompressor4 => BlockType::MultibandCompressor,
+            MultiDelay1 | MultiDelay2 | MultiDelay3 | MultiDelay4 => BlockType::MultiDelay,
+            Multiplexer1 | Multiplexer2 | Multiplexer3 | Multiplexer4 => BlockType::Multiplexer,
+            Output | Output1 | Output2 | Output3 | Output4 => BlockType::Output,
+            ParametricEQ1 | ParametricEQ2 | ParametricEQ3 | ParametricEQ4 => {
+                BlockType::ParametricEQ
+            }
+            Phaser1 | Phaser2 | Phaser3 | Phaser4 => BlockType::Phaser,
+            Pitch1 | Pitch2 | Pitch3 | Pitch4 => BlockType::Pitch,
+            PlexDelay1 | PlexDelay2 | PlexDelay3 | PlexDelay4 => BlockType::PlexDelay,
+            QuadChorus1 | QuadChorus2 => BlockType::QuadChorus,
+            RealtimeAnalyzer1 | RealtimeAnalyzer2 | RealtimeAnalyzer3 | RealtimeAnalyzer4 => {
+                BlockType::RealtimeAnalyzer
+            }
+            Resonator1 | Resonator2 | Resonator3 | Resonator4 => BlockType::Resonator,
+            Reverb1 | Reverb2 | Reverb3 | Reverb4 => BlockType::Reverb,
+            RingModulator1 | RingModulator2 | RingModulator3 | RingModulator4 => {
+                BlockType::RingModulator
+            }
+            RotarySpeaker1 | RotarySpeaker2 | RotarySpeaker3 | RotarySpeaker4 => BlockType::Rotary,
+            Synth1 | Synth2 | Synth3 | Synth4 => BlockType::Synth,
+            TenTapDelay1 | TenTapDelay2 | TenTapDelay3 | TenTapDelay4 => BlockType::TenTapDelay,
+            ToneMatch1 | ToneMatch2 | ToneMatch3 | ToneMatch4 => BlockType::ToneMatch,
+            TremoloPanner1 | TremoloPanner2 | TremoloPanner3 | TremoloPanner4 => BlockType::Tremolo,
+            Vocoder1 | Vocoder2 | Vocoder3 | Vocoder4 => BlockType::Vocoder,
+            VolumePan1 | VolumePan2 | VolumePan3 | VolumePan4 => BlockType::VolumePan,
+            Wah1 | Wah2 | Wah3 | Wah4 => BlockType::Wah,
+            Control | Controllers | FootController | MIDI | PresetFC | Shunt | Tuner
+            | IRCapture | Unknown(_) => return None,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParameterKind {
+    Knob,
+    Switch,
+    /// A list of named choices. An empty list means the choices aren't
+    /// catalogued, but the raw value is still the selected index.
+    List(&'static [&'static str]),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ParameterInfo {
+    pub id: u32,
+    pub parameter: Parameter,
+    pub name: &'static str,
+    pub min: f32,
+    pub max: f32,
+    pub unit: &'static str,
+    pub kind: ParameterKind,
+}
+
+/// Largest raw value a knob reports, at the top of its range.
+pub const MAX_RAW_VALUE: u32 = 65534;
+
+impl ParameterInfo {
+    /// Converts a raw value into the units shown on the device.
+    pub fn display_value(&self, raw: u32) -> f32 {
+        match self.kind {
+            ParameterKind::Knob => {
+                self.min
+                    + (self.max - self.min) * raw.min(MAX_RAW_VALUE) as f32 / MAX_RAW_VALUE as f32
+            }
+            _ => raw as f32,
+        }
+    }
+
+    /// The name of the selected choice, for list parameters with catalogued
+    /// choices.
+    pub fn choice(&self, raw: u32) -> Option<&'static str> {
+        match self.kind {
+            ParameterKind::List(choices) => choices.get(raw as usize).copied(),
+            ParameterKind::Switch => Some(if raw == 0 { "Off" } else { "On" }),
+            ParameterKind::Knob => None,
+        }
+    }
+}
+
+const fn knob(
+    id: u32,
+    parameter: Parameter,
+    name: &'static str,
+    min: f32,
+    max: f32,
+    unit: &'static str,
+) -> ParameterInfo {
+    ParameterInfo {
+        id,
+        parameter,
+        name,
+        min,
+        max,
+        unit,
+        kind: ParameterKind::Knob,
+    }
+}
+
+const fn switch(id: u32, parameter: Parameter, name: &'static str) -> ParameterInfo {
+    ParameterInfo {
+        id,
+        parameter,
+        name,
+        min: 0.0,
+        max: 1.0,
+        unit: "",
+        kind: ParameterKind::Switch,
+    }
+}
+
+const fn list(
+    id: u32,
+    parameter: Parameter,
+    name: &'static str,
+    choices: &'static [&'static str],
+) -> ParameterInfo {
+    ParameterInfo {
+        id,
+        parameter,
+        name,
+        min: 0.0,
+        max: if choices.is_empty() {
+            0.0
+        } else {
+            (choices.len() - 1) as f32
+        },
+        unit: "",
+        kind: ParameterKind::List(choices),
+    }
+}
+
+const BYPASS_MODES: &[&str] = &["Thru", "Mute FX Out", "Mute Out", "Mute FX In", "Mute In"];
+const INPUT_SELECTS: &[&str] = &["Left", "Right", "Sum L+R"];
+const KNEES: &[&str] = &["Hard", "Soft", "Softer", "Softest"];
+const KEYS: &[&str] = &[
+    "A", "A#", "B", "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#",
+];
+const TONE_LOCATIONS: &[&str] = &["Pre", "Post", "Mid", "End"];
+const TREMOLO_TYPES: &[&str] = &["Tremolo", "Panner"];
+
+const AMP: &[ParameterInfo] = &[
+    list(0, Parameter::EffectType, "Type", &[]),
+    knob(1, Parameter::InputDrive, "Input Drive", 0.0, 10.0, ""),
+    knob(2, Parameter::Bass, "Bass", 0.0, 10.0, ""),
+    knob(3, Parameter::Middle, "Middle", 0.0, 10.0, ""),
+    knob(4, Parameter::Treble, "Treble", 0.0, 10.0, ""),
+    knob(5, Parameter::MasterVolume, "Master Volume", 0.0, 10.0, ""),
+    knob(
+        6,
+        Parameter::PreampLowCut,
+        "Preamp Low Cut",
+        5.0,
+        2000.0,
+        "Hz",
+    ),
+    knob(
+        7,
+        Parameter::HighCutFrequency,
+        "High Cut Freq",
+        200.0,
+        20000.0,
+        "Hz",
+    ),
+    knob(
+        8,
+        Parameter::ToneFrequency,
+        "Tone Freq",
+        200.0,
+        2000.0,
+        "Hz",
+    ),
+    knob(
+        9,
+        Parameter::TransformerGrind,
+        "XFormer Grind",
+        0.0,
+        10.0,
+        "",
+    ),
+    switch(10, Parameter::BrightCap, "Bright Cap"),
+    knob(
+        12,
+        Parameter::TransformerLowFrequency,
+        "XFormer Low Freq",
+        10.0,
+        1000.0,
+        "Hz",
+    ),
+    knob(
+        13,
+        Parameter::TransformerHighFrequency,
+        "XFormer Hi Freq",
+        1000.0,
+        20000.0,
+        "Hz",
+    ),
+    list(14, Parameter::ToneLocation, "Tone Location", TONE_LOCATIONS),
+    list(15, Parameter::InputSelect, "Input Select", INPUT_SELECTS),
+    knob(16, Parameter::Depth, "Depth", 0.0, 10.0, ""),
+    knob(17, Parameter::SupplySag, "Supply Sag", 0.0, 10.0, ""),
+    knob(18, Parameter::Presence, "Presence", 0.0, 10.0, ""),
+    knob(19, Parameter::Level, "Level", -80.0, 20.0, "dB"),
+    knob(20, Parameter::Balance, "Balance", -100.0, 100.0, ""),
+    list(21, Parameter::BypassMode, "Bypass Mode", BYPASS_MODES),
+];
+
+const CAB: &[ParameterInfo] = &[
+    list(0, Parameter::CabinetLeft, "Cab L", &[]),
+    list(1, Parameter::MicLeft, "Mic L", &[]),
+    list(2, Parameter::CabinetRight, "Cab R", &[]),
+    list(3, Parameter::MicRight, "Mic R", &[]),
+    switch(4, Parameter::Link, "Link"),
+    knob(5, Parameter::LowCut, "Low Cut", 20.0, 2000.0, "Hz"),
+    knob(6, Parameter::HighCut, "High Cut", 200.0, 20000.0, "Hz"),
+    knob(7, Parameter::Level, "Level", -80.0, 20.0, "dB"),
+    knob(8, Parameter::Balance, "Balance", -100.0, 100.0, ""),
+    list(9, Parameter::BypassMode, "Bypass Mode", BYPASS_MODES),
+    knob(10, Parameter::RoomLevel, "Room Level", 0.0, 100.0, "%"),
+    knob(11, Parameter::RoomSize, "Room Size", 0.0, 100.0, "%"),
+    knob(12, Parameter::MicSpacing, "Mic Spacing", 0.0, 100.0, "%"),
+];
+
+const COMPRESSOR: &[ParameterInfo] = &[
+    knob(0, Parameter::Threshold, "Threshold", -60.0, 0.0, "dB"),
+    knob(1, Parameter::Ratio, "Ratio", 1.0, 20.0, ""),
+    knob(2, Parameter::Attack, "Attack", 0.1, 100.0, "ms"),
+    knob(3, Parameter::Release, "Release", 2.0, 1000.0, "ms"),
+    knob(4, Parameter::Level, "Level", -20.0, 20.0, "dB"),
+    list(5, Parameter::EffectType, "Type", &[]),
+    list(6, Parameter::Knee, "Knee", KNEES),
+    switch(7, Parameter::Auto, "Auto"),
+    knob(8, Parameter::Mix, "Mix", 0.0, 100.0, "%"),
+    list(9, Parameter::BypassMode, "Bypass Mode", BYPASS_MODES),
+];
+
+const GRAPHIC_EQ: &[ParameterInfo] = &[
+    knob(0, Parameter::BandGain(1), "31 Hz", -12.0, 12.0, "dB"),
+    knob(1, Parameter::BandGain(2), "63 Hz", -12.0, 12.0, "dB"),
+    knob(2, Parameter::BandGain(3), "125 Hz", -12.0, 12.0, "dB"),
+    knob(3, Parameter::BandGain(4), "250 Hz", -12.0, 12.0, "dB"),
+    knob(4, Parameter::BandGain(5), "500 Hz", -12.0, 12.0, "dB"),
+    knob(5, Parameter::BandGain(6), "1 kHz", -12.0, 12.0, "dB"),
+    knob(6, Parameter::BandGain(7), "2 kHz", -12.0, 12.0, "dB"),
+    knob(7, Parameter::BandGain(8), "4 kHz", -12.0, 12.0, "dB"),
+    knob(8, Parameter::BandGain(9), "8 kHz", -12.0, 12.0, "dB"),
+    knob(9, Parameter::BandGain(10), "16 kHz", -12.0, 12.0, "dB"),
+    knob(10, Parameter::Level, "Level", -20.0, 20.0, "dB"),
+    knob(11, Parameter::Balance, "Balance", -100.0, 100.0, ""),
+    list(12, Parameter::BypassMode, "Bypass Mode", BYPASS_MODES),
+    list(13, Parameter::EffectType, "Type", &[]),
+];
+
+const PARAMETRIC_EQ: &[ParameterInfo] = &[
+    knob(0, Parameter::BandFrequency(1), "Freq 1", 20.0, 2000.0, "Hz"),
+    knob(1, Parameter::BandFrequency(2), "Freq 2", 20.0, 5000.0, "Hz"),
+    knob(
+        2,
+        Parameter::BandFrequency(3),
+        "Freq 3",
+        100.0,
+        10000.0,
+        "Hz",
+    ),
+    knob(
+        3,
+        Parameter::BandFrequency(4),
+        "Freq 4",
+        200.0,
+        20000.0,
+        "Hz",
+    ),
+    knob(
+        4,
+        Parameter::BandFrequency(5),
+        "Freq 5",
+        500.0,
+        20000.0,
+        "Hz",
+    ),
+    knob(5, Parameter::BandQ(1), "Q 1", 0.1, 10.0, ""),
+    knob(6, Parameter::BandQ(2), "Q 2", 0.1, 10.0, ""),
+    knob(7, Parameter::BandQ(3), "Q 3", 0.1, 10.0, ""),
+    knob(8, Parameter::BandQ(4), "Q 4", 0.1, 10.0, ""),
+    knob(9, Parameter::BandQ(5), "Q 5", 0.1, 10.0, ""),
+    knob(10, Parameter::BandGain(1), "Gain 1", -12.0, 12.0, "dB"),
+    knob(11, Parameter::BandGain(2), "Gain 2", -12.0, 12.0, "dB"),
+    knob(12, Parameter::BandGain(3), "Gain 3", -12.0, 12.0, "dB"),
+    knob(13, Parameter::BandGain(4), "Gain 4", -12.0, 12.0, "dB"),
+    knob(14, Parameter::BandGain(5), "Gain 5", -12.0, 12.0, "dB"),
+    knob(15, Parameter::Level, "Level", -20.0, 20.0, "dB"),
+    knob(16, Parameter::Balance, "Balance", -100.0, 100.0, ""),
+    list(17, Parameter::BypassMode, "Bypass Mode", BYPASS_MODES),
+];
+
+const REVERB: &[ParameterInfo] = &[
+    list(0, Parameter::EffectType, "Type", &[]),
+    knob(1, Parameter::Time, "Time", 0.1, 100.0, "s"),
+    knob(2, Parameter::HighCut, "High Cut", 200.0, 20000.0, "Hz"),
+    knob(3, Parameter::LowCut, "Low Cut", 20.0, 2000.0, "Hz"),
+    knob(
+        4,
+        Parameter::InputDiffusion,
+        "Input Diffusion",
+        0.0,
+        100.0,
+        "%",
+    ),
+    knob(5, Parameter::Predelay, "Predelay", 0.0, 250.0, "ms"),
+    knob(6, Parameter::Mix, "Mix", 0.0, 100.0, "%"),
+    knob(7, Parameter::Level, "Level", -80.0, 20.0, "dB"),
+    knob(8, Parameter::Balance, "Balance", -100.0, 100.0, ""),
+    list(9, Parameter::BypassMode, "Bypass Mode", BYPASS_MODES),
+    knob(10, Parameter::Size, "Size", 0.0, 100.0, "%"),
+];
+
+const DELAY: &[ParameterInfo] = &[
+    list(0, Parameter::EffectType, "Type", &[]),
+    knob(1, Parameter::Time, "Time", 1.0, 8000.0, "ms"),
+    list(2, Parameter::Tempo, "Tempo", &[]),
+    knob(3, Parameter::Feedback, "Feedback", -100.0, 100.0, "%"),
+    knob(4, Parameter::Mix, "Mix", 0.0, 100.0, "%"),
+    knob(5, Parameter::Level, "Level", -80.0, 20.0, "dB"),
+    knob(6, Parameter::Balance, "Balance", -100.0, 100.0, ""),
+    list(7, Parameter::BypassMode, "Bypass Mode", BYPASS_MODES),
+    knob(8, Parameter::LowCut, "Low Cut", 20.0, 2000.0, "Hz"),
+    knob(9, Parameter::HighCut, "High Cut", 200.0, 20000.0, "Hz"),
+    knob(10, Parameter::Depth, "Depth", 0.0, 100.0, "%"),
+    knob(11, Parameter::Rate, "Rate", 0.1, 10.0, "Hz"),
+];
+
+const CHORUS: &[ParameterInfo] = &[
+    list(0, Parameter::EffectType, "Type", &[]),
+    knob(1, Parameter::Voices, "Voices", 2.0, 8.0, ""),
+    knob(2, Parameter::Rate, "Rate", 0.05, 10.0, "Hz"),
+    list(3, Parameter::Tempo, "Tempo", &[]),
+    knob(4, Parameter::Depth, "Depth", 0.0, 100.0, "%"),
+    knob(5, Parameter::Mix, "Mix", 0.0, 100.0, "%"),
+    knob(6, Parameter::Level, "Level", -80.0, 20.0, "dB"),
+    knob(7, Parameter::Balance, "Balance", -100.0, 100.0, ""),
+    list(8, Parameter::BypassMode, "Bypass Mode", BYPASS_MODES),
+];
+
+const FLANGER: &[ParameterInfo] = &[
+    list(0, Parameter::EffectType, "Type", &[]),
+    knob(1, Parameter::Rate, "Rate", 0.05, 10.0, "Hz"),
+    list(2, Parameter::Tempo, "Tempo", &[]),
+    knob(3, Parameter::Depth, "Depth", 0.0, 100.0, "%"),
+    knob(4, Parameter::Feedback, "Feedback", -100.0, 100.0, "%"),
+    knob(5, Parameter::Mix, "Mix", 0.0, 100.0, "%"),
+    knob(6, Parameter::Level, "Level", -80.0, 20.0, "dB"),
+    knob(7, Parameter::Balance, "Balance", -100.0, 100.0, ""),
+    list(8, Parameter::BypassMode, "Bypass Mode", BYPASS_MODES),
+];
+
+const PHASER: &[ParameterInfo] = &[
+    list(0, Parameter::EffectType, "Type", &[]),
+    knob(1, Parameter::Rate, "Rate", 0.05, 10.0, "Hz"),
+    list(2, Parameter::Tempo, "Tempo", &[]),
+    knob(3, Parameter::Depth, "Depth", 0.0, 100.0, "%"),
+    knob(4, Parameter::Feedback, "Feedback", -100.0, 100.0, "%"),
+    knob(5, Parameter::Mix, "Mix", 0.0, 100.0, "%"),
+    knob(6, Parameter::Level, "Level", -80.0, 20.0, "dB"),
+    knob(7, Parameter::Balance, "Balance", -100.0, 100.0, ""),
+    list(8, Parameter::BypassMode, "Bypass Mode", BYPASS_MODES),
+];
+
+const WAH: &[ParameterInfo] = &[
+    list(0, Parameter::EffectType, "Type", &[]),
+    knob(
+        1,
+        Parameter::MinimumFrequency,
+        "Min Freq",
+        100.0,
+        1000.0,
+        "Hz",
+    ),
+    knob(
+        2,
+        Parameter::MaximumFrequency,
+        "Max Freq",
+        500.0,
+        5000.0,
+        "Hz",
+    ),
+    knob(3, Parameter::Resonance, "Resonance", 0.0, 10.0, ""),
+    knob(4, Parameter::Control, "Control", 0.0, 100.0, "%"),
+    knob(5, Parameter::Level, "Level", -80.0, 20.0, "dB"),
+    list(6, Parameter::BypassMode, "Bypass Mode", BYPASS_MODES),
+];
+
+const DRIVE: &[ParameterInfo] = &[
+    list(0, Parameter::EffectType, "Type", &[]),
+    knob(1, Parameter::Drive, "Drive", 0.0, 10.0, ""),
+    knob(2, Parameter::Tone, "Tone", 0.0, 10.0, ""),
+    knob(3, Parameter::Level, "Level", 0.0, 10.0, ""),
+    knob(4, Parameter::Mix, "Mix", 0.0, 100.0, "%"),
+    knob(5, Parameter::LowCut, "Low Cut", 20.0, 2000.0, "Hz"),
+    knob(6, Parameter::HighCut, "High Cut", 200.0, 20000.0, "Hz"),
+    knob(7, Parameter::Bass, "Bass", -10.0, 10.0, ""),
+    knob(8, Parameter::Middle, "Mid", -10.0, 10.0, ""),
+    knob(9, Parameter::Treble, "Treble", -10.0, 10.0, ""),
+    knob(10, Parameter::Balance, "Balance", -100.0, 100.0, ""),
+    list(11, Parameter::BypassMode, "Bypass Mode", BYPASS_MODES),
+];
+
+const PITCH: &[ParameterInfo] = &[
+    list(0, Parameter::EffectType, "Type", &[]),
+    knob(1, Parameter::Shift(1), "Voice 1 Shift", -24.0, 24.0, "st"),
+    knob(2, Parameter::Shift(2), "Voice 2 Shift", -24.0, 24.0, "st"),
+    knob(3, Parameter::Detune, "Detune", -50.0, 50.0, "cents"),
+    knob(4, Parameter::Mix, "Mix", 0.0, 100.0, "%"),
+    knob(5, Parameter::Level, "Level", -80.0, 20.0, "dB"),
+    knob(6, Parameter::Balance, "Balance", -100.0, 100.0, ""),
+    list(7, Parameter::BypassMode, "Bypass Mode", BYPASS_MODES),
+    list(8, Parameter::Key, "Key", KEYS),
+];
+
+const FILTER: &[ParameterInfo] = &[
+    list(0, Parameter::EffectType, "Type", &[]),
+    knob(1, Parameter::Frequency, "Freq", 20.0, 20000.0, "Hz"),
+    knob(2, Parameter::Q, "Q", 0.1, 10.0, ""),
+    knob(3, Parameter::Gain, "Gain", -12.0, 12.0, "dB"),
+    knob(4, Parameter::Level, "Level", -80.0, 20.0, "dB"),
+    knob(5, Parameter::Balance, "Balance", -100.0, 100.0, ""),
+    list(6, Parameter::BypassMode, "Bypass Mode", BYPASS_MODES),
+];
+
+const GATE: &[ParameterInfo] = &[
+    knob(0, Parameter::Threshold, "Threshold", -80.0, 0.0, "dB"),
+    knob(1, Parameter::Attack, "Attack", 0.1, 100.0, "ms"),
+    knob(2, Parameter::Hold, "Hold", 0.0, 250.0, "ms"),
+    knob(3, Parameter::Release, "Release", 2.0, 1000.0, "ms"),
+    knob(4, Parameter::Ratio, "Ratio", 1.0, 10.0, ""),
+    knob(5, Parameter::Level, "Level", -80.0, 20.0, "dB"),
+    knob(6, Parameter::Balance, "Balance", -100.0, 100.0, ""),
+    list(7, Parameter::BypassMode, "Bypass Mode", BYPASS_MODES),
+];
+
+const VOLUME_PAN: &[ParameterInfo] = &[
+    knob(0, Parameter::Volume, "Volume", 0.0, 100.0, "%"),
+    knob(1, Parameter::Balance, "Balance", -100.0, 100.0, ""),
+    knob(2, Parameter::PanLeft, "Pan L", -100.0, 100.0, ""),
+    knob(3, Parameter::PanRight, "Pan R", -100.0, 100.0, ""),
+    knob(4, Parameter::Level, "Level", -80.0, 20.0, "dB"),
+    list(5, Parameter::BypassMode, "Bypass Mode", BYPASS_MODES),
+    list(6, Parameter::Taper, "Taper", &[]),
+];
+
+const TREMOLO: &[ParameterInfo] = &[
+    list(0, Parameter::EffectType, "Type", TREMOLO_TYPES),
+    knob(1, Parameter::Rate, "Rate", 0.05, 20.0, "Hz"),
+    list(2, Parameter::Tempo, "Tempo", &[]),
+    knob(3, Parameter::Depth, "Depth", 0.0, 100.0, "%"),
+    knob(4, Parameter::Duty, "Duty", 0.0, 100.0, "%"),
+    knob(5, Parameter::Level, "Level", -80.0, 20.0, "dB"),
+    list(6, Parameter::BypassMode, "Bypass Mode", BYPASS_MODES),
+];
+
+const ROTARY: &[ParameterInfo] = &[
+    knob(0, Parameter::Rate, "Rate", 0.0, 10.0, "Hz"),
+    knob(1, Parameter::LowDepth, "Low Depth", 0.0, 100.0, "%"),
+    knob(2, Parameter::HighDepth, "High Depth", 0.0, 100.0, "%"),
+    knob(3, Parameter::Mix, "Mix", 0.0, 100.0, "%"),
+    knob(4, Parameter::Level, "Level", -80.0, 20.0, "dB"),
+    list(5, Parameter::BypassMode, "Bypass Mode", BYPASS_MODES),
+];
+
+/// All catalogued parameters for a block type, in ID order. Blocks that
+/// aren't catalogued yet return an empty slice.
+pub fn parameters_for_block(block: BlockType) -> &'static [ParameterInfo] {
+    match block {
+        BlockType::Amp => AMP,
+        BlockType::Cab => CAB,
+        BlockType::Chorus => CHORUS,
+        BlockType::Compressor => COMPRESSOR,
+        BlockType::Delay => DELAY,
+        BlockType::Drive => DRIVE,
+        BlockType::Filter => FILTER,
+        BlockType::Flanger => FLANGER,
+        BlockType::Gate => GATE,
+        BlockType::GraphicEQ => GRAPHIC_EQ,
+        BlockType::ParametricEQ => PARAMETRIC_EQ,
+        BlockType::Phaser => PHASER,
+        BlockType::Pitch => PITCH,
+        BlockType::Reverb => REVERB,
+        BlockType::Rotary => ROTARY,
+        BlockType::Tremolo => TREMOLO,
+        BlockType::VolumePan => VOLUME_PAN,
+        BlockType::Wah => WAH,
+        _ => &[],
+    }
+}
+
+pub fn parameter_info(effect: Effect, parameter_id: u32) -> Option<&'static ParameterInfo> {
+    parameters_for_block(effect.block_type()?)
+        .iter()
+        .find(|info| info.id == parameter_id)
+}
+
+pub(crate) fn parameter_for(effect: Effect, parameter_id: u32) -> Parameter {
+    parameter_info(effect, parameter_id)
+        .map(|info| info.parameter)
+        .unwrap_or(Parameter::Unknown)
+}
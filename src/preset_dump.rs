@@ -0,0 +1,198 @@
+//! Moving whole presets between a computer and the device.
+//!
+//! A dump requested with `request_preset_dump` arrives as the same header,
+//! data chunk and footer messages that make up a preset `.syx` file, so it is
+//! collected into the same type.
+
+use crate::preset_file::{
+    PresetFile, PresetFileError, PRESET_DUMP_CHUNK, PRESET_DUMP_FOOTER, PRESET_DUMP_HEADER,
+};
+use crate::{FractalModel, MidiMessage, SysexFrame};
+use std::time::Duration;
+
+/// A preset received from the device or read from a file.
+pub type PresetDump = PresetFile;
+
+/// Pause between upload messages that keeps the device's input buffer from
+/// overflowing.
+pub const UPLOAD_MESSAGE_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PresetTarget {
+    EditBuffer,
+    Preset(u32),
+}
+
+/// The two preset number bytes of a dump request or dump header. The III
+/// family sends the number least significant byte first and marks the edit
+/// buffer `7F 7F`; older models send it most significant byte first and mark
+/// the edit buffer `7F 00`.
+pub(crate) fn encode_dump_target(model: FractalModel, target: PresetTarget) -> [u8; 2] {
+    let iii = model.uses_iii_protocol();
+    match target {
+        PresetTarget::EditBuffer if iii => [0x7F, 0x7F],
+        PresetTarget::EditBuffer => [0x7F, 0x00],
+        PresetTarget::Preset(n) => {
+            let (msb, lsb) = ((n >> 7) as u8 & 0x7F, n as u8 & 0x7F);
+            if iii {
+                [lsb, msb]
+            } else {
+                [msb, lsb]
+            }
+        }
+    }
+}
+
+/// The inverse of `encode_dump_target`, or `None` if there are fewer than
+/// two bytes.
+pub(crate) fn decode_dump_target(model: FractalModel, bytes: &[u8]) -> Option<PresetTarget> {
+    let iii = model.uses_iii_protocol();
+    Some(match *bytes.get(..2)? {
+        [0x7F, 0x7F] if iii => PresetTarget::EditBuffer,
+        [0x7F, 0x00] if !iii => PresetTarget::EditBuffer,
+        [lsb, msb] if iii => PresetTarget::Preset(u32::from(msb) << 7 | u32::from(lsb)),
+        [msb, lsb] => PresetTarget::Preset(u32::from(msb) << 7 | u32::from(lsb)),
+        _ => return None,
+    })
+}
+
+/// A header message, a run of data messages and a footer: the shape shared
+/// by preset dumps and cab IRs.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Sequence {
+    pub header: SysexFrame,
+    pub data: Vec<SysexFrame>,
+    pub footer: SysexFrame,
+}
+
+/// Collects a `Sequence` whose messages carry the function IDs `header`,
+/// `data` and `footer`.
+///
+/// Messages must be pushed in order, header first. `index` and `offset` in
+/// errors count the messages and bytes pushed so far.
+#[derive(Debug)]
+pub(crate) struct SequenceAssembler {
+    functions: [u8; 3],
+    header: Option<SysexFrame>,
+    data: Vec<SysexFrame>,
+    index: usize,
+    offset: usize,
+}
+
+impl SequenceAssembler {
+    pub(crate) fn new(header: u8, data: u8, footer: u8) -> Self {
+        SequenceAssembler {
+            functions: [header, data, footer],
+            header: None,
+            data: vec![],
+            index: 0,
+            offset: 0,
+        }
+    }
+
+    /// Adds one message, returning the whole sequence once the footer
+    /// arrives.
+    pub(crate) fn push(&mut self, frame: SysexFrame) -> Result<Option<Sequence>, PresetFileError> {
+        let [header_id, data_id, footer_id] = self.functions;
+        let (index, offset) = (self.index, self.offset);
+        self.index += 1;
+        self.offset += frame.to_message().len();
+        let unexpected = PresetFileError::UnexpectedMessage {
+            index,
+            offset,
+            function_id: frame.function_id,
+        };
+        let model = match &self.header {
+            Some(header) => header.model,
+            None if frame.function_id == header_id => {
+                self.header = Some(frame);
+                return Ok(None);
+            }
+            None => return Err(unexpected),
+        };
+        if frame.model != model {
+            return Err(PresetFileError::ModelMismatch {
+                index,
+                offset,
+                model: frame.model,
+            });
+        }
+        match frame.function_id {
+            id if id == data_id => {
+                self.data.push(frame);
+                Ok(None)
+            }
+            id if id == footer_id && !self.data.is_empty() => {
+                let header = self.header.take().ok_or(PresetFileError::Incomplete)?;
+                Ok(Some(Sequence {
+                    header,
+                    data: std::mem::take(&mut self.data),
+                    footer: frame,
+                }))
+            }
+            _ => Err(unexpected),
+        }
+    }
+}
+
+/// Collects the messages of a preset dump as they arrive.
+///
+/// Messages must be pushed in order, header first. `index` and `offset` in
+/// errors count the messages and bytes pushed so far.
+#[derive(Debug)]
+pub struct PresetDumpAssembler {
+    frames: SequenceAssembler,
+}
+
+impl Default for PresetDumpAssembler {
+    fn default() -> Self {
+        PresetDumpAssembler {
+            frames: SequenceAssembler::new(
+                PRESET_DUMP_HEADER,
+                PRESET_DUMP_CHUNK,
+                PRESET_DUMP_FOOTER,
+            ),
+        }
+    }
+}
+
+impl PresetDumpAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds one message of the dump, returning the whole dump once the
+    /// footer arrives.
+    pub fn push(&mut self, frame: SysexFrame) -> Result<Option<PresetDump>, PresetFileError> {
+        Ok(self.frames.push(frame)?.map(|sequence| {
+            PresetFile::from_frames(sequence.header, sequence.data, sequence.footer)
+        }))
+    }
+}
+
+impl PresetFile {
+    /// The messages that load this preset into the edit buffer: the dump with
+    /// its header pointed at the edit buffer. Send them
+    /// `UPLOAD_MESSAGE_INTERVAL` apart.
+    ///
+    /// There is no upload straight to a preset slot: the header's preset
+    /// number encoding hasn't been checked against a capture, and a mistake
+    /// would overwrite the wrong preset. Store the edit buffer afterwards
+    /// with `store_in_preset` instead.
+    pub fn upload_messages(&self) -> Vec<MidiMessage> {
+        self.messages_for(PresetTarget::EditBuffer)
+    }
+
+    /// The dump with its header pointed at `target`.
+    pub(crate) fn messages_for(&self, target: PresetTarget) -> Vec<MidiMessage> {
+        let mut header_payload = self.header.payload.clone();
+        header_payload.resize(header_payload.len().max(2), 0);
+        header_payload[..2].copy_from_slice(&encode_dump_target(self.model, target));
+        let header = SysexFrame::new(self.model, PRESET_DUMP_HEADER, header_payload);
+        std::iter::once(&header)
+            .chain(self.chunks.iter())
+            .chain(std::iter::once(&self.footer))
+            .map(SysexFrame::to_message)
+            .collect()
+    }
+}
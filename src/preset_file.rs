@@ -0,0 +1,200 @@
+//! Reading preset `.syx` files as exported by Fractal-Bot and the editors.
+//!
+//! A preset dump is a header message (function `0x77`) carrying the target
+//! preset number, a run of data chunks (`0x78`) and a footer (`0x79`). The
+//! data is a stream of 16-bit words, each sent as three 7-bit bytes least
+//! significant first; the first 32 words of the first chunk hold the name.
+//!
+//! The word packing and the name's position have not been checked against a
+//! file exported by Fractal-Bot; the tests build their dumps from the same
+//! layout, so they can't catch a mistake in it. Until a real export backs
+//! them, the decoded preset number and name stay private to the crate.
+
+use crate::parse::{decode_parameter_value, ParseError};
+use crate::preset_dump::{decode_dump_target, PresetDumpAssembler, PresetTarget};
+use crate::{FractalModel, MidiMessage, SysexFrame};
+use std::fmt;
+
+pub const PRESET_DUMP_HEADER: u8 = 0x77;
+pub const PRESET_DUMP_CHUNK: u8 = 0x78;
+pub const PRESET_DUMP_FOOTER: u8 = 0x79;
+
+const NAME_LENGTH: usize = 32;
+const WORD_SIZE: usize = 3;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PresetFileError {
+    /// The message starting at `offset` is malformed or fails its checksum.
+    /// `offset` points at the offending byte where there is one.
+    InvalidMessage {
+        index: usize,
+        offset: usize,
+        error: ParseError,
+    },
+    /// The message isn't part of the dump, or is out of order.
+    UnexpectedMessage {
+        index: usize,
+        offset: usize,
+        function_id: u8,
+    },
+    /// The message targets a different model than the dump's header.
+    ModelMismatch {
+        index: usize,
+        offset: usize,
+        model: FractalModel,
+    },
+    /// The file has no header, or ends before the footer.
+    Incomplete,
+}
+
+impl fmt::Display for PresetFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PresetFileError::InvalidMessage {
+                index,
+                offset,
+                error,
+            } => write!(f, "message {} (byte {}): {}", index, offset, error),
+            PresetFileError::UnexpectedMessage {
+                index,
+                offset,
+                function_id,
+            } => write!(
+                f,
+                "message {} (byte {}): unexpected function ID 0x{:02X}",
+                index, offset, function_id
+            ),
+            PresetFileError::ModelMismatch {
+                index,
+                offset,
+                model,
+            } => write!(
+                f,
+                "message {} (byte {}): model {:?} does not match the header",
+                index, offset, model
+            ),
+            PresetFileError::Incomplete => write!(f, "dump is incomplete"),
+        }
+    }
+}
+
+impl std::error::Error for PresetFileError {}
+
+/// Splits a `.syx` file into its SysEx messages, with the byte offset of
+/// each. Bytes between messages are an error.
+pub fn split_messages(bytes: &[u8]) -> Result<Vec<(usize, &[u8])>, PresetFileError> {
+    let mut messages = vec![];
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let index = messages.len();
+        if bytes[offset] != 0xF0 {
+            return Err(PresetFileError::InvalidMessage {
+                index,
+                offset,
+                error: ParseError::MissingHeader,
+            });
+        }
+        let end = match bytes[offset..].iter().position(|byte| *byte == 0xF7) {
+            Some(len) => offset + len + 1,
+            None => {
+                return Err(PresetFileError::InvalidMessage {
+                    index,
+                    offset,
+                    error: ParseError::Truncated,
+                })
+            }
+        };
+        messages.push((offset, &bytes[offset..end]));
+        offset = end;
+    }
+    Ok(messages)
+}
+
+/// Parses and checksums one message of a file.
+pub(crate) fn parse_frame(
+    index: usize,
+    offset: usize,
+    msg: &[u8],
+) -> Result<SysexFrame, PresetFileError> {
+    let invalid = |error, at| PresetFileError::InvalidMessage {
+        index,
+        offset: offset + at,
+        error,
+    };
+    let frame = SysexFrame::parse_request(msg).map_err(|error| match error {
+        ParseError::InvalidDataByte { index, .. } => invalid(error, index),
+        _ => invalid(error, 0),
+    })?;
+    frame
+        .verify()
+        .map_err(|error| invalid(error, msg.len() - 2))?;
+    Ok(frame)
+}
+
+/// A preset dump read from a `.syx` file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PresetFile {
+    pub model: FractalModel,
+    /// The preset slot the dump was taken from, or `None` for the edit buffer.
+    pub(crate) preset_number: Option<u32>,
+    pub(crate) name: String,
+    pub header: SysexFrame,
+    pub chunks: Vec<SysexFrame>,
+    pub footer: SysexFrame,
+}
+
+impl PresetFile {
+    pub fn parse(bytes: &[u8]) -> Result<Self, PresetFileError> {
+        let mut assembler = PresetDumpAssembler::new();
+        for (index, (offset, msg)) in split_messages(bytes)?.into_iter().enumerate() {
+            if let Some(dump) = assembler.push(parse_frame(index, offset, msg)?)? {
+                return Ok(dump);
+            }
+        }
+        Err(PresetFileError::Incomplete)
+    }
+
+    pub(crate) fn from_frames(
+        header: SysexFrame,
+        chunks: Vec<SysexFrame>,
+        footer: SysexFrame,
+    ) -> Self {
+        PresetFile {
+            model: header.model,
+            preset_number: match decode_dump_target(header.model, &header.payload) {
+                Some(PresetTarget::Preset(n)) => Some(n),
+                _ => None,
+            },
+            name: decode_name(&chunks[0].payload),
+            header,
+            chunks,
+            footer,
+        }
+    }
+
+    /// The messages of the dump, in order.
+    pub fn messages(&self) -> Vec<MidiMessage> {
+        std::iter::once(&self.header)
+            .chain(self.chunks.iter())
+            .chain(std::iter::once(&self.footer))
+            .map(SysexFrame::to_message)
+            .collect()
+    }
+
+    /// The file contents: every message of the dump back to back.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.messages().concat()
+    }
+}
+
+fn decode_name(payload: &[u8]) -> String {
+    payload
+        .chunks_exact(WORD_SIZE)
+        .take(NAME_LENGTH)
+        .map(|word| decode_parameter_value(word[0], word[1], word[2]) as u8)
+        .take_while(|c| *c > 0)
+        .map(|c| c as char)
+        .collect::<String>()
+        .trim_end()
+        .to_string()
+}
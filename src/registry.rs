@@ -0,0 +1,294 @@
+use crate::{Effect, EffectID, FractalModel};
+
+/// Maps effects to and from the 14-bit IDs a model uses on the wire.
+///
+/// The Axe-Fx II family numbers its blocks from 100, while the Axe-Fx III and
+/// FM-series use `EffectID`. IDs missing from the table round-trip through
+/// `Effect::Unknown`, so nothing the device reports is lost.
+#[derive(Clone, Copy, Debug)]
+pub struct EffectRegistry {
+    model: FractalModel,
+}
+
+impl EffectRegistry {
+    pub fn for_model(model: FractalModel) -> Self {
+        EffectRegistry { model }
+    }
+
+    pub fn model(&self) -> FractalModel {
+        self.model
+    }
+
+    pub fn effect(&self, id: u32) -> Effect {
+        self.entries()
+            .iter()
+            .find(|(entry_id, _)| *entry_id == id)
+            .map(|(_, effect)| *effect)
+            .unwrap_or(Effect::Unknown(id))
+    }
+
+    /// The ID for `effect`, or `None` if the model has no such block.
+    pub fn id(&self, effect: Effect) -> Option<u32> {
+        match effect {
+            Effect::Unknown(id) => Some(id),
+            _ => self
+                .entries()
+                .iter()
+                .find(|(_, entry_effect)| *entry_effect == effect)
+                .map(|(id, _)| *id),
+        }
+    }
+
+    /// Every known effect with its ID, in ID order.
+    pub fn entries(&self) -> &'static [(u32, Effect)] {
+        if self.model.uses_iii_protocol() {
+            III_EFFECT_IDS
+        } else {
+            II_EFFECT_IDS
+        }
+    }
+}
+
+const II_EFFECT_IDS: &[(u32, Effect)] = &[
+    (2, Effect::Control),
+    (100, Effect::Compressor1),
+    (101, Effect::Compressor2),
+    (102, Effect::GraphicEQ1),
+    (103, Effect::GraphicEQ2),
+    (104, Effect::ParametricEQ1),
+    (105, Effect::ParametricEQ2),
+    (106, Effect::Amp1),
+    (107, Effect::Amp2),
+    (108, Effect::Cab1),
+    (109, Effect::Cab2),
+    (110, Effect::Reverb1),
+    (111, Effect::Reverb2),
+    (112, Effect::Delay1),
+    (113, Effect::Delay2),
+    (114, Effect::MultiDelay1),
+    (115, Effect::MultiDelay2),
+    (116, Effect::Chorus1),
+    (117, Effect::Chorus2),
+    (118, Effect::Flanger1),
+    (119, Effect::Flanger2),
+    (120, Effect::RotarySpeaker1),
+    (121, Effect::RotarySpeaker2),
+    (122, Effect::Phaser1),
+    (123, Effect::Phaser2),
+    (124, Effect::Wah1),
+    (125, Effect::Wah2),
+    (126, Effect::Formant),
+    (127, Effect::VolumePan1),
+    (128, Effect::TremoloPanner1),
+    (129, Effect::TremoloPanner2),
+    (130, Effect::Pitch1),
+    (131, Effect::Filter1),
+    (132, Effect::Filter2),
+    (133, Effect::Drive1),
+    (134, Effect::Drive2),
+    (135, Effect::Enhancer1),
+    (136, Effect::FXLoop),
+    (137, Effect::Mixer1),
+    (138, Effect::Mixer2),
+    (139, Effect::InputNoiseGate),
+    (140, Effect::Output),
+    (141, Effect::Controllers),
+    (142, Effect::FeedbackSend1),
+    (143, Effect::FeedbackReturn1),
+    (144, Effect::Synth1),
+    (145, Effect::Synth2),
+    (146, Effect::Vocoder1),
+    (147, Effect::MegatapDelay1),
+    (148, Effect::Crossover1),
+    (149, Effect::Crossover2),
+    (150, Effect::GateExpander1),
+    (151, Effect::GateExpander2),
+    (153, Effect::Pitch2),
+    (154, Effect::MultibandCompressor1),
+    (155, Effect::MultibandCompressor2),
+    (156, Effect::QuadChorus1),
+    (157, Effect::QuadChorus2),
+    (158, Effect::Resonator1),
+    (159, Effect::Resonator2),
+    (160, Effect::GraphicEQ3),
+    (161, Effect::GraphicEQ4),
+    (162, Effect::ParametricEQ3),
+    (163, Effect::ParametricEQ4),
+    (164, Effect::Filter3),
+    (165, Effect::Filter4),
+    (166, Effect::VolumePan2),
+    (167, Effect::VolumePan3),
+    (168, Effect::VolumePan4),
+    (169, Effect::Looper1),
+    (207, Effect::Shunt),
+];
+
+const III_EFFECT_IDS: &[(u32, Effect)] = &[
+    (EffectID::ID_CONTROL as u32, Effect::Control),
+    (EffectID::ID_TUNER as u32, Effect::Tuner),
+    (EffectID::ID_IRCAPTURE as u32, Effect::IRCapture),
+    (EffectID::ID_INPUT1 as u32, Effect::Input1),
+    (EffectID::ID_INPUT2 as u32, Effect::Input2),
+    (EffectID::ID_INPUT3 as u32, Effect::Input3),
+    (EffectID::ID_INPUT4 as u32, Effect::Input4),
+    (EffectID::ID_INPUT5 as u32, Effect::Input5),
+    (EffectID::ID_OUTPUT1 as u32, Effect::Output1),
+    (EffectID::ID_OUTPUT2 as u32, Effect::Output2),
+    (EffectID::ID_OUTPUT3 as u32, Effect::Output3),
+    (EffectID::ID_OUTPUT4 as u32, Effect::Output4),
+    (EffectID::ID_COMP1 as u32, Effect::Compressor1),
+    (EffectID::ID_COMP2 as u32, Effect::Compressor2),
+    (EffectID::ID_COMP3 as u32, Effect::Compressor3),
+    (EffectID::ID_COMP4 as u32, Effect::Compressor4),
+    (EffectID::ID_GRAPHEQ1 as u32, Effect::GraphicEQ1),
+    (EffectID::ID_GRAPHEQ2 as u32, Effect::GraphicEQ2),
+    (EffectID::ID_GRAPHEQ3 as u32, Effect::GraphicEQ3),
+    (EffectID::ID_GRAPHEQ4 as u32, Effect::GraphicEQ4),
+    (EffectID::ID_PARAEQ1 as u32, Effect::ParametricEQ1),
+    (EffectID::ID_PARAEQ2 as u32, Effect::ParametricEQ2),
+    (EffectID::ID_PARAEQ3 as u32, Effect::ParametricEQ3),
+    (EffectID::ID_PARAEQ4 as u32, Effect::ParametricEQ4),
+    (EffectID::ID_DISTORT1 as u32, Effect::Amp1),
+    (EffectID::ID_DISTORT2 as u32, Effect::Amp2),
+    (EffectID::ID_DISTORT3 as u32, Effect::Amp3),
+    (EffectID::ID_DISTORT4 as u32, Effect::Amp4),
+    (EffectID::ID_CAB1 as u32, Effect::Cab1),
+    (EffectID::ID_CAB2 as u32, Effect::Cab2),
+    (EffectID::ID_CAB3 as u32, Effect::Cab3),
+    (EffectID::ID_CAB4 as u32, Effect::Cab4),
+    (EffectID::ID_REVERB1 as u32, Effect::Reverb1),
+    (EffectID::ID_REVERB2 as u32, Effect::Reverb2),
+    (EffectID::ID_REVERB3 as u32, Effect::Reverb3),
+    (EffectID::ID_REVERB4 as u32, Effect::Reverb4),
+    (EffectID::ID_DELAY1 as u32, Effect::Delay1),
+    (EffectID::ID_DELAY2 as u32, Effect::Delay2),
+    (EffectID::ID_DELAY3 as u32, Effect::Delay3),
+    (EffectID::ID_DELAY4 as u32, Effect::Delay4),
+    (EffectID::ID_MULTITAP1 as u32, Effect::MultiDelay1),
+    (EffectID::ID_MULTITAP2 as u32, Effect::MultiDelay2),
+    (EffectID::ID_MULTITAP3 as u32, Effect::MultiDelay3),
+    (EffectID::ID_MULTITAP4 as u32, Effect::MultiDelay4),
+    (EffectID::ID_CHORUS1 as u32, Effect::Chorus1),
+    (EffectID::ID_CHORUS2 as u32, Effect::Chorus2),
+    (EffectID::ID_CHORUS3 as u32, Effect::Chorus3),
+    (EffectID::ID_CHORUS4 as u32, Effect::Chorus4),
+    (EffectID::ID_FLANGER1 as u32, Effect::Flanger1),
+    (EffectID::ID_FLANGER2 as u32, Effect::Flanger2),
+    (EffectID::ID_FLANGER3 as u32, Effect::Flanger3),
+    (EffectID::ID_FLANGER4 as u32, Effect::Flanger4),
+    (EffectID::ID_ROTARY1 as u32, Effect::RotarySpeaker1),
+    (EffectID::ID_ROTARY2 as u32, Effect::RotarySpeaker2),
+    (EffectID::ID_ROTARY3 as u32, Effect::RotarySpeaker3),
+    (EffectID::ID_ROTARY4 as u32, Effect::RotarySpeaker4),
+    (EffectID::ID_PHASER1 as u32, Effect::Phaser1),
+    (EffectID::ID_PHASER2 as u32, Effect::Phaser2),
+    (EffectID::ID_PHASER3 as u32, Effect::Phaser3),
+    (EffectID::ID_PHASER4 as u32, Effect::Phaser4),
+    (EffectID::ID_WAH1 as u32, Effect::Wah1),
+    (EffectID::ID_WAH2 as u32, Effect::Wah2),
+    (EffectID::ID_WAH3 as u32, Effect::Wah3),
+    (EffectID::ID_WAH4 as u32, Effect::Wah4),
+    (EffectID::ID_FORMANT1 as u32, Effect::Formant),
+    (EffectID::ID_FORMANT2 as u32, Effect::Formant2),
+    (EffectID::ID_FORMANT3 as u32, Effect::Formant3),
+    (EffectID::ID_FORMANT4 as u32, Effect::Formant4),
+    (EffectID::ID_VOLUME1 as u32, Effect::VolumePan1),
+    (EffectID::ID_VOLUME2 as u32, Effect::VolumePan2),
+    (EffectID::ID_VOLUME3 as u32, Effect::VolumePan3),
+    (EffectID::ID_VOLUME4 as u32, Effect::VolumePan4),
+    (EffectID::ID_TREMOLO1 as u32, Effect::TremoloPanner1),
+    (EffectID::ID_TREMOLO2 as u32, Effect::TremoloPanner2),
+    (EffectID::ID_TREMOLO3 as u32, Effect::TremoloPanner3),
+    (EffectID::ID_TREMOLO4 as u32, Effect::TremoloPanner4),
+    (EffectID::ID_PITCH1 as u32, Effect::Pitch1),
+    (EffectID::ID_PITCH2 as u32, Effect::Pitch2),
+    (EffectID::ID_PITCH3 as u32, Effect::Pitch3),
+    (EffectID::ID_PITCH4 as u32, Effect::Pitch4),
+    (EffectID::ID_FILTER1 as u32, Effect::Filter1),
+    (EffectID::ID_FILTER2 as u32, Effect::Filter2),
+    (EffectID::ID_FILTER3 as u32, Effect::Filter3),
+    (EffectID::ID_FILTER4 as u32, Effect::Filter4),
+    (EffectID::ID_FUZZ1 as u32, Effect::Drive1),
+    (EffectID::ID_FUZZ2 as u32, Effect::Drive2),
+    (EffectID::ID_FUZZ3 as u32, Effect::Drive3),
+    (EffectID::ID_FUZZ4 as u32, Effect::Drive4),
+    (EffectID::ID_ENHANCER1 as u32, Effect::Enhancer1),
+    (EffectID::ID_ENHANCER2 as u32, Effect::Enhancer2),
+    (EffectID::ID_ENHANCER3 as u32, Effect::Enhancer3),
+    (EffectID::ID_ENHANCER4 as u32, Effect::Enhancer4),
+    (EffectID::ID_MIXER1 as u32, Effect::Mixer1),
+    (EffectID::ID_MIXER2 as u32, Effect::Mixer2),
+    (EffectID::ID_MIXER3 as u32, Effect::Mixer3),
+    (EffectID::ID_MIXER4 as u32, Effect::Mixer4),
+    (EffectID::ID_SYNTH1 as u32, Effect::Synth1),
+    (EffectID::ID_SYNTH2 as u32, Effect::Synth2),
+    (EffectID::ID_SYNTH3 as u32, Effect::Synth3),
+    (EffectID::ID_SYNTH4 as u32, Effect::Synth4),
+    (EffectID::ID_VOCODER1 as u32, Effect::Vocoder1),
+    (EffectID::ID_VOCODER2 as u32, Effect::Vocoder2),
+    (EffectID::ID_VOCODER3 as u32, Effect::Vocoder3),
+    (EffectID::ID_VOCODER4 as u32, Effect::Vocoder4),
+    (EffectID::ID_MEGATAP1 as u32, Effect::MegatapDelay1),
+    (EffectID::ID_MEGATAP2 as u32, Effect::MegatapDelay2),
+    (EffectID::ID_MEGATAP3 as u32, Effect::MegatapDelay3),
+    (EffectID::ID_MEGATAP4 as u32, Effect::MegatapDelay4),
+    (EffectID::ID_CROSSOVER1 as u32, Effect::Crossover1),
+    (EffectID::ID_CROSSOVER2 as u32, Effect::Crossover2),
+    (EffectID::ID_CROSSOVER3 as u32, Effect::Crossover3),
+    (EffectID::ID_CROSSOVER4 as u32, Effect::Crossover4),
+    (EffectID::ID_GATE1 as u32, Effect::GateExpander1),
+    (EffectID::ID_GATE2 as u32, Effect::GateExpander2),
+    (EffectID::ID_GATE3 as u32, Effect::GateExpander3),
+    (EffectID::ID_GATE4 as u32, Effect::GateExpander4),
+    (EffectID::ID_RINGMOD1 as u32, Effect::RingModulator1),
+    (EffectID::ID_RINGMOD2 as u32, Effect::RingModulator2),
+    (EffectID::ID_RINGMOD3 as u32, Effect::RingModulator3),
+    (EffectID::ID_RINGMOD4 as u32, Effect::RingModulator4),
+    (EffectID::ID_MULTICOMP1 as u32, Effect::MultibandCompressor1),
+    (EffectID::ID_MULTICOMP2 as u32, Effect::MultibandCompressor2),
+    (EffectID::ID_MULTICOMP3 as u32, Effect::MultibandCompressor3),
+    (EffectID::ID_MULTICOMP4 as u32, Effect::MultibandCompressor4),
+    (EffectID::ID_TENTAP1 as u32, Effect::TenTapDelay1),
+    (EffectID::ID_TENTAP2 as u32, Effect::TenTapDelay2),
+    (EffectID::ID_TENTAP3 as u32, Effect::TenTapDelay3),
+    (EffectID::ID_TENTAP4 as u32, Effect::TenTapDelay4),
+    (EffectID::ID_RESONATOR1 as u32, Effect::Resonator1),
+    (EffectID::ID_RESONATOR2 as u32, Effect::Resonator2),
+    (EffectID::ID_RESONATOR3 as u32, Effect::Resonator3),
+    (EffectID::ID_RESONATOR4 as u32, Effect::Resonator4),
+    (EffectID::ID_LOOPER1 as u32, Effect::Looper1),
+    (EffectID::ID_LOOPER2 as u32, Effect::Looper2),
+    (EffectID::ID_LOOPER3 as u32, Effect::Looper3),
+    (EffectID::ID_LOOPER4 as u32, Effect::Looper4),
+    (EffectID::ID_TONEMATCH1 as u32, Effect::ToneMatch1),
+    (EffectID::ID_TONEMATCH2 as u32, Effect::ToneMatch2),
+    (EffectID::ID_TONEMATCH3 as u32, Effect::ToneMatch3),
+    (EffectID::ID_TONEMATCH4 as u32, Effect::ToneMatch4),
+    (EffectID::ID_RTA1 as u32, Effect::RealtimeAnalyzer1),
+    (EffectID::ID_RTA2 as u32, Effect::RealtimeAnalyzer2),
+    (EffectID::ID_RTA3 as u32, Effect::RealtimeAnalyzer3),
+    (EffectID::ID_RTA4 as u32, Effect::RealtimeAnalyzer4),
+    (EffectID::ID_PLEX1 as u32, Effect::PlexDelay1),
+    (EffectID::ID_PLEX2 as u32, Effect::PlexDelay2),
+    (EffectID::ID_PLEX3 as u32, Effect::PlexDelay3),
+    (EffectID::ID_PLEX4 as u32, Effect::PlexDelay4),
+    (EffectID::ID_FBSEND1 as u32, Effect::FeedbackSend1),
+    (EffectID::ID_FBSEND2 as u32, Effect::FeedbackSend2),
+    (EffectID::ID_FBSEND3 as u32, Effect::FeedbackSend3),
+    (EffectID::ID_FBSEND4 as u32, Effect::FeedbackSend4),
+    (EffectID::ID_FBRETURN1 as u32, Effect::FeedbackReturn1),
+    (EffectID::ID_FBRETURN2 as u32, Effect::FeedbackReturn2),
+    (EffectID::ID_FBRETURN3 as u32, Effect::FeedbackReturn3),
+    (EffectID::ID_FBRETURN4 as u32, Effect::FeedbackReturn4),
+    (EffectID::ID_MIDIBLOCK as u32, Effect::MIDI),
+    (EffectID::ID_MULTIPLEXER1 as u32, Effect::Multiplexer1),
+    (EffectID::ID_MULTIPLEXER2 as u32, Effect::Multiplexer2),
+    (EffectID::ID_MULTIPLEXER3 as u32, Effect::Multiplexer3),
+    (EffectID::ID_MULTIPLEXER4 as u32, Effect::Multiplexer4),
+    (EffectID::ID_IRPLAYER1 as u32, Effect::IRPlayer1),
+    (EffectID::ID_IRPLAYER2 as u32, Effect::IRPlayer2),
+    (EffectID::ID_IRPLAYER3 as u32, Effect::IRPlayer3),
+    (EffectID::ID_IRPLAYER4 as u32, Effect::IRPlayer4),
+    (EffectID::ID_FOOTCONTROLLER as u32, Effect::FootController),
+    (EffectID::ID_PRESET_FC as u32, Effect::PresetFC),
+];
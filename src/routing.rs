@@ -0,0 +1,359 @@
+//! Following the signal through a preset's block grid.
+//!
+//! The input feeds every block in the first column that has a connection
+//! flag set, and blocks in the last column feed the output. A block is live
+//! when it sits on some path from input to output; every other block is
+//! either disconnected (no connections at all) or part of a dead-end branch.
+
+use crate::grid::Grid;
+use crate::parse::{BlockGridBlock, Effect};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// Enumerating paths stops here; a fully cross-connected 6×14 grid has
+/// billions of them.
+pub const MAX_PATHS: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Cell {
+    pub row: usize,
+    pub column: usize,
+}
+
+/// A run of columns where the signal is split across several rows.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParallelSection {
+    pub first_column: usize,
+    pub last_column: usize,
+    pub rows: Vec<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Routing {
+    grid: Grid,
+    /// Every path from input to output, top row first, up to `MAX_PATHS`.
+    pub paths: Vec<Vec<Cell>>,
+    pub truncated: bool,
+    /// Blocks with no connection in either direction.
+    pub disconnected: Vec<Cell>,
+    /// Connected blocks that aren't on any path from input to output.
+    pub dead_ends: Vec<Cell>,
+    pub parallel: Vec<ParallelSection>,
+}
+
+fn effect_at(grid: &Grid, cell: Cell) -> Option<Effect> {
+    match grid.get(cell.row, cell.column) {
+        Some(BlockGridBlock::EffectBlock { effect, .. }) => Some(*effect),
+        _ => None,
+    }
+}
+
+fn connections(grid: &Grid, cell: Cell) -> u8 {
+    match grid.get(cell.row, cell.column) {
+        Some(BlockGridBlock::EffectBlock { connections, .. }) => *connections,
+        _ => 0,
+    }
+}
+
+fn blocks(grid: &Grid) -> Vec<Cell> {
+    (0..grid.columns())
+        .flat_map(|column| (0..grid.rows()).map(move |row| Cell { row, column }))
+        .filter(|cell| effect_at(grid, *cell).is_some())
+        .collect()
+}
+
+/// Blocks in the previous column that feed `cell`.
+fn sources(grid: &Grid, cell: Cell) -> Vec<Cell> {
+    if cell.column == 0 {
+        return vec![];
+    }
+    let flags = connections(grid, cell);
+    (0..grid.rows())
+        .filter(|row| flags & 1 << row != 0)
+        .map(|row| Cell {
+            row,
+            column: cell.column - 1,
+        })
+        .filter(|source| effect_at(grid, *source).is_some())
+        .collect()
+}
+
+/// Blocks in the next column fed by `cell`.
+fn targets(grid: &Grid, cell: Cell) -> Vec<Cell> {
+    (0..grid.rows())
+        .map(|row| Cell {
+            row,
+            column: cell.column + 1,
+        })
+        .filter(|target| sources(grid, *target).contains(&cell))
+        .collect()
+}
+
+fn fed_by_input(grid: &Grid, cell: Cell) -> bool {
+    cell.column == 0 && connections(grid, cell) != 0
+}
+
+fn feeds_output(grid: &Grid, cell: Cell) -> bool {
+    cell.column + 1 == grid.columns()
+}
+
+impl Grid {
+    pub fn routing(&self) -> Routing {
+        let blocks = blocks(self);
+
+        // Columns run left to right, so one pass in each direction settles
+        // what the input reaches and what reaches the output.
+        let mut from_input = BTreeSet::new();
+        for cell in &blocks {
+            if fed_by_input(self, *cell)
+                || sources(self, *cell).iter().any(|s| from_input.contains(s))
+            {
+                from_input.insert(*cell);
+            }
+        }
+        let mut to_output = BTreeSet::new();
+        for cell in blocks.iter().rev() {
+            if feeds_output(self, *cell)
+                || targets(self, *cell).iter().any(|t| to_output.contains(t))
+            {
+                to_output.insert(*cell);
+            }
+        }
+        let live: BTreeSet<Cell> = from_input.intersection(&to_output).copied().collect();
+
+        let mut routing = Routing {
+            grid: self.clone(),
+            paths: vec![],
+            truncated: false,
+            disconnected: vec![],
+            dead_ends: vec![],
+            parallel: vec![],
+        };
+        for cell in &blocks {
+            if live.contains(cell) {
+                continue;
+            }
+            let connected = fed_by_input(self, *cell)
+                || !sources(self, *cell).is_empty()
+                || !targets(self, *cell).is_empty();
+            if connected {
+                routing.dead_ends.push(*cell);
+            } else {
+                routing.disconnected.push(*cell);
+            }
+        }
+
+        let mut starts: Vec<Cell> = live
+            .iter()
+            .filter(|cell| fed_by_input(self, **cell))
+            .copied()
+            .collect();
+        starts.sort_by_key(|cell| cell.row);
+        for start in starts {
+            routing.truncated |= !self.walk(&live, vec![start], &mut routing.paths);
+        }
+
+        let mut section: Option<ParallelSection> = None;
+        for column in 0..self.columns() {
+            let rows: Vec<usize> = live
+                .iter()
+                .filter(|cell| cell.column == column)
+                .map(|cell| cell.row)
+                .collect();
+            match &mut section {
+                Some(current) if rows.len() > 1 => {
+                    current.last_column = column;
+                    for row in rows {
+                        if !current.rows.contains(&row) {
+                            current.rows.push(row);
+                        }
+                    }
+                    current.rows.sort_unstable();
+                }
+                None if rows.len() > 1 => {
+                    section = Some(ParallelSection {
+                        first_column: column,
+                        last_column: column,
+                        rows,
+                    })
+                }
+                _ => routing.parallel.extend(section.take()),
+            }
+        }
+        routing.parallel.extend(section);
+        routing
+    }
+
+    /// Extends `path` through live blocks, returning false once `MAX_PATHS`
+    /// is reached.
+    fn walk(&self, live: &BTreeSet<Cell>, path: Vec<Cell>, paths: &mut Vec<Vec<Cell>>) -> bool {
+        let last = path[path.len() - 1];
+        if feeds_output(self, last) {
+            if paths.len() == MAX_PATHS {
+                return false;
+            }
+            paths.push(path);
+            return true;
+        }
+        for target in targets(self, last) {
+            if live.contains(&target) {
+                let next = [&path[..], &[target]].concat();
+                if !self.walk(live, next, paths) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Picks the box-drawing character joining the lines that leave a gutter
+/// position in each direction.
+fn junction(up: bool, down: bool, left: bool, right: bool) -> char {
+    match (up, down, left, right) {
+        (false, false, true, true) => '─',
+        (false, true, true, false) => '┐',
+        (false, true, false, true) => '┌',
+        (false, true, true, true) => '┬',
+        (true, false, true, false) => '┘',
+        (true, false, false, true) => '└',
+        (true, false, true, true) => '┴',
+        (true, true, false, false) => '│',
+        (true, true, true, false) => '┤',
+        (true, true, false, true) => '├',
+        (true, true, true, true) => '┼',
+        _ => ' ',
+    }
+}
+
+impl Routing {
+    pub fn effect(&self, cell: Cell) -> Option<Effect> {
+        effect_at(&self.grid, cell)
+    }
+
+    fn label(&self, cell: Cell) -> String {
+        self.effect(cell)
+            .map(|effect| format!("{:?}", effect))
+            .unwrap_or_default()
+    }
+
+    /// A box-drawing diagram of the grid, one text line per row, followed by
+    /// a line for every disconnected or dead-end block.
+    ///
+    /// Connections between two columns are drawn as a shared bus, so the
+    /// diagram shows which blocks are joined but not always which pairs;
+    /// `to_dot` has the exact edges.
+    pub fn to_text(&self) -> String {
+        let grid = &self.grid;
+        let width = blocks(grid)
+            .iter()
+            .map(|cell| self.label(*cell).chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut lines: Vec<String> = (0..grid.rows())
+            .map(|row| {
+                let fed = fed_by_input(grid, Cell { row, column: 0 });
+                String::from(if fed { "in ─" } else { "    " })
+            })
+            .collect();
+        for column in 0..grid.columns() {
+            if column > 0 {
+                let has_out = |row| {
+                    !targets(
+                        grid,
+                        Cell {
+                            row,
+                            column: column - 1,
+                        },
+                    )
+                    .is_empty()
+                };
+                let has_in = |row| !sources(grid, Cell { row, column }).is_empty();
+                let joined: Vec<usize> = (0..grid.rows())
+                    .filter(|row| has_out(*row) || has_in(*row))
+                    .collect();
+                for (row, line) in lines.iter_mut().enumerate() {
+                    let (left, right) = (has_out(row), has_in(row));
+                    let (up, down) = match (joined.first(), joined.last()) {
+                        (Some(top), Some(bottom)) => {
+                            (row > *top && row <= *bottom, row >= *top && row < *bottom)
+                        }
+                        _ => (false, false),
+                    };
+                    line.push(if left { '─' } else { ' ' });
+                    line.push(junction(up, down, left, right));
+                    line.push(if right { '─' } else { ' ' });
+                }
+            }
+            for (row, line) in lines.iter_mut().enumerate() {
+                let cell = Cell { row, column };
+                match self.effect(cell) {
+                    Some(_) => write!(line, "[{:<width$}]", self.label(cell), width = width),
+                    None => write!(line, " {:width$} ", "", width = width),
+                }
+                .unwrap();
+            }
+        }
+        let last = grid.columns() - 1;
+        for (row, line) in lines.iter_mut().enumerate() {
+            if self.effect(Cell { row, column: last }).is_some() {
+                line.push_str("─ out");
+            }
+            let trimmed = line.trim_end().len();
+            line.truncate(trimmed);
+        }
+        let notes = self
+            .disconnected
+            .iter()
+            .map(|cell| (cell, "disconnected"))
+            .chain(self.dead_ends.iter().map(|cell| (cell, "dead end")));
+        for (cell, problem) in notes {
+            lines.push(format!(
+                "{}: {} (row {}, column {})",
+                problem,
+                self.label(*cell),
+                cell.row + 1,
+                cell.column + 1
+            ));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    /// The routing as a Graphviz digraph. Blocks off the signal path are
+    /// drawn dashed and red.
+    pub fn to_dot(&self) -> String {
+        let grid = &self.grid;
+        let id = |cell: Cell| format!("r{}c{}", cell.row + 1, cell.column + 1);
+        let mut dot = String::from("digraph preset {\n    rankdir=LR;\n");
+        dot.push_str("    input [label=\"Input\", shape=circle];\n");
+        dot.push_str("    output [label=\"Output\", shape=doublecircle];\n");
+        for cell in blocks(grid) {
+            let style = if self.disconnected.contains(&cell) || self.dead_ends.contains(&cell) {
+                ", style=dashed, color=red"
+            } else {
+                ""
+            };
+            writeln!(
+                dot,
+                "    {} [label=\"{}\", shape=box{}];",
+                id(cell),
+                self.label(cell),
+                style
+            )
+            .unwrap();
+        }
+        for cell in blocks(grid) {
+            if fed_by_input(grid, cell) {
+                writeln!(dot, "    input -> {};", id(cell)).unwrap();
+            }
+            for target in targets(grid, cell) {
+                writeln!(dot, "    {} -> {};", id(cell), id(target)).unwrap();
+            }
+            if feeds_output(grid, cell) {
+                writeln!(dot, "    {} -> output;", id(cell)).unwrap();
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
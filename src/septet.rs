@@ -0,0 +1,45 @@
+//! Packing of 8-bit data into 7-bit SysEx data bytes.
+//!
+//! The Axe-Fx III sends names and other 8-bit data as a bit stream, most
+//! significant bit first, cut into 7-bit bytes. Every 7 input bytes become 8
+//! output bytes; a shorter tail is padded with zero bits.
+
+/// Packs the `i`th byte of a 7-byte group. `last` is the partially filled
+/// output byte; returns it completed along with the start of the next one.
+pub(crate) fn pack_byte(i: usize, last: u8, x: u8) -> (u8, u8) {
+    let i = i % 7;
+    (last | (x >> (i + 1)), 0x7F & (x << (6 - i)))
+}
+
+pub fn pack(bytes: &[u8]) -> Vec<u8> {
+    bytes
+        .chunks(7)
+        .flat_map(|chunk| {
+            chunk.iter().enumerate().fold(vec![0], |mut acc, (i, x)| {
+                let len = acc.len();
+                let (last, next) = pack_byte(i, acc[len - 1], *x);
+                acc[len - 1] = last;
+                acc.push(next);
+                acc
+            })
+        })
+        .collect()
+}
+
+/// The inverse of `pack`. Trailing bits that don't fill a whole byte are
+/// dropped, so `unpack(&pack(bytes)) == bytes` for any input.
+pub fn unpack(septets: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(septets.len() * 7 / 8);
+    let mut bits: u16 = 0;
+    let mut bit_count = 0;
+    for septet in septets {
+        bits = (bits << 7) | u16::from(septet & 0x7F);
+        bit_count += 7;
+        if bit_count >= 8 {
+            bit_count -= 8;
+            bytes.push((bits >> bit_count) as u8);
+            bits &= (1 << bit_count) - 1;
+        }
+    }
+    bytes
+}
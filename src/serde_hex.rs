@@ -0,0 +1,22 @@
+//! Raw SysEx bytes as hex strings, eg. `"F0 00 01 74 10 14 7F 7F 01 F7"`,
+//! for `#[serde(with = "crate::serde_hex")]` fields.
+//!
+//! Strings are read back with `parse_hex`, so any layout it accepts will do.
+
+use crate::parse_hex;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serializer};
+
+pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    let text = bytes
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect::<Vec<_>>()
+        .join(" ");
+    serializer.serialize_str(&text)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let text = String::deserialize(deserializer)?;
+    parse_hex(&text).map_err(D::Error::custom)
+}
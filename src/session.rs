@@ -0,0 +1,625 @@
+use crate::cab_file::CabFile;
+use crate::looper::{loop_length, Looper, LooperCommand, LooperError, LooperPhase};
+use crate::preset_file::PresetFileError;
+use crate::transport::MidiTransport;
+use crate::*;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
+
+const SCENE_COUNT: u8 = 8;
+
+#[derive(Debug)]
+pub enum SessionError {
+    Transport(io::Error),
+    /// No matching response arrived before the session's timeout.
+    Timeout,
+    /// The device answered with a multipurpose response carrying an error code.
+    Rejected {
+        function_id: u8,
+        response_code: u8,
+    },
+    /// The request has no equivalent for the session's model.
+    Unsupported,
+    /// The block has fewer channels than the one requested.
+    InvalidChannel(Channel),
+    /// The model has no ID for the effect.
+    UnknownEffect(Effect),
+    /// The raw parameter value is above `MAX_RAW_VALUE`.
+    InvalidValue(u32),
+    /// A preset dump arrived out of order or for another model.
+    InvalidDump(PresetFileError),
+    Looper(LooperError),
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SessionError::Transport(err) => write!(f, "transport error: {}", err),
+            SessionError::Timeout => write!(f, "timed out waiting for a response"),
+            SessionError::Rejected {
+                function_id,
+                response_code,
+            } => write!(
+                f,
+                "device rejected function ID 0x{:02X} with response code {}",
+                function_id, response_code
+            ),
+            SessionError::Unsupported => write!(f, "not supported by this model"),
+            SessionError::InvalidDump(err) => write!(f, "invalid preset dump: {}", err),
+            SessionError::InvalidChannel(channel) => {
+                write!(f, "block has no channel {:?}", channel)
+            }
+            SessionError::UnknownEffect(effect) => {
+                write!(f, "{:?} has no ID on this model", effect)
+            }
+            SessionError::InvalidValue(value) => {
+                write!(f, "{} is above the largest raw value", value)
+            }
+            SessionError::Looper(err) => write!(f, "looper: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+impl From<io::Error> for SessionError {
+    fn from(err: io::Error) -> Self {
+        SessionError::Transport(err)
+    }
+}
+
+impl From<LooperError> for SessionError {
+    fn from(err: LooperError) -> Self {
+        SessionError::Looper(err)
+    }
+}
+
+/// Sends requests to a device over a `MidiTransport` and waits for the
+/// matching responses.
+///
+/// Messages that arrive while waiting but don't answer the pending request
+/// (eg. `MIDITempoBeat` or `FrontPanelChangeDetected`) are kept and can be
+/// collected with `take_unsolicited`.
+pub struct Session<T: MidiTransport> {
+    transport: T,
+    model: FractalModel,
+    timeout: Duration,
+    assembler: SysexAssembler,
+    unsolicited: Vec<FractalMessage>,
+    pending: VecDeque<FractalMessage>,
+}
+
+/// The result of walking a preset bank with `Session::preset_bank`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PresetBank {
+    /// Preset numbers with their names, in ascending order.
+    pub names: Vec<(u32, String)>,
+    /// Presets that got no reply in time, or that the device rejected.
+    pub missing: Vec<u32>,
+    /// Whether the walk was stopped by the progress callback.
+    pub cancelled: bool,
+}
+
+/// A multipurpose response rejecting the request with `function_id`.
+fn rejection(message: &FractalMessage, function_id: Option<u8>) -> Option<SessionError> {
+    match *message {
+        FractalMessage::MultipurposeResponse {
+            function_id: response_function_id,
+            response_code,
+        } if Some(response_function_id) == function_id && response_code != 0 => {
+            Some(SessionError::Rejected {
+                function_id: response_function_id,
+                response_code,
+            })
+        }
+        _ => None,
+    }
+}
+
+impl<T: MidiTransport> Session<T> {
+    pub fn new(transport: T, model: FractalModel) -> Self {
+        Session {
+            transport,
+            model,
+            timeout: DEFAULT_TIMEOUT,
+            assembler: SysexAssembler::new(),
+            unsolicited: vec![],
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn model(&self) -> FractalModel {
+        self.model
+    }
+
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    pub fn transport_mut(&mut self) -> &mut T {
+        &mut self.transport
+    }
+
+    pub fn into_transport(self) -> T {
+        self.transport
+    }
+
+    /// Sends a message without waiting for a response.
+    pub fn send(&mut self, msg: &[u8]) -> Result<(), SessionError> {
+        Ok(self.transport.send(msg)?)
+    }
+
+    /// Sends `msg` and returns the first response for which `matcher` returns
+    /// `Some`.
+    pub fn request<R, F>(&mut self, msg: &[u8], mut matcher: F) -> Result<R, SessionError>
+    where
+        F: FnMut(&FractalMessage) -> Option<R>,
+    {
+        let function_id = msg.get(5).copied();
+        self.transport.send(msg)?;
+        let deadline = Instant::now() + self.timeout;
+        let result = loop {
+            let message = match self.next_message(deadline) {
+                Ok(message) => message,
+                Err(err) => break Err(err),
+            };
+            if let Some(err) = rejection(&message, function_id) {
+                break Err(err);
+            }
+            if let Some(result) = matcher(&message) {
+                break Ok(result);
+            }
+            self.unsolicited.push(message);
+        };
+        self.unsolicited.extend(self.pending.drain(..));
+        result
+    }
+
+    /// Returns the next decoded message, reading from the transport until one
+    /// is complete or `deadline` passes.
+    fn next_message(&mut self, deadline: Instant) -> Result<FractalMessage, SessionError> {
+        loop {
+            if let Some(message) = self.pending.pop_front() {
+                return Ok(message);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining == Duration::from_secs(0) {
+                return Err(SessionError::Timeout);
+            }
+            let bytes = self.transport.receive(remaining)?;
+            if bytes.is_empty() {
+                return Err(SessionError::Timeout);
+            }
+            self.pending.extend(self.assembler.push_fractal(&bytes));
+        }
+    }
+
+    /// Reads whatever has arrived without sending anything, and returns it
+    /// along with any messages left over from earlier requests.
+    pub fn poll(&mut self) -> Result<Vec<FractalMessage>, SessionError> {
+        let bytes = self.transport.receive(Duration::from_secs(0))?;
+        let messages = self.assembler.push_fractal(&bytes);
+        self.unsolicited.extend(messages);
+        Ok(self.take_unsolicited())
+    }
+
+    pub fn take_unsolicited(&mut self) -> Vec<FractalMessage> {
+        std::mem::take(&mut self.unsolicited)
+    }
+
+    pub fn current_preset_number(&mut self) -> Result<u32, SessionError> {
+        if self.model.uses_iii_protocol() {
+            let msg = get_current_preset_name(self.model);
+            self.request(&msg, |message| match message {
+                FractalMessage::PresetName(number, _) => Some(*number),
+                _ => None,
+            })
+        } else {
+            let msg = get_preset_number(self.model);
+            self.request(&msg, |message| match message {
+                FractalMessage::CurrentPresetNumber(number) => Some(*number),
+                _ => None,
+            })
+        }
+    }
+
+    pub fn current_preset_name(&mut self) -> Result<String, SessionError> {
+        let msg = get_current_preset_name(self.model);
+        self.request(&msg, |message| match message {
+            FractalMessage::PresetName(_, name) | FractalMessage::CurrentPresetName(name) => {
+                Some(name.clone())
+            }
+            _ => None,
+        })
+    }
+
+    /// Reads the name of any preset.
+    ///
+    /// The II can only name the current preset, so there this loads the
+    /// preset and waits for the device to confirm it before asking (see
+    /// `get_preset_name`). The device answers in the order it was asked, so
+    /// a late reply to an earlier query arrives before the confirmation and
+    /// is never taken for this preset's name.
+    pub fn preset_name(&mut self, preset_number: u32) -> Result<String, SessionError> {
+        if !self.model.uses_iii_protocol() {
+            let msg = set_preset_number(self.model, preset_number);
+            self.request(&msg, |message| match message {
+                FractalMessage::CurrentPresetNumber(number) if *number == preset_number => Some(()),
+                _ => None,
+            })?;
+            return self.current_preset_name();
+        }
+        let msg = get_preset_name(self.model, preset_number).remove(0);
+        self.request(&msg, |message| match message {
+            FractalMessage::PresetName(number, name) if *number == preset_number => {
+                Some(name.clone())
+            }
+            _ => None,
+        })
+    }
+
+    /// Reads the name of every preset in the bank, one request at a time.
+    ///
+    /// `progress` is called after each preset with the number handled so far
+    /// and the bank size; returning `false` stops the walk and keeps what was
+    /// read. Presets that time out or are rejected are recorded in `missing`
+    /// rather than failing the walk; see `preset_name` for why a late reply
+    /// is never credited to the wrong preset.
+    ///
+    /// On the II every preset is loaded in turn, so the walk is audible; the
+    /// preset that was loaded beforehand is loaded again at the end.
+    pub fn preset_bank<F>(&mut self, progress: F) -> Result<PresetBank, SessionError>
+    where
+        F: FnMut(usize, usize) -> bool,
+    {
+        if self.model.uses_iii_protocol() {
+            return self.walk_preset_bank(progress);
+        }
+        let loaded = self.current_preset_number()?;
+        let bank = self.walk_preset_bank(progress)?;
+        let msg = set_preset_number(self.model, loaded);
+        self.request(&msg, |message| match message {
+            FractalMessage::CurrentPresetNumber(number) if *number == loaded => Some(()),
+            _ => None,
+        })?;
+        Ok(bank)
+    }
+
+    fn walk_preset_bank<F>(&mut self, mut progress: F) -> Result<PresetBank, SessionError>
+    where
+        F: FnMut(usize, usize) -> bool,
+    {
+        let count = preset_count(self.model);
+        let mut bank = PresetBank::default();
+        for preset_number in 0..count {
+            match self.preset_name(preset_number) {
+                Ok(name) => bank.names.push((preset_number, name)),
+                Err(SessionError::Timeout) | Err(SessionError::Rejected { .. }) => {
+                    bank.missing.push(preset_number)
+                }
+                Err(err) => return Err(err),
+            }
+            if !progress(preset_number as usize + 1, count as usize) {
+                bank.cancelled = true;
+                break;
+            }
+        }
+        Ok(bank)
+    }
+
+    pub fn scene_name(&mut self, scene: u8) -> Result<String, SessionError> {
+        if !self.model.uses_iii_protocol() {
+            return Err(SessionError::Unsupported);
+        }
+        let msg = get_scene_name(self.model, scene);
+        self.request(&msg, |message| match message {
+            FractalMessage::SceneName(number, name) if scene == 0x7F || *number == scene => {
+                Some(name.clone())
+            }
+            _ => None,
+        })
+    }
+
+    pub fn current_scene_name(&mut self) -> Result<String, SessionError> {
+        self.scene_name(0x7F)
+    }
+
+    /// Fetches the names of all eight scenes of the current preset. The
+    /// queries are sent back to back and the answers collected as they
+    /// arrive, so this takes one timeout at most rather than eight.
+    pub fn scene_names(&mut self) -> Result<Vec<String>, SessionError> {
+        if !self.model.uses_iii_protocol() {
+            return Err(SessionError::Unsupported);
+        }
+        for scene in 0..SCENE_COUNT {
+            let msg = get_scene_name(self.model, scene);
+            self.transport.send(&msg)?;
+        }
+        let deadline = Instant::now() + self.timeout;
+        let mut names: Vec<Option<String>> = vec![None; SCENE_COUNT as usize];
+        let result = loop {
+            if names.iter().all(Option::is_some) {
+                break Ok(names.into_iter().flatten().collect());
+            }
+            let message = match self.next_message(deadline) {
+                Ok(message) => message,
+                Err(err) => break Err(err),
+            };
+            if let Some(err) = rejection(&message, Some(0x0E)) {
+                break Err(err);
+            }
+            match message {
+                FractalMessage::SceneName(scene, name) if scene < SCENE_COUNT => {
+                    names[scene as usize] = Some(name)
+                }
+                message => self.unsolicited.push(message),
+            }
+        };
+        self.unsolicited.extend(self.pending.drain(..));
+        result
+    }
+
+    /// Selects a scene and returns the scene number the device reports back.
+    pub fn set_scene_number(&mut self, scene: u8) -> Result<u8, SessionError> {
+        let msg = set_scene_number(self.model, scene);
+        self.request(&msg, |message| match message {
+            FractalMessage::CurrentSceneNumber(number) => Some(*number),
+            _ => None,
+        })
+    }
+
+    pub fn tempo(&mut self) -> Result<u32, SessionError> {
+        if !self.model.uses_iii_protocol() {
+            return Err(SessionError::Unsupported);
+        }
+        let msg = get_tempo(self.model);
+        self.request(&msg, |message| match message {
+            FractalMessage::CurrentTempo(tempo) => Some(*tempo),
+            _ => None,
+        })
+    }
+
+    pub fn grid(&mut self) -> Result<Grid, SessionError> {
+        let msg = get_grid_layout_and_routing(self.model);
+        self.request(&msg, |message| match message {
+            FractalMessage::BlockGrid(grid) => Some(grid.clone()),
+            _ => None,
+        })
+    }
+
+    /// Turns the tuner on and returns the first reading the device sends.
+    pub fn tuner_reading(&mut self, config: &TunerConfig) -> Result<TunerReading, SessionError> {
+        let msg = toggle_tuner_sysex(self.model, TunerStatus::On);
+        self.request_tuner_reading(&msg, config)
+    }
+
+    /// Like `tuner_reading`, but turns the tuner on with a control change on
+    /// `midi_channel` for setups that don't pass SysEx through.
+    pub fn tuner_reading_cc(
+        &mut self,
+        midi_channel: u8,
+        config: &TunerConfig,
+    ) -> Result<TunerReading, SessionError> {
+        let msg = toggle_tuner(midi_channel, TunerStatus::On);
+        self.request_tuner_reading(&msg, config)
+    }
+
+    fn request_tuner_reading(
+        &mut self,
+        msg: &[u8],
+        config: &TunerConfig,
+    ) -> Result<TunerReading, SessionError> {
+        self.request(msg, |message| TunerReading::from_message(message, config))
+    }
+
+    /// Sends a looper command and updates `looper` from the state the device
+    /// reports back.
+    pub fn looper_command(
+        &mut self,
+        looper: &mut Looper,
+        command: LooperCommand,
+    ) -> Result<LooperPhase, SessionError> {
+        let messages = looper.command(command)?;
+        for (i, msg) in messages.iter().enumerate() {
+            // Replies to the first messages of a sequence show the looper
+            // part way through it; only the last one is worth following.
+            let last = i + 1 == messages.len();
+            self.request(msg, |message| match message {
+                FractalMessage::LooperState { .. } => {
+                    if last {
+                        looper.handle(message);
+                    }
+                    Some(())
+                }
+                _ => None,
+            })?;
+        }
+        Ok(looper.phase())
+    }
+
+    /// Records a loop of `beats` beats at `bpm`, then plays it.
+    pub fn record_loop(
+        &mut self,
+        looper: &mut Looper,
+        beats: u32,
+        bpm: f64,
+    ) -> Result<LooperPhase, SessionError> {
+        let length = loop_length(beats, bpm)?;
+        self.looper_command(looper, LooperCommand::Record)?;
+        std::thread::sleep(length);
+        self.looper_command(looper, LooperCommand::Play)
+    }
+
+    pub fn firmware_version(&mut self) -> Result<(u8, u8), SessionError> {
+        let msg = get_firmware_version(self.model);
+        self.request(&msg, |message| match message {
+            FractalMessage::FirmwareVersion { major, minor } => Some((*major, *minor)),
+            _ => None,
+        })
+    }
+
+    pub fn midi_channel(&mut self) -> Result<u8, SessionError> {
+        let msg = get_midi_channel(self.model);
+        self.request(&msg, |message| match message {
+            FractalMessage::MIDIChannel(channel) => Some(*channel),
+            _ => None,
+        })
+    }
+
+    /// Sets a block parameter and returns the raw value and display label the
+    /// device reports back. Axe-Fx II only; see `set_block_parameter`.
+    pub fn set_block_parameter(
+        &mut self,
+        effect: Effect,
+        parameter_id: u32,
+        value: u32,
+    ) -> Result<(u32, String), SessionError> {
+        if self.model.uses_iii_protocol() {
+            return Err(SessionError::Unsupported);
+        }
+        if value > MAX_RAW_VALUE {
+            return Err(SessionError::InvalidValue(value));
+        }
+        let msg = set_block_parameter(self.model, effect, parameter_id, value)
+            .ok_or(SessionError::UnknownEffect(effect))?;
+        self.request(&msg, |message| match message {
+            FractalMessage::BlockParameterValue {
+                effect: response_effect,
+                parameter_id: response_parameter_id,
+                value_raw,
+                label,
+                ..
+            } if *response_effect == effect && *response_parameter_id == parameter_id => {
+                Some((*value_raw, label.clone()))
+            }
+            _ => None,
+        })
+    }
+
+    pub fn block_bypass(&mut self, effect_id: EffectID) -> Result<bool, SessionError> {
+        let msg = get_block_bypass(self.model, effect_id);
+        self.request_bypass(&msg, effect_id)
+    }
+
+    /// Bypasses or engages a block and returns the state the device reports back.
+    pub fn set_block_bypass(
+        &mut self,
+        effect_id: EffectID,
+        bypassed: bool,
+    ) -> Result<bool, SessionError> {
+        let msg = set_block_bypass(self.model, effect_id, bypassed);
+        self.request_bypass(&msg, effect_id)
+    }
+
+    fn request_bypass(&mut self, msg: &[u8], effect_id: EffectID) -> Result<bool, SessionError> {
+        if !self.model.uses_iii_protocol() {
+            return Err(SessionError::Unsupported);
+        }
+        self.request(msg, |message| match message {
+            FractalMessage::BlockBypass {
+                effect_id: id,
+                bypassed,
+                ..
+            } if *id == effect_id as u32 => Some(*bypassed),
+            _ => None,
+        })
+    }
+
+    pub fn block_channel(&mut self, effect_id: EffectID) -> Result<Channel, SessionError> {
+        let msg = get_block_channel(self.model, effect_id);
+        self.request_channel(&msg, effect_id)
+    }
+
+    /// Selects a block's channel and returns the channel the device reports
+    /// back. See `set_block_channel` for `max_channels`.
+    pub fn set_block_channel(
+        &mut self,
+        effect_id: EffectID,
+        channel: Channel,
+        max_channels: u8,
+    ) -> Result<Channel, SessionError> {
+        let msg = set_block_channel(self.model, effect_id, channel, max_channels)
+            .ok_or(SessionError::InvalidChannel(channel))?;
+        self.request_channel(&msg, effect_id)
+    }
+
+    fn request_channel(
+        &mut self,
+        msg: &[u8],
+        effect_id: EffectID,
+    ) -> Result<Channel, SessionError> {
+        if !self.model.uses_iii_protocol() {
+            return Err(SessionError::Unsupported);
+        }
+        self.request(msg, |message| match message {
+            FractalMessage::BlockChannel {
+                effect_id: id,
+                channel,
+                ..
+            } if *id == effect_id as u32 => Some(*channel),
+            _ => None,
+        })
+    }
+
+    /// Downloads a preset. The whole dump has to arrive within the session's
+    /// timeout, so allow for the size of the dump when choosing it.
+    pub fn fetch_preset(&mut self, target: PresetTarget) -> Result<PresetDump, SessionError> {
+        let msg = request_preset_dump(self.model, target);
+        let mut assembler = PresetDumpAssembler::new();
+        self.request(&msg, |message| match message {
+            FractalMessage::PresetDumpMessage(frame) => assembler.push(frame.clone()).transpose(),
+            _ => None,
+        })?
+        .map_err(SessionError::InvalidDump)
+    }
+
+    /// Uploads a preset to the edit buffer, pausing `interval` between
+    /// messages (see `UPLOAD_MESSAGE_INTERVAL`).
+    pub fn upload_preset(
+        &mut self,
+        dump: &PresetDump,
+        interval: Duration,
+    ) -> Result<(), SessionError> {
+        self.send_paced(&dump.upload_messages(), interval)
+    }
+
+    /// Uploads a user cab IR, unchanged, to the slot its header names,
+    /// pausing `interval` between messages.
+    pub fn upload_cab(&mut self, cab: &CabFile, interval: Duration) -> Result<(), SessionError> {
+        self.send_paced(&cab.messages(), interval)
+    }
+
+    fn send_paced(
+        &mut self,
+        messages: &[MidiMessage],
+        interval: Duration,
+    ) -> Result<(), SessionError> {
+        for (i, msg) in messages.iter().enumerate() {
+            if i > 0 {
+                std::thread::sleep(interval);
+            }
+            self.transport.send(msg)?;
+        }
+        Ok(())
+    }
+
+    pub fn status(&mut self) -> Result<Vec<EffectStatus>, SessionError> {
+        if !self.model.uses_iii_protocol() {
+            return Err(SessionError::Unsupported);
+        }
+        let msg = status_dump(self.model);
+        self.request(&msg, |message| match message {
+            FractalMessage::StatusDump(effects) => Some(effects.clone()),
+            _ => None,
+        })
+    }
+}
@@ -0,0 +1,513 @@
+//! A virtual Axe-Fx that answers the messages built by this crate, for
+//! exercising a `Session` (or any other `MidiTransport` consumer) without
+//! hardware.
+
+use crate::parse::decode_preset_name;
+use crate::preset_dump::decode_dump_target;
+use crate::transport::MidiTransport;
+use crate::*;
+use num_traits::FromPrimitive;
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::time::Duration;
+
+const LOOPER_RECORD: u8 = 0b00000001;
+const LOOPER_PLAY: u8 = 0b00000010;
+const LOOPER_OVERDUB: u8 = 0b00000100;
+const LOOPER_ONCE: u8 = 0b00001000;
+const LOOPER_REVERSE: u8 = 0b00010000;
+const LOOPER_HALF_SPEED: u8 = 0b00100000;
+
+fn encode_name(name: &str) -> Vec<u8> {
+    let mut bytes: Vec<u8> = name
+        .chars()
+        .filter(|c| c.is_ascii())
+        .map(|c| c as u8)
+        .take(32)
+        .collect();
+    bytes.resize(32, 32);
+    bytes.push(0);
+    bytes
+}
+
+fn encode_block_flags(status: &EffectStatus) -> Vec<u8> {
+    let id = status.effect_id;
+    vec![
+        if status.bypassed { 2 } else { 3 },
+        0,
+        0,
+        ((id & 0x0F) << 3) as u8,
+        ((id >> 4) & 0x0F) as u8,
+    ]
+}
+
+/// The state of a virtual device. Every field can be set up directly before
+/// handing the simulator to a `Session`, and inspected afterwards.
+pub struct Simulator {
+    pub model: FractalModel,
+    pub preset_number: u32,
+    /// Names of the stored presets, indexed by preset number.
+    pub preset_names: Vec<String>,
+    /// Name of the preset currently loaded, which may have unsaved edits.
+    pub current_preset_name: String,
+    /// Dumps of the stored presets, keyed by preset number. Presets without
+    /// one can't be dumped.
+    pub preset_dumps: BTreeMap<u32, PresetDump>,
+    /// Dump of the preset currently loaded, if it has one.
+    pub edit_buffer_dump: Option<PresetDump>,
+    pub scene: u8,
+    pub scene_names: [String; 8],
+    pub tempo: u32,
+    pub tuner: TunerStatus,
+    pub metronome: bool,
+    /// Looper flags in the bit layout of the looper state response.
+    pub looper: u8,
+    pub effects: Vec<EffectStatus>,
+    pub grid: Grid,
+    /// Raw parameter values keyed by effect ID and parameter ID.
+    pub parameters: BTreeMap<(u32, u32), u32>,
+    pub firmware_version: (u8, u8),
+    pub midi_channel: u8,
+    pub connected: bool,
+    outgoing: VecDeque<MidiMessage>,
+    assembler: SysexAssembler,
+    upload: PresetDumpAssembler,
+}
+
+impl Simulator {
+    pub fn new(model: FractalModel) -> Self {
+        let preset_names = vec![String::new(); preset_count(model) as usize];
+        Simulator {
+            model,
+            preset_number: 0,
+            current_preset_name: preset_names[0].clone(),
+            preset_names,
+            preset_dumps: BTreeMap::new(),
+            edit_buffer_dump: None,
+            scene: 0,
+            scene_names: Default::default(),
+            tempo: 120,
+            tuner: TunerStatus::Off,
+            metronome: false,
+            looper: 0,
+            effects: vec![],
+            grid: Grid::new(model),
+            parameters: BTreeMap::new(),
+            firmware_version: (1, 0),
+            midi_channel: 1,
+            connected: true,
+            outgoing: VecDeque::new(),
+            assembler: SysexAssembler::new(),
+            upload: PresetDumpAssembler::new(),
+        }
+    }
+
+    /// Queues an unsolicited "front panel change detected" notification.
+    pub fn inject_front_panel_change(&mut self) {
+        self.reply(0x21, vec![]);
+    }
+
+    /// Queues an unsolicited tempo beat notification.
+    pub fn inject_tempo_beat(&mut self) {
+        self.reply(0x10, vec![]);
+    }
+
+    /// Queues a tuner reading, as sent while the tuner is on.
+    pub fn inject_tuner_info(&mut self, note: u8, string_number: u8, tuner_data: u8) {
+        self.reply(0x0D, vec![note, string_number, tuner_data]);
+    }
+
+    /// Queues arbitrary bytes to be received next.
+    pub fn inject_raw(&mut self, bytes: &[u8]) {
+        self.outgoing.push_back(bytes.to_vec());
+    }
+
+    /// Handles a single complete message, queueing any responses.
+    pub fn handle(&mut self, msg: &[u8]) {
+        match msg.first() {
+            Some(0xF0) => self.handle_sysex(msg),
+            Some(status) if status & 0xF0 == 0xB0 && msg.len() == 3 => {
+                self.handle_control_change(msg[1], msg[2])
+            }
+            _ => {}
+        }
+    }
+
+    fn reply(&mut self, function_id: u8, payload: Vec<u8>) {
+        self.outgoing
+            .push_back(SysexFrame::new(self.model, function_id, payload).to_message());
+    }
+
+    fn acknowledge(&mut self, function_id: u8) {
+        self.reply(0x64, vec![function_id, 0]);
+    }
+
+    fn reject(&mut self, function_id: u8) {
+        self.reply(0x64, vec![function_id, 1]);
+    }
+
+    fn handle_control_change(&mut self, cc: u8, value: u8) {
+        match cc {
+            15 => {
+                self.tuner = if value > 63 {
+                    TunerStatus::On
+                } else {
+                    TunerStatus::Off
+                }
+            }
+            122 => self.metronome = value > 63,
+            _ => {}
+        }
+    }
+
+    fn handle_sysex(&mut self, msg: &[u8]) {
+        let frame = match SysexFrame::parse_request(msg) {
+            Ok(frame) => frame,
+            Err(_) => return,
+        };
+        if frame.verify().is_err() || frame.model != self.model {
+            self.reject(frame.function_id);
+            return;
+        }
+        let iii = self.model.uses_iii_protocol();
+        let payload = frame.payload.as_slice();
+        match (frame.function_id, payload.len()) {
+            (0x14, 0) if !iii => self.reply_preset_number(),
+            (0x14, 0) => self.reply_tempo(),
+            (0x14, 2) if payload == [0x7F, 0x7F] => self.reply_tempo(),
+            (0x14, 2) => {
+                self.tempo = u32::from(payload[0]) | u32::from(payload[1]) << 7;
+                if iii {
+                    self.reply_tempo();
+                } else {
+                    self.acknowledge(0x14);
+                }
+            }
+            (0x3C, 2) => {
+                let n = u32::from(payload[0]) << 7 | u32::from(payload[1]);
+                self.load_preset(n);
+                if iii {
+                    self.reply_preset_name(self.preset_number);
+                } else {
+                    self.reply_preset_number();
+                }
+            }
+            (0x0D, 2) if iii => {
+                let n = u32::from(payload[0]) | u32::from(payload[1]) << 7;
+                self.reply_preset_name(if n == 0x3FFF { self.preset_number } else { n });
+            }
+            (0x0F, 0) if !iii => {
+                let name = encode_name(&self.current_preset_name);
+                self.reply(0x0F, name);
+            }
+            (0x0F, 1) => {
+                if payload[0] != 0x7F {
+                    self.update_looper(payload[0]);
+                }
+                if iii {
+                    self.reply(0x0F, vec![self.looper]);
+                }
+            }
+            (0x0E, 1) if iii => {
+                let scene = if payload[0] == 0x7F {
+                    self.scene
+                } else {
+                    payload[0]
+                };
+                match self.scene_names.get(scene as usize) {
+                    Some(name) => {
+                        let name = encode_name(name);
+                        self.reply(0x0E, [vec![scene], name].concat());
+                    }
+                    None => self.reject(0x0E),
+                }
+            }
+            (0x0E, 0) => {
+                let flags = self.effects.iter().flat_map(encode_block_flags).collect();
+                self.reply(0x0E, flags);
+            }
+            (0x09, _) if !iii => {
+                self.current_preset_name = decode_preset_name(payload);
+                self.acknowledge(0x09);
+            }
+            (0x0C, 1) if iii => {
+                if payload[0] < 8 {
+                    self.scene = payload[0];
+                }
+                self.reply(0x0C, vec![self.scene]);
+            }
+            (0x29, 1) if !iii => {
+                if payload[0] < 8 {
+                    self.scene = payload[0];
+                }
+                self.reply(0x29, vec![self.scene]);
+            }
+            (0x08, 0) => {
+                let (major, minor) = self.firmware_version;
+                self.reply(0x08, vec![major, minor, 0, 0, 0, 0, 0]);
+            }
+            (0x42, 0) => self.connected = false,
+            (0x17, 0) => self.reply(0x17, vec![self.midi_channel.saturating_sub(1), 0]),
+            (0x11, 1) => {
+                self.tuner = if payload[0] == 0 {
+                    TunerStatus::Off
+                } else {
+                    TunerStatus::On
+                };
+                self.reply(0x11, vec![payload[0]]);
+            }
+            (0x20, 0) => self.reply(0x20, self.grid.encode()),
+            (0x01, 2) => self.reply_block_parameters(payload),
+            (0x02, 8) => self.set_block_parameter(payload),
+            (0x01, _) if iii && payload[0] == 0x26 && payload.len() >= 8 => {
+                let n = u32::from(payload[6]) | u32::from(payload[7]) << 7;
+                self.store(n);
+                self.acknowledge(0x01);
+            }
+            (0x01, _) if iii && payload[0] == 0x28 && payload.len() >= 15 => {
+                let n = u32::from(payload[6]) | u32::from(payload[7]) << 7;
+                let name = decode_preset_name_iii(&payload[15..]);
+                if n == self.preset_number {
+                    self.current_preset_name = name.clone();
+                }
+                if let Some(slot) = self.preset_names.get_mut(n as usize) {
+                    *slot = name;
+                }
+                self.acknowledge(0x01);
+            }
+            (0x1D, 2) if !iii => {
+                self.store(u32::from(payload[0]) << 7 | u32::from(payload[1]));
+                self.acknowledge(0x1D);
+            }
+            (0x0A, 3) if iii => {
+                let effect_id = u32::from(payload[0]) | u32::from(payload[1]) << 7;
+                match self.effects.iter_mut().find(|s| s.effect_id == effect_id) {
+                    Some(status) => {
+                        if payload[2] != 0x7F {
+                            status.bypassed = payload[2] != 0;
+                        }
+                        let bypassed = status.bypassed as u8;
+                        self.reply(0x0A, vec![payload[0], payload[1], bypassed]);
+                    }
+                    None => self.reject(0x0A),
+                }
+            }
+            (0x0B, 3) if iii => {
+                let effect_id = u32::from(payload[0]) | u32::from(payload[1]) << 7;
+                let status = self.effects.iter_mut().find(|s| s.effect_id == effect_id);
+                let channel = match (status, FromPrimitive::from_u8(payload[2])) {
+                    (Some(status), _) if payload[2] == 0x7F => status.channel,
+                    (Some(status), Some(channel)) if payload[2] < status.max_channels => {
+                        status.channel = channel;
+                        channel
+                    }
+                    _ => return self.reject(0x0B),
+                };
+                self.reply(0x0B, vec![payload[0], payload[1], channel as u8]);
+            }
+            (0x03, 2) => {
+                let messages = decode_dump_target(self.model, payload).and_then(|target| {
+                    let dump = match target {
+                        PresetTarget::EditBuffer => self.edit_buffer_dump.as_ref(),
+                        PresetTarget::Preset(n) => self.preset_dumps.get(&n),
+                    };
+                    dump.map(|dump| dump.messages_for(target))
+                });
+                match messages {
+                    Some(messages) => self.outgoing.extend(messages),
+                    None => self.reject(0x03),
+                }
+            }
+            (0x77, _) | (0x78, _) | (0x79, _) => match self.upload.push(frame.clone()) {
+                Ok(Some(dump)) => self.receive_dump(dump),
+                Ok(None) => {}
+                Err(_) => {
+                    self.upload = PresetDumpAssembler::new();
+                    self.reject(frame.function_id);
+                }
+            },
+            (0x13, 0) if iii => {
+                let statuses = self
+                    .effects
+                    .iter()
+                    .flat_map(|status| {
+                        vec![
+                            (status.effect_id & 0x7F) as u8,
+                            ((status.effect_id >> 7) & 0x7F) as u8,
+                            status.bypassed as u8
+                                | (status.channel as u8) << 1
+                                | (status.max_channels & 0b0111) << 4,
+                        ]
+                    })
+                    .collect();
+                self.reply(0x13, statuses);
+            }
+            (function_id, _) => self.reject(function_id),
+        }
+    }
+
+    fn load_preset(&mut self, n: u32) {
+        if let Some(name) = self.preset_names.get(n as usize) {
+            self.preset_number = n;
+            self.current_preset_name = name.clone();
+            self.edit_buffer_dump = self.preset_dumps.get(&n).cloned();
+            self.scene = 0;
+        }
+    }
+
+    fn store(&mut self, n: u32) {
+        if let Some(slot) = self.preset_names.get_mut(n as usize) {
+            *slot = self.current_preset_name.clone();
+            if let Some(dump) = &self.edit_buffer_dump {
+                self.preset_dumps.insert(n, dump.clone());
+            }
+        }
+    }
+
+    /// Takes an uploaded dump into the slot its header names, or into the
+    /// edit buffer.
+    fn receive_dump(&mut self, dump: PresetDump) {
+        match dump.preset_number {
+            Some(n) if (n as usize) < self.preset_names.len() => {
+                self.preset_names[n as usize] = dump.name.clone();
+                self.preset_dumps.insert(n, dump);
+            }
+            Some(_) => self.reject(0x77),
+            None => {
+                self.current_preset_name = dump.name.clone();
+                self.edit_buffer_dump = Some(dump);
+            }
+        }
+    }
+
+    fn update_looper(&mut self, code: u8) {
+        let looper = self.looper;
+        self.looper = match code {
+            0 if looper & LOOPER_RECORD != 0 => (looper & !LOOPER_RECORD) | LOOPER_PLAY,
+            0 if looper & LOOPER_PLAY != 0 => looper ^ LOOPER_OVERDUB,
+            0 => looper | LOOPER_RECORD,
+            1 if looper & (LOOPER_RECORD | LOOPER_OVERDUB) != 0 => {
+                (looper & !(LOOPER_RECORD | LOOPER_OVERDUB)) | LOOPER_PLAY
+            }
+            1 => looper ^ LOOPER_PLAY,
+            2 => looper & !LOOPER_OVERDUB,
+            3 => looper ^ LOOPER_ONCE,
+            4 => looper ^ LOOPER_REVERSE,
+            5 => looper ^ LOOPER_HALF_SPEED,
+            _ => looper,
+        };
+    }
+
+    fn reply_preset_number(&mut self) {
+        let n = self.preset_number;
+        self.reply(0x14, vec![(n >> 7) as u8 & 0x7F, n as u8 & 0x7F]);
+    }
+
+    fn reply_preset_name(&mut self, n: u32) {
+        let name = match self.preset_names.get(n as usize) {
+            Some(_) if n == self.preset_number => self.current_preset_name.clone(),
+            Some(name) => name.clone(),
+            None => return self.reject(0x0D),
+        };
+        self.reply(
+            0x0D,
+            [
+                vec![n as u8 & 0x7F, (n >> 7) as u8 & 0x7F],
+                encode_name(&name),
+            ]
+            .concat(),
+        );
+    }
+
+    fn reply_tempo(&mut self) {
+        let tempo = self.tempo;
+        self.reply(0x14, vec![tempo as u8 & 0x7F, (tempo >> 7) as u8 & 0x7F]);
+    }
+
+    fn set_block_parameter(&mut self, payload: &[u8]) {
+        let effect_id = u32::from(payload[0]) | u32::from(payload[1]) << 7;
+        let parameter_id = u32::from(payload[2]) | u32::from(payload[3]) << 7;
+        let effect = EffectRegistry::for_model(self.model).effect(effect_id);
+        let info = match parameter_info(effect, parameter_id) {
+            Some(info) => info,
+            None => return self.reject(0x02),
+        };
+        let mut value =
+            u32::from(payload[4]) | u32::from(payload[5]) << 7 | u32::from(payload[6]) << 14;
+        if payload[7] == 0x01 {
+            value = value.min(MAX_RAW_VALUE);
+            self.parameters.insert((effect_id, parameter_id), value);
+        } else {
+            value = self
+                .parameters
+                .get(&(effect_id, parameter_id))
+                .copied()
+                .unwrap_or(0);
+        }
+        let label = match info.choice(value) {
+            Some(choice) => choice.to_string(),
+            None => format!("{:.2}", info.display_value(value)),
+        };
+        self.reply(
+            0x02,
+            [
+                vec![
+                    payload[0],
+                    payload[1],
+                    payload[2],
+                    payload[3],
+                    (value & 0x7F) as u8,
+                    ((value >> 7) & 0x7F) as u8,
+                    ((value >> 14) & 0x7F) as u8,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                ],
+                label.into_bytes(),
+                vec![0],
+            ]
+            .concat(),
+        );
+    }
+
+    fn reply_block_parameters(&mut self, payload: &[u8]) {
+        let effect_id = u32::from(payload[0]) | u32::from(payload[1]) << 7;
+        let values: Vec<(u32, u32)> = self
+            .parameters
+            .range((effect_id, 0)..=(effect_id, u32::MAX))
+            .map(|(&(_, parameter_id), &value)| (parameter_id, value))
+            .collect();
+        if values.is_empty() {
+            return self.reject(0x01);
+        }
+        for (parameter_id, value) in values {
+            self.reply(
+                0x01,
+                vec![
+                    payload[0],
+                    payload[1],
+                    (parameter_id & 0x7F) as u8,
+                    ((parameter_id >> 7) & 0x7F) as u8,
+                    (value & 0x7F) as u8,
+                    ((value >> 7) & 0x7F) as u8,
+                    ((value >> 14) & 0x7F) as u8,
+                ],
+            );
+        }
+    }
+}
+
+impl MidiTransport for Simulator {
+    fn send(&mut self, bytes: &[u8]) -> io::Result<()> {
+        for msg in self.assembler.push(bytes) {
+            self.handle(&msg);
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self, _timeout: Duration) -> io::Result<Vec<u8>> {
+        Ok(self.outgoing.pop_front().unwrap_or_default())
+    }
+}
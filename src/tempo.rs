@@ -0,0 +1,190 @@
+//! Following the device's tempo and setting it by tapping.
+//!
+//! Timestamps are `Duration`s since any fixed origin, such as the timestamps
+//! MIDI backends attach to incoming messages. Nothing here reads the clock,
+//! so both engines give the same answer for the same input.
+
+use crate::{set_tempo, FractalMessage, FractalModel, MidiMessage};
+use std::time::Duration;
+
+/// The tempo range the devices accept, in BPM.
+pub const MIN_TEMPO: f64 = 24.0;
+pub const MAX_TEMPO: f64 = 250.0;
+
+/// Taps further apart than this start a new tempo.
+pub const TAP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Intervals further than this fraction from the running estimate are
+/// treated as outliers, or as a tempo change once two agree.
+const TOLERANCE: f64 = 0.2;
+
+fn seconds_per_beat(bpm: f64) -> f64 {
+    60.0 / bpm
+}
+
+fn close(a: f64, b: f64, tolerance: f64) -> bool {
+    (a - b).abs() <= b * tolerance
+}
+
+/// Estimates the device's tempo from the `MIDITempoBeat` messages it sends
+/// on every beat.
+///
+/// Each beat interval is blended into an exponential moving average. A
+/// single interval far from the average is ignored as jitter or a dropped
+/// message; two in a row that agree with each other are taken as a tempo
+/// change and replace the average outright.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TempoTracker {
+    smoothing: f64,
+    last_beat: Option<Duration>,
+    interval: Option<f64>,
+    pending: Option<f64>,
+}
+
+impl Default for TempoTracker {
+    fn default() -> Self {
+        TempoTracker::new()
+    }
+}
+
+impl TempoTracker {
+    pub fn new() -> Self {
+        TempoTracker::with_smoothing(0.25)
+    }
+
+    /// `smoothing` is the weight of each new interval, between 0 (never
+    /// move) and 1 (no smoothing).
+    pub fn with_smoothing(smoothing: f64) -> Self {
+        TempoTracker {
+            smoothing: smoothing.clamp(0.0, 1.0),
+            last_beat: None,
+            interval: None,
+            pending: None,
+        }
+    }
+
+    /// Feeds a message received at `at`; anything but `MIDITempoBeat` is
+    /// ignored.
+    pub fn handle(&mut self, message: &FractalMessage, at: Duration) -> Option<f64> {
+        match message {
+            FractalMessage::MIDITempoBeat => self.beat(at),
+            _ => self.bpm(),
+        }
+    }
+
+    /// Records a beat at `at` and returns the updated estimate.
+    pub fn beat(&mut self, at: Duration) -> Option<f64> {
+        let last = self.last_beat.replace(at);
+        let interval = match last {
+            Some(last) if at > last => (at - last).as_secs_f64(),
+            _ => return self.bpm(),
+        };
+        if interval > seconds_per_beat(MIN_TEMPO) * (1.0 + TOLERANCE) {
+            // Beats stopped for a while; whatever comes next is a new tempo.
+            self.interval = None;
+            self.pending = None;
+            return None;
+        }
+        match self.interval {
+            None => self.interval = Some(interval),
+            Some(average) if close(interval, average, TOLERANCE) => {
+                self.interval = Some(average + self.smoothing * (interval - average));
+                self.pending = None;
+            }
+            Some(_) => match self.pending.take() {
+                Some(pending) if close(interval, pending, TOLERANCE) => {
+                    self.interval = Some((interval + pending) / 2.0);
+                }
+                _ => self.pending = Some(interval),
+            },
+        }
+        self.bpm()
+    }
+
+    pub fn bpm(&self) -> Option<f64> {
+        self.interval.map(|interval| 60.0 / interval)
+    }
+
+    pub fn reset(&mut self) {
+        *self = TempoTracker::with_smoothing(self.smoothing);
+    }
+}
+
+/// Turns taps into a tempo.
+///
+/// Every tap interval is compared with the median: intervals close to twice
+/// or half of it are a skipped or doubled tap and are folded back, and
+/// whatever is still far from it is dropped before averaging. The result is
+/// doubled or halved until it falls within the device's range.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TapTempo {
+    taps: Vec<Duration>,
+}
+
+impl TapTempo {
+    /// Only this many of the latest taps count towards the estimate.
+    pub const WINDOW: usize = 8;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a tap at `at` and returns the estimate, once there are at
+    /// least two taps.
+    pub fn tap(&mut self, at: Duration) -> Option<f64> {
+        match self.taps.last() {
+            Some(last) if at <= *last => return self.bpm(),
+            Some(last) if at - *last > TAP_TIMEOUT => self.taps.clear(),
+            _ => {}
+        }
+        self.taps.push(at);
+        if self.taps.len() > Self::WINDOW {
+            self.taps.remove(0);
+        }
+        self.bpm()
+    }
+
+    pub fn bpm(&self) -> Option<f64> {
+        let intervals: Vec<f64> = self
+            .taps
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).as_secs_f64())
+            .collect();
+        if intervals.is_empty() {
+            return None;
+        }
+        let mut sorted = intervals.clone();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let median = sorted[sorted.len() / 2];
+        let kept: Vec<f64> = intervals
+            .iter()
+            .map(|interval| {
+                if close(*interval, median * 2.0, TOLERANCE / 2.0) {
+                    interval / 2.0
+                } else if close(*interval, median / 2.0, TOLERANCE / 2.0) {
+                    interval * 2.0
+                } else {
+                    *interval
+                }
+            })
+            .filter(|interval| close(*interval, median, TOLERANCE))
+            .collect();
+        let mut bpm = 60.0 * kept.len() as f64 / kept.iter().sum::<f64>();
+        while bpm > MAX_TEMPO {
+            bpm /= 2.0;
+        }
+        while bpm < MIN_TEMPO {
+            bpm *= 2.0;
+        }
+        Some(bpm)
+    }
+
+    /// The `set_tempo` message for the current estimate.
+    pub fn message(&self, model: FractalModel) -> Option<MidiMessage> {
+        self.bpm().map(|bpm| set_tempo(model, bpm.round() as u32))
+    }
+
+    pub fn reset(&mut self) {
+        self.taps.clear();
+    }
+}
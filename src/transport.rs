@@ -0,0 +1,60 @@
+use crate::MidiMessage;
+use std::collections::VecDeque;
+use std::io;
+use std::time::Duration;
+
+/// A bidirectional byte pipe to a device, eg. a CoreMIDI port pair or a serial
+/// connection.
+pub trait MidiTransport {
+    fn send(&mut self, bytes: &[u8]) -> io::Result<()>;
+
+    /// Blocks until some bytes arrive or `timeout` elapses. Bytes may be
+    /// delivered in arbitrary chunks; an empty result means nothing arrived in
+    /// time.
+    fn receive(&mut self, timeout: Duration) -> io::Result<Vec<u8>>;
+}
+
+type Responder = Box<dyn FnMut(&[u8]) -> Vec<MidiMessage> + Send>;
+
+/// An in-memory transport for tests. Everything sent is recorded, and replies
+/// are either queued up front or produced by a responder closure.
+#[derive(Default)]
+pub struct MockTransport {
+    pub sent: Vec<MidiMessage>,
+    incoming: VecDeque<Vec<u8>>,
+    responder: Option<Responder>,
+}
+
+impl MockTransport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues bytes to be returned by a later `receive`.
+    pub fn queue_incoming(&mut self, bytes: &[u8]) {
+        self.incoming.push_back(bytes.to_vec());
+    }
+
+    /// Sets a closure that is called with every sent message; whatever it
+    /// returns is queued as incoming bytes.
+    pub fn respond_with<F>(&mut self, responder: F)
+    where
+        F: FnMut(&[u8]) -> Vec<MidiMessage> + Send + 'static,
+    {
+        self.responder = Some(Box::new(responder));
+    }
+}
+
+impl MidiTransport for MockTransport {
+    fn send(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.sent.push(bytes.to_vec());
+        if let Some(responder) = self.responder.as_mut() {
+            self.incoming.extend(responder(bytes));
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self, _timeout: Duration) -> io::Result<Vec<u8>> {
+        Ok(self.incoming.pop_front().unwrap_or_default())
+    }
+}
@@ -0,0 +1,96 @@
+//! Turning the raw `TunerInfo` bytes into something a tuner display can show.
+//!
+//! The device reports the note as 0–11 counting semitones up from A, the
+//! string as 1–6 with 1 the high E, and the deviation as 0–127 with 63 in
+//! tune and each end about 50 cents out.
+
+use crate::parse::FractalMessage;
+
+const NOTE_NAMES: [&str; 12] = [
+    "A", "A#", "B", "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#",
+];
+
+/// MIDI note numbers of the open strings in standard tuning, high E first.
+const OPEN_STRINGS: [i32; 6] = [64, 59, 55, 50, 45, 40];
+
+const IN_TUNE: f64 = 63.0;
+const CENTS_PER_STEP: f64 = 50.0 / 63.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TunerConfig {
+    /// The frequency of A4 in Hz.
+    pub reference_pitch: f64,
+    /// How far off a note may be, in cents, and still count as in tune.
+    pub tolerance_cents: f64,
+}
+
+impl Default for TunerConfig {
+    fn default() -> Self {
+        TunerConfig {
+            reference_pitch: 440.0,
+            tolerance_cents: 3.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TunerReading {
+    pub note_name: &'static str,
+    /// Scientific pitch notation octave, guessed from the string being
+    /// played: the octave that puts the note between three semitones below
+    /// the open string (for down-tuned strings) and the eighth fret. A note
+    /// played higher up the neck is reported an octave low, and one tuned
+    /// down further an octave high. `None` when the device doesn't say which
+    /// string it is.
+    pub octave: Option<i32>,
+    pub string_number: u8,
+    /// Negative when flat, positive when sharp.
+    pub cents: f64,
+    pub in_tune: bool,
+    /// The estimated frequency in Hz, when the octave is known.
+    pub frequency: Option<f64>,
+}
+
+impl TunerReading {
+    pub fn new(note: u8, string_number: u8, tuner_data: u8, config: &TunerConfig) -> Self {
+        let semitones_from_a = i32::from(note % 12);
+        let cents = (f64::from(tuner_data) - IN_TUNE) * CENTS_PER_STEP;
+        let midi_note = OPEN_STRINGS
+            .get(usize::from(string_number).wrapping_sub(1))
+            .map(|open| {
+                // The lowest note with this name at or above the open
+                // string, allowing for strings tuned down a few semitones.
+                let lowest = open - 3;
+                let pitch_class = (semitones_from_a + 9) % 12;
+                lowest + (pitch_class - lowest).rem_euclid(12)
+            });
+        TunerReading {
+            note_name: NOTE_NAMES[semitones_from_a as usize],
+            octave: midi_note.map(|n| n / 12 - 1),
+            string_number,
+            cents,
+            in_tune: cents.abs() <= config.tolerance_cents,
+            frequency: midi_note.map(|n| {
+                let semitones = f64::from(n - 69) + cents / 100.0;
+                config.reference_pitch * 2f64.powf(semitones / 12.0)
+            }),
+        }
+    }
+
+    /// Decodes a `TunerInfo` message; anything else gives `None`.
+    pub fn from_message(message: &FractalMessage, config: &TunerConfig) -> Option<Self> {
+        match message {
+            FractalMessage::TunerInfo {
+                note,
+                string_number,
+                tuner_data,
+            } => Some(TunerReading::new(
+                *note,
+                *string_number,
+                *tuner_data,
+                config,
+            )),
+            _ => None,
+        }
+    }
+}